@@ -0,0 +1,374 @@
+//! Binary attachments in tool results
+//!
+//! MCP carries binary content (images, audio) as base64 strings inside JSON.
+//! Encoding inside the plugin and decoding again in the host is wasteful, so
+//! helpers such as [`utils::image_bytes_content`](crate::utils::image_bytes_content)
+//! keep the raw bytes next to the JSON result instead, and the generated
+//! dispatcher decides at the transport edge how to deliver them:
+//!
+//! - If the host sets `"binaryAttachments": true` in the call context, the
+//!   result is returned as a [multipart buffer](#multipart-format): the JSON
+//!   envelope plus the raw attachments. Each content item holding binary data
+//!   carries `"attachment": <index>` instead of the base64 field.
+//! - Otherwise the bytes are base64-encoded into the JSON result, which is
+//!   then identical to what `image_data_content` produces.
+//!
+//! ## Multipart Format
+//!
+//! All integers are little-endian `u64`; offsets are from the buffer start.
+//!
+//! ```text
+//! magic        8 bytes   b"\0mcp-mp1"
+//! json_len     u64
+//! count        u64
+//! index        count × (offset u64, length u64)
+//! json         json_len bytes (UTF-8)
+//! attachments  raw bytes, located through the index
+//! ```
+//!
+//! JSON never starts with a NUL byte, so hosts can tell both formats apart
+//! with [`is_multipart`]. Use [`MultipartResult::decode`] to read one.
+
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+
+/// Prefix identifying a multipart result buffer
+pub const MULTIPART_MAGIC: &[u8; 8] = b"\0mcp-mp1";
+
+/// Call context key through which hosts accept multipart results
+pub const CONTEXT_KEY: &str = "binaryAttachments";
+
+/// A binary attachment collected during a call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// Raw bytes
+    pub data: Vec<u8>,
+    /// Field receiving the base64 data when the attachment is inlined
+    pub inline_key: String,
+}
+
+thread_local! {
+    static COLLECTOR: RefCell<Option<Vec<Attachment>>> = const { RefCell::new(None) };
+}
+
+/// Run `f`, collecting the attachments it creates
+///
+/// Used by the generated dispatchers around each handler call. Outside of
+/// `capture`, attachments are inlined as base64 immediately.
+pub fn capture<R>(f: impl FnOnce() -> R) -> (R, Vec<Attachment>) {
+    // Restores the outer collector, even if `f` panics
+    struct Restore(Option<Vec<Attachment>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            COLLECTOR.with(|c| *c.borrow_mut() = previous);
+        }
+    }
+
+    let restore = Restore(COLLECTOR.with(|c| c.borrow_mut().replace(Vec::new())));
+    let result = f();
+    let attachments = COLLECTOR.with(|c| c.borrow_mut().take());
+    drop(restore);
+    (result, attachments.unwrap_or_default())
+}
+
+/// Store binary data in a JSON object
+///
+/// Inside [`capture`], the data is collected and `object` gets an
+/// `"attachment": <index>` reference. Otherwise it is base64-encoded into
+/// `object[inline_key]` right away.
+pub fn attach(object: &mut Map<String, Value>, inline_key: &str, data: Vec<u8>) {
+    let data = COLLECTOR.with(|c| match c.borrow_mut().as_mut() {
+        Some(attachments) => {
+            object.insert("attachment".to_string(), Value::from(attachments.len()));
+            attachments.push(Attachment {
+                data,
+                inline_key: inline_key.to_string(),
+            });
+            None
+        }
+        None => Some(data),
+    });
+
+    if let Some(data) = data {
+        object.insert(inline_key.to_string(), Value::String(base64_encode(&data)));
+    }
+}
+
+/// Replace attachment references in `value` with base64 data
+///
+/// Used when the host did not accept multipart results.
+pub fn inline_attachments(value: &mut Value, attachments: &[Attachment]) {
+    match value {
+        Value::Object(object) => {
+            let index = object.get("attachment").and_then(Value::as_u64);
+            match index.and_then(|i| attachments.get(i as usize)) {
+                Some(attachment) => {
+                    object.remove("attachment");
+                    object.insert(
+                        attachment.inline_key.clone(),
+                        Value::String(base64_encode(&attachment.data)),
+                    );
+                }
+                None => object
+                    .values_mut()
+                    .for_each(|v| inline_attachments(v, attachments)),
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|v| inline_attachments(v, attachments)),
+        _ => {}
+    }
+}
+
+/// Check whether a result buffer is in the multipart format
+pub fn is_multipart(buffer: &[u8]) -> bool {
+    buffer.starts_with(MULTIPART_MAGIC)
+}
+
+/// A JSON result together with its binary attachments
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultipartResult<'a> {
+    /// The JSON envelope
+    pub json: Value,
+    /// Attachment bytes, by index
+    pub attachments: Vec<&'a [u8]>,
+}
+
+impl<'a> MultipartResult<'a> {
+    /// Encode a JSON result and its attachments into a multipart buffer
+    pub fn encode(json: &Value, attachments: &[Attachment]) -> Vec<u8> {
+        let json = json.to_string();
+        let header_len = MULTIPART_MAGIC.len() + 16 + 16 * attachments.len();
+        let total = header_len
+            + json.len()
+            + attachments.iter().map(|a| a.data.len()).sum::<usize>();
+
+        let mut buffer = Vec::with_capacity(total);
+        buffer.extend_from_slice(MULTIPART_MAGIC);
+        buffer.extend_from_slice(&(json.len() as u64).to_le_bytes());
+        buffer.extend_from_slice(&(attachments.len() as u64).to_le_bytes());
+
+        let mut offset = header_len + json.len();
+        for attachment in attachments {
+            buffer.extend_from_slice(&(offset as u64).to_le_bytes());
+            buffer.extend_from_slice(&(attachment.data.len() as u64).to_le_bytes());
+            offset += attachment.data.len();
+        }
+
+        buffer.extend_from_slice(json.as_bytes());
+        for attachment in attachments {
+            buffer.extend_from_slice(&attachment.data);
+        }
+        buffer
+    }
+
+    /// Decode a multipart buffer without copying the attachments
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use mcp_plugin_api::attachment::{Attachment, MultipartResult};
+    /// use serde_json::json;
+    ///
+    /// let png = Attachment { data: vec![0x89, b'P', b'N', b'G'], inline_key: "imageData".into() };
+    /// let buffer = MultipartResult::encode(&json!({"content": [{"attachment": 0}]}), &[png]);
+    ///
+    /// let decoded = MultipartResult::decode(&buffer).unwrap();
+    /// assert_eq!(decoded.json["content"][0]["attachment"], 0);
+    /// assert_eq!(decoded.attachments[0], &[0x89, b'P', b'N', b'G']);
+    /// ```
+    pub fn decode(buffer: &'a [u8]) -> Result<Self, String> {
+        let mut reader = Reader { buffer, pos: 0 };
+        if reader.bytes(MULTIPART_MAGIC.len())? != MULTIPART_MAGIC {
+            return Err("Not a multipart buffer".to_string());
+        }
+
+        let json_len = reader.number()?;
+        let count = reader.number()?;
+        let mut index = Vec::new();
+        for _ in 0..count {
+            index.push((reader.number()?, reader.number()?));
+        }
+
+        let json = serde_json::from_slice(reader.bytes(json_len)?)
+            .map_err(|e| format!("Invalid multipart JSON: {}", e))?;
+
+        let attachments = index
+            .into_iter()
+            .map(|(offset, len)| {
+                offset
+                    .checked_add(len)
+                    .and_then(|end| buffer.get(offset..end))
+                    .ok_or_else(|| "Attachment out of bounds".to_string())
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(MultipartResult { json, attachments })
+    }
+}
+
+struct Reader<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.buffer.len())
+            .ok_or_else(|| "Truncated multipart buffer".to_string())?;
+        let bytes = &self.buffer[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        let bytes: [u8; 8] = self.bytes(8)?.try_into().expect("8 bytes");
+        usize::try_from(u64::from_le_bytes(bytes)).map_err(|e| e.to_string())
+    }
+}
+
+/// Encode bytes as standard base64 with padding
+///
+/// # Example
+///
+/// ```
+/// use mcp_plugin_api::attachment::base64_encode;
+///
+/// assert_eq!(base64_encode(b"Man"), "TWFu");
+/// assert_eq!(base64_encode(b"Ma"), "TWE=");
+/// ```
+pub fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        encoded.push(ALPHABET[(n >> 18) as usize & 63] as char);
+        encoded.push(ALPHABET[(n >> 12) as usize & 63] as char);
+        encoded.push(if chunk.len() > 1 { ALPHABET[(n >> 6) as usize & 63] as char } else { '=' });
+        encoded.push(if chunk.len() > 2 { ALPHABET[n as usize & 63] as char } else { '=' });
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{audio_bytes_content, image_bytes_content, image_data_content};
+    use serde_json::json;
+
+    /// Decode standard padded base64, the way hosts read inlined data
+    fn base64_decode(encoded: &str) -> Vec<u8> {
+        const ALPHABET: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+        let mut decoded = Vec::new();
+        for chunk in encoded.as_bytes().chunks(4) {
+            let digits: Vec<u32> = chunk
+                .iter()
+                .take_while(|c| **c != b'=')
+                .map(|c| ALPHABET.iter().position(|a| a == c).unwrap() as u32)
+                .collect();
+            let n = digits.iter().fold(0, |n, d| n << 6 | d) << (6 * (4 - digits.len()));
+            decoded.extend(n.to_be_bytes()[1..digits.len()].iter());
+        }
+        decoded
+    }
+
+    fn every_byte() -> Vec<u8> {
+        (0..=255).collect()
+    }
+
+    #[test]
+    fn base64_round_trips_every_length() {
+        let data = every_byte();
+        for len in 0..8 {
+            let encoded = base64_encode(&data[..len]);
+            assert_eq!(encoded.len() % 4, 0);
+            assert_eq!(base64_decode(&encoded), &data[..len]);
+        }
+        assert_eq!(base64_decode(&base64_encode(&data)), data);
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(&[0xfb, 0xff]), "+/8=");
+    }
+
+    #[test]
+    fn bytes_are_inlined_outside_of_capture() {
+        let result = image_bytes_content(every_byte(), "image/png");
+        let image = &result["content"][0];
+
+        assert_eq!(image["mimeType"], "image/png");
+        assert!(image.get("attachment").is_none());
+        assert_eq!(base64_decode(image["imageData"].as_str().unwrap()), every_byte());
+        assert_eq!(
+            result,
+            image_data_content(base64_encode(&every_byte()), Some("image/png".to_string()))
+        );
+    }
+
+    #[test]
+    fn captured_bytes_are_inlined_on_request() {
+        let (mut result, attachments) = capture(|| audio_bytes_content(every_byte(), "audio/wav"));
+        assert_eq!(
+            result,
+            json!({ "content": [{ "type": "audio", "mimeType": "audio/wav", "attachment": 0 }] })
+        );
+        assert_eq!(attachments, [Attachment { data: every_byte(), inline_key: "data".into() }]);
+
+        inline_attachments(&mut result, &attachments);
+        let audio = &result["content"][0];
+        assert_eq!(audio["mimeType"], "audio/wav");
+        assert!(audio.get("attachment").is_none());
+        assert_eq!(base64_decode(audio["data"].as_str().unwrap()), every_byte());
+    }
+
+    #[test]
+    fn multipart_results_keep_bytes_and_mime_types() {
+        let (result, attachments) = capture(|| {
+            let mut result = image_bytes_content(vec![0x89, b'P', b'N', b'G'], "image/png");
+            let audio = audio_bytes_content(every_byte(), "audio/ogg");
+            result["content"].as_array_mut().unwrap().push(audio["content"][0].clone());
+            result
+        });
+
+        let buffer = MultipartResult::encode(&result, &attachments);
+        assert!(is_multipart(&buffer));
+        assert!(!is_multipart(result.to_string().as_bytes()));
+
+        let decoded = MultipartResult::decode(&buffer).unwrap();
+        assert_eq!(decoded.json, result);
+        assert_eq!(decoded.json["content"][0]["mimeType"], "image/png");
+        assert_eq!(decoded.json["content"][1]["mimeType"], "audio/ogg");
+        assert_eq!(decoded.json["content"][1]["attachment"], 1);
+        assert_eq!(decoded.attachments, [&[0x89, b'P', b'N', b'G'][..], &every_byte()[..]]);
+    }
+
+    #[test]
+    fn malformed_multipart_buffers_are_rejected() {
+        let attachment = Attachment { data: vec![1, 2, 3], inline_key: "data".into() };
+        let buffer = MultipartResult::encode(&json!({}), &[attachment]);
+
+        assert_eq!(MultipartResult::decode(b"{}"), Err("Truncated multipart buffer".to_string()));
+        assert_eq!(
+            MultipartResult::decode(b"{\"a\": \"not multipart\"}"),
+            Err("Not a multipart buffer".to_string())
+        );
+        assert_eq!(
+            MultipartResult::decode(&buffer[..buffer.len() - 1]),
+            Err("Attachment out of bounds".to_string())
+        );
+        assert_eq!(
+            MultipartResult::decode(&buffer[..MULTIPART_MAGIC.len() + 8]),
+            Err("Truncated multipart buffer".to_string())
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Caller identity (MCP authorization)
+//!
+//! Hosts that authenticate their users (e.g. through OAuth) pass the caller
+//! to the plugin as `"principal"` in the call context:
+//!
+//! ```json
+//! {
+//!   "principal": {
+//!     "subject": "user-42",
+//!     "tenant": "acme",
+//!     "scopes": ["orders:read", "orders:write"],
+//!     "claims": { "email": "jane@acme.example" }
+//!   }
+//! }
+//! ```
+//!
+//! Only `subject` is required; `scopes` may also be a space-separated string
+//! as in an OAuth `scope` claim. Handlers read it through
+//! [`ToolContext::principal`](crate::ToolContext::principal) instead of
+//! trusting a user id in the arguments.
+//!
+//! Tools declaring [`ToolBuilder::require_scopes`](crate::ToolBuilder::require_scopes)
+//! are only run for a principal holding all of these scopes; the generated
+//! dispatcher returns `PluginStatus::Forbidden` otherwise. The scopes are
+//! listed in the tool's `_meta.requiredScopes`, so hosts can hide tools a
+//! user cannot call.
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+/// Call context key carrying the principal
+pub const CONTEXT_KEY: &str = "principal";
+
+/// The authenticated caller of a tool
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Principal {
+    /// Stable identifier of the user or client (OAuth `sub`)
+    pub subject: String,
+    /// Tenant or organization the caller acts for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Granted scopes
+    #[serde(default, deserialize_with = "deserialize_scopes")]
+    pub scopes: Vec<String>,
+    /// All claims of the token, as provided by the host
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub claims: Value,
+}
+
+impl Principal {
+    /// Create a principal without tenant, scopes or claims
+    pub fn new(subject: impl Into<String>) -> Self {
+        Principal {
+            subject: subject.into(),
+            ..Principal::default()
+        }
+    }
+
+    /// Parse the `"principal"` object of a call context
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        Principal::deserialize(value).map_err(|e| format!("Invalid principal: {}", e))
+    }
+
+    /// Whether the principal was granted a scope
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    /// The scopes of `required` the principal was not granted
+    pub fn missing_scopes<'a>(&self, required: &'a [String]) -> Vec<&'a str> {
+        required
+            .iter()
+            .filter(|scope| !self.has_scope(scope))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Look up a single claim
+    pub fn claim(&self, name: &str) -> Option<&Value> {
+        self.claims.get(name)
+    }
+}
+
+/// Check that a caller holds all required scopes
+///
+/// Returns the error message for the host if not. Calls without a principal
+/// only pass when nothing is required.
+///
+/// # Example
+///
+/// ```
+/// use mcp_plugin_api::auth::{authorize, Principal};
+///
+/// let required = vec!["orders:write".to_string()];
+/// let mut jane = Principal::new("jane");
+/// assert!(authorize(Some(&jane), &required).is_err());
+///
+/// jane.scopes.push("orders:write".to_string());
+/// assert!(authorize(Some(&jane), &required).is_ok());
+/// assert!(authorize(None, &[]).is_ok());
+/// ```
+pub fn authorize(principal: Option<&Principal>, required: &[String]) -> Result<(), String> {
+    if required.is_empty() {
+        return Ok(());
+    }
+
+    let principal = principal.ok_or("Caller is not authenticated")?;
+    let missing = principal.missing_scopes(required);
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Missing required scopes: {}", missing.join(", ")))
+    }
+}
+
+fn deserialize_scopes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Scopes {
+        List(Vec<String>),
+        Text(String),
+    }
+
+    Ok(match Scopes::deserialize(deserializer)? {
+        Scopes::List(scopes) => scopes,
+        Scopes::Text(text) => text.split_whitespace().map(str::to_string).collect(),
+    })
+}
@@ -0,0 +1,480 @@
+//! Result caching for idempotent tools
+//!
+//! Read-only tools such as exchange rate or product lookups are often called
+//! again and again with the same arguments. A tool built with
+//! [`ToolBuilder::cache`](crate::ToolBuilder::cache) keeps its successful
+//! results for a while, keyed by the canonicalized arguments (object keys
+//! sorted, whitespace removed), and answers repeated calls from the cache
+//! without running the handler:
+//!
+//! ```ignore
+//! Tool::builder("exchange_rate", "Get the exchange rate of two currencies")
+//!     .param_string("from", "Source currency", true)
+//!     .param_string("to", "Target currency", true)
+//!     .cache(Duration::from_secs(60), 1000)
+//!     .handler(handle_exchange_rate)
+//! ```
+//!
+//! The lookup happens in place of the handler, so
+//! [required scopes](crate::ToolBuilder::require_scopes) and all
+//! [middleware](crate::middleware) run on cache hits as well. Results are
+//! only shared between calls of the same caller (`subject` and `tenant` of
+//! the [`Principal`](crate::Principal)), so one tenant is never served
+//! another tenant's result. The session is not part of the key: a caller
+//! reconnecting, or working in several sessions at once, keeps hitting the
+//! cache, so handlers of cached tools must not depend on session state.
+//! Errors and streamed calls are not cached.
+//!
+//! Hits and misses are counted per cache ([`ToolCache::stats`]) and in the
+//! tool's [metrics](crate::metrics).
+//!
+//! ## Invalidation
+//!
+//! Tools that change the underlying data drop stale results with
+//! `invalidate_tool_cache` generated by `declare_tools!`, or directly through
+//! [`ToolCache::invalidate`] and [`ToolCache::clear`]. Each
+//! [instance](crate::instance) has its own caches.
+
+use crate::context::ToolContext;
+use crate::encoding::{self, WireEncoding};
+use crate::tool::{ToolArgs, ToolOutput};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Key of a cached result
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Caller and wire encoding the result was produced for
+    scope: String,
+    /// Canonical arguments (see [`canonical_args`])
+    args: String,
+}
+
+struct Entry {
+    output: ToolOutput,
+    expires: Instant,
+    // Position in `Entries::recency`
+    tick: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<CacheKey, Entry>,
+    // Keys by last use, least recently used first
+    recency: BTreeMap<u64, CacheKey>,
+    next_tick: u64,
+}
+
+impl Entries {
+    fn touch(&mut self, key: &CacheKey) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        if let Some(entry) = self.map.get_mut(key) {
+            self.recency.remove(&entry.tick);
+            entry.tick = tick;
+            self.recency.insert(tick, key.clone());
+        }
+    }
+
+    fn remove(&mut self, key: &CacheKey) -> Option<Entry> {
+        let entry = self.map.remove(key)?;
+        self.recency.remove(&entry.tick);
+        Some(entry)
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.recency.clear();
+    }
+}
+
+/// Cache of a tool's results
+pub struct ToolCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    // Tool whose metrics the counters are mirrored to
+    metrics: OnceLock<String>,
+}
+
+/// Counters of a [`ToolCache`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Calls answered from the cache
+    pub hits: u64,
+    /// Calls that ran the handler
+    pub misses: u64,
+    /// Results currently cached, including expired ones not yet removed
+    pub entries: usize,
+}
+
+impl ToolCache {
+    /// Create a cache keeping up to `max_entries` results for `ttl` each
+    ///
+    /// When full, the least recently used result is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_entries` is zero.
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        assert!(max_entries > 0, "Tool cache needs room for at least one entry");
+
+        ToolCache {
+            ttl,
+            max_entries,
+            entries: Mutex::new(Entries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            metrics: OnceLock::new(),
+        }
+    }
+
+    /// How long a result is kept
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Maximum number of cached results
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Cache key of a call
+    ///
+    /// Returns `None` for raw arguments that are not valid JSON; such calls
+    /// bypass the cache.
+    pub fn key(&self, ctx: &ToolContext, args: &ToolArgs<'_>) -> Option<CacheKey> {
+        let args = match args {
+            ToolArgs::Value(value) => canonical_args(value),
+            ToolArgs::Raw(raw) => canonical_args(&serde_json::from_str(raw.get()).ok()?),
+        };
+        Some(CacheKey {
+            scope: scope(encoding::current(), ctx),
+            args,
+        })
+    }
+
+    /// Look up a cached result, counting a hit or a miss
+    pub fn get(&self, key: &CacheKey) -> Option<ToolOutput> {
+        let now = Instant::now();
+        let output = {
+            let mut entries = self.entries();
+            match entries.map.get(key) {
+                Some(entry) if entry.expires > now => {
+                    let output = entry.output.clone();
+                    entries.touch(key);
+                    Some(output)
+                }
+                Some(_) => {
+                    entries.remove(key);
+                    None
+                }
+                None => None,
+            }
+        };
+
+        let hit = output.is_some();
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        if let Some(tool) = self.metrics.get() {
+            crate::metrics::record_cache(tool, hit);
+        }
+        output
+    }
+
+    /// Store a result, dropping the least recently used one if full
+    pub fn insert(&self, key: CacheKey, output: ToolOutput) {
+        let mut entries = self.entries();
+
+        if entries.remove(&key).is_none() && entries.map.len() >= self.max_entries {
+            let oldest = entries.recency.first_key_value().map(|(_, key)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+
+        let tick = entries.next_tick;
+        entries.next_tick += 1;
+        entries.recency.insert(tick, key.clone());
+        entries.map.insert(
+            key,
+            Entry {
+                output,
+                expires: Instant::now() + self.ttl,
+                tick,
+            },
+        );
+    }
+
+    /// Drop the cached results for these arguments, for all callers
+    ///
+    /// Returns `false` if none was cached.
+    pub fn invalidate(&self, args: &Value) -> bool {
+        let args = canonical_args(args);
+        let mut entries = self.entries();
+        let stale: Vec<CacheKey> = entries
+            .map
+            .keys()
+            .filter(|key| key.args == args)
+            .cloned()
+            .collect();
+        for key in &stale {
+            entries.remove(key);
+        }
+        !stale.is_empty()
+    }
+
+    /// Drop all cached results
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    /// Hit and miss counters and the number of cached results
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries().map.len(),
+        }
+    }
+
+    /// Count hits and misses in the [metrics](crate::metrics) of `tool` too
+    ///
+    /// Called by the dispatcher of `declare_tools!` with the listed tool
+    /// name. Only the first call has an effect.
+    pub fn report_metrics(&self, tool: &str) {
+        self.metrics.get_or_init(|| tool.to_string());
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl std::fmt::Debug for ToolCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolCache")
+            .field("ttl", &self.ttl)
+            .field("max_entries", &self.max_entries)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// Caller and wire encoding of a call
+///
+/// Typed results are cached in the selected wire encoding, so it is part of
+/// the scope as well. Sessions are left out on purpose (see the module
+/// docs).
+fn scope(encoding: WireEncoding, ctx: &ToolContext) -> String {
+    let principal = ctx.principal();
+    let scope = serde_json::json!([
+        encoding as u32,
+        principal.map(|p| p.subject.as_str()),
+        principal.and_then(|p| p.tenant.as_deref()),
+    ]);
+    scope.to_string()
+}
+
+/// Canonical form of tool arguments, used in cache keys
+///
+/// Object keys are sorted, so arguments differing only in key order or
+/// whitespace share a key.
+///
+/// # Example
+///
+/// ```
+/// use mcp_plugin_api::cache::canonical_args;
+/// use serde_json::json;
+///
+/// assert_eq!(
+///     canonical_args(&json!({ "to": "EUR", "from": "USD" })),
+///     r#"{"from":"USD","to":"EUR"}"#,
+/// );
+/// ```
+pub fn canonical_args(args: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(args, &mut out);
+    out
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut fields: Vec<_> = map.iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+
+            out.push('{');
+            for (i, (name, value)) in fields.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::from(name.as_str()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Principal;
+    use serde_json::json;
+
+    fn output(n: i64) -> ToolOutput {
+        ToolOutput::Value(json!(n), Vec::new())
+    }
+
+    fn value(output: Option<ToolOutput>) -> Option<Value> {
+        match output? {
+            ToolOutput::Value(value, _) => Some(value),
+            ToolOutput::Encoded(_) => None,
+        }
+    }
+
+    fn key(cache: &ToolCache, ctx: &ToolContext, args: Value) -> CacheKey {
+        cache.key(ctx, &ToolArgs::Value(args)).unwrap()
+    }
+
+    #[test]
+    fn key_ignores_argument_order() {
+        let cache = ToolCache::new(Duration::from_secs(60), 10);
+        let ctx = ToolContext::new();
+        assert_eq!(
+            key(&cache, &ctx, json!({ "a": 1, "b": [{ "y": 2, "x": 1 }] })),
+            key(&cache, &ctx, json!({ "b": [{ "x": 1, "y": 2 }], "a": 1 })),
+        );
+    }
+
+    #[test]
+    fn key_separates_callers() {
+        let cache = ToolCache::new(Duration::from_secs(60), 10);
+        let args = json!({ "id": 1 });
+        let alice = ToolContext::new().with_principal(Principal::new("alice"));
+        let mut tenant_b = Principal::new("alice");
+        tenant_b.tenant = Some("b".to_string());
+        let tenant_b = ToolContext::new().with_principal(tenant_b);
+
+        let keys = [
+            key(&cache, &ToolContext::new(), args.clone()),
+            key(&cache, &alice, args.clone()),
+            key(&cache, &tenant_b, args.clone()),
+        ];
+        for (i, a) in keys.iter().enumerate() {
+            for b in &keys[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+
+        cache.insert(keys[1].clone(), output(1));
+        assert_eq!(value(cache.get(&keys[1])), Some(json!(1)));
+        assert_eq!(value(cache.get(&keys[2])), None);
+    }
+
+    #[test]
+    fn key_is_shared_across_sessions() {
+        let cache = ToolCache::new(Duration::from_secs(60), 10);
+        let args = json!({ "id": 1 });
+        let in_session = |session: &str| {
+            let ctx = ToolContext::from_json(json!({ "sessionId": session }));
+            ctx.with_principal(Principal::new("alice"))
+        };
+
+        let first = key(&cache, &in_session("s1"), args.clone());
+        assert_eq!(first, key(&cache, &in_session("s2"), args.clone()));
+        assert_eq!(
+            key(&cache, &ToolContext::from_json(json!({ "sessionId": "s1" })), args.clone()),
+            key(&cache, &ToolContext::new(), args)
+        );
+
+        cache.insert(first.clone(), output(1));
+        assert_eq!(value(cache.get(&first)), Some(json!(1)));
+    }
+
+    #[test]
+    fn scope_includes_wire_encoding() {
+        let ctx = ToolContext::new();
+        assert_ne!(
+            scope(WireEncoding::Json, &ctx),
+            scope(WireEncoding::Cbor, &ctx)
+        );
+    }
+
+    #[test]
+    fn entries_expire() {
+        let cache = ToolCache::new(Duration::from_millis(20), 10);
+        let key = key(&cache, &ToolContext::new(), json!({}));
+        cache.insert(key.clone(), output(1));
+        assert_eq!(value(cache.get(&key)), Some(json!(1)));
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(value(cache.get(&key)), None);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                entries: 0
+            }
+        );
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let cache = ToolCache::new(Duration::from_secs(60), 2);
+        let ctx = ToolContext::new();
+        let [a, b, c] = [1, 2, 3].map(|n| key(&cache, &ctx, json!({ "n": n })));
+
+        cache.insert(a.clone(), output(1));
+        cache.insert(b.clone(), output(2));
+        assert!(cache.get(&a).is_some());
+        cache.insert(c.clone(), output(3));
+
+        assert!(cache.get(&a).is_some());
+        assert!(cache.get(&b).is_none());
+        assert!(cache.get(&c).is_some());
+        assert_eq!(cache.stats().entries, 2);
+    }
+
+    #[test]
+    fn invalidate_drops_results_of_all_callers() {
+        let cache = ToolCache::new(Duration::from_secs(60), 10);
+        let alice = ToolContext::new().with_principal(Principal::new("alice"));
+        let bob = ToolContext::new().with_principal(Principal::new("bob"));
+        let keys = [
+            key(&cache, &alice, json!({ "id": 1 })),
+            key(&cache, &bob, json!({ "id": 1 })),
+            key(&cache, &bob, json!({ "id": 2 })),
+        ];
+        for (n, key) in keys.iter().enumerate() {
+            cache.insert(key.clone(), output(n as i64));
+        }
+
+        assert!(cache.invalidate(&json!({ "id": 1 })));
+        assert!(!cache.invalidate(&json!({ "id": 1 })));
+        assert_eq!(cache.stats().entries, 1);
+        assert!(cache.get(&keys[2]).is_some());
+
+        cache.clear();
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
@@ -0,0 +1,416 @@
+//! API version compatibility checking
+//!
+//! Hosts use this module to decide whether a loaded plugin can be used,
+//! based on the API version recorded in its [`PluginDeclaration`](crate::PluginDeclaration).
+//!
+//! ## Rules
+//!
+//! The crate follows semantic versioning, with the usual `0.x` twist that the
+//! minor version acts as the breaking component:
+//!
+//! | Plugin  | Host    | Result                                    |
+//! |---------|---------|-------------------------------------------|
+//! | `0.1.x` | `0.1.y` | Compatible if `y >= x`, incompatible otherwise |
+//! | `0.1.x` | `0.2.y` | Incompatible                              |
+//! | `1.a.x` | `1.b.y` | Compatible if `(b, y) >= (a, x)`, incompatible otherwise |
+//! | `1.a.x` | `2.b.y` | Incompatible                              |
+//!
+//! A host older than the plugin is rejected: the plugin may rely on features
+//! the host does not provide, even though the host could still read its
+//! append-only declaration (see [`PluginDeclaration::read`](crate::PluginDeclaration::read)).
+//! Pre-release versions only match themselves exactly; anything else is
+//! reported as a warning.
+//!
+//! ## Example
+//!
+//! ```
+//! use mcp_plugin_api::compat::{check_compatibility, ApiVersion, Compatibility};
+//!
+//! let plugin: ApiVersion = "0.1.2".parse().unwrap();
+//! assert_eq!(check_compatibility(&plugin, &"0.1.5".parse().unwrap()), Compatibility::Compatible);
+//! assert!(!check_compatibility(&plugin, &"0.1.0".parse().unwrap()).is_compatible());
+//! assert!(!check_compatibility(&plugin, &"0.2.0".parse().unwrap()).is_compatible());
+//! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// A parsed `MAJOR.MINOR.PATCH[-PRERELEASE][+BUILD]` version
+///
+/// Build metadata is accepted but ignored, as required by semver.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ApiVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// Pre-release identifier (the part after `-`), if any
+    pub pre: Option<String>,
+}
+
+impl ApiVersion {
+    /// Create a release version
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        ApiVersion {
+            major,
+            minor,
+            patch,
+            pre: None,
+        }
+    }
+
+    /// The version of this crate, i.e. [`API_VERSION`](crate::API_VERSION)
+    pub fn current() -> Self {
+        crate::API_VERSION
+            .parse()
+            .expect("crate version is valid semver")
+    }
+
+    /// Parse a version string
+    pub fn parse(s: &str) -> Result<Self, VersionParseError> {
+        let err = || VersionParseError(s.to_string());
+
+        // Build metadata carries no precedence, drop it
+        let s_no_build = match s.split_once('+') {
+            Some((v, build)) if !build.is_empty() => v,
+            Some(_) => return Err(err()),
+            None => s,
+        };
+
+        let (core, pre) = match s_no_build.split_once('-') {
+            Some((core, pre)) if !pre.is_empty() => (core, Some(pre.to_string())),
+            Some(_) => return Err(err()),
+            None => (s_no_build, None),
+        };
+
+        let mut parts = core.split('.');
+        let mut next = || -> Result<u64, VersionParseError> {
+            let part = parts.next().ok_or_else(err)?;
+            // Numeric identifiers must not have leading zeros
+            if part.is_empty() || (part.len() > 1 && part.starts_with('0')) {
+                return Err(err());
+            }
+            part.parse().map_err(|_| err())
+        };
+
+        let version = ApiVersion {
+            major: next()?,
+            minor: next()?,
+            patch: next()?,
+            pre,
+        };
+
+        if parts.next().is_some() {
+            return Err(err());
+        }
+
+        Ok(version)
+    }
+
+    /// The components that decide compatibility under the 0.x rules
+    ///
+    /// Returns `(breaking, feature)` pairs: for `0.x` versions the minor
+    /// number is breaking and the patch number adds features.
+    fn significance(&self) -> ((u64, u64), (u64, u64)) {
+        if self.major == 0 {
+            ((0, self.minor), (self.patch, 0))
+        } else {
+            ((self.major, 0), (self.minor, self.patch))
+        }
+    }
+}
+
+impl FromStr for ApiVersion {
+    type Err = VersionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ApiVersion::parse(s)
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(pre) = &self.pre {
+            write!(f, "-{}", pre)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when a version string is not valid semver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionParseError(pub String);
+
+impl fmt::Display for VersionParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid API version: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for VersionParseError {}
+
+/// Result of a compatibility check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Compatibility {
+    /// The plugin can be used without restrictions
+    Compatible,
+    /// The plugin can be used, but the host should report the warnings
+    CompatibleWithWarnings(Vec<CompatWarning>),
+    /// The plugin must not be loaded
+    Incompatible(Incompatibility),
+}
+
+impl Compatibility {
+    /// Whether the plugin can be used (with or without warnings)
+    pub fn is_compatible(&self) -> bool {
+        !matches!(self, Compatibility::Incompatible(_))
+    }
+}
+
+/// Non-fatal compatibility issue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompatWarning {
+    /// One side uses a pre-release version that does not exactly match the other
+    PreRelease { plugin: ApiVersion, host: ApiVersion },
+}
+
+impl fmt::Display for CompatWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompatWarning::PreRelease { plugin, host } => write!(
+                f,
+                "pre-release API versions do not match (plugin {}, host {})",
+                plugin, host
+            ),
+        }
+    }
+}
+
+/// Fatal compatibility issue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incompatibility {
+    /// The breaking component differs (major, or minor for `0.x`)
+    BreakingChange { plugin: ApiVersion, host: ApiVersion },
+    /// The plugin was built against a newer API than the host provides
+    HostOlder { plugin: ApiVersion, host: ApiVersion },
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Incompatibility::BreakingChange { plugin, host } => write!(
+                f,
+                "plugin built against API {} is incompatible with host API {}",
+                plugin, host
+            ),
+            Incompatibility::HostOlder { plugin, host } => write!(
+                f,
+                "plugin built against API {} needs a host providing at least that version, host provides {}",
+                plugin, host
+            ),
+        }
+    }
+}
+
+/// Check whether a plugin built against `plugin` can be loaded by a host using `host`
+///
+/// See the [module documentation](self) for the rules.
+pub fn check_compatibility(plugin: &ApiVersion, host: &ApiVersion) -> Compatibility {
+    let (plugin_breaking, plugin_feature) = plugin.significance();
+    let (host_breaking, host_feature) = host.significance();
+
+    if plugin_breaking != host_breaking {
+        return Compatibility::Incompatible(Incompatibility::BreakingChange {
+            plugin: plugin.clone(),
+            host: host.clone(),
+        });
+    }
+
+    if plugin_feature.cmp(&host_feature) == Ordering::Greater {
+        return Compatibility::Incompatible(Incompatibility::HostOlder {
+            plugin: plugin.clone(),
+            host: host.clone(),
+        });
+    }
+
+    let mut warnings = Vec::new();
+
+    if (plugin.pre.is_some() || host.pre.is_some()) && plugin != host {
+        warnings.push(CompatWarning::PreRelease {
+            plugin: plugin.clone(),
+            host: host.clone(),
+        });
+    }
+
+    if warnings.is_empty() {
+        Compatibility::Compatible
+    } else {
+        Compatibility::CompatibleWithWarnings(warnings)
+    }
+}
+
+/// Error returned when a plugin's declared API version cannot be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginVersionError {
+    /// The declaration or its `api_version` is null
+    Missing,
+    /// The `api_version` string is not valid semver
+    Invalid(VersionParseError),
+}
+
+impl fmt::Display for PluginVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginVersionError::Missing => write!(f, "plugin declares no API version"),
+            PluginVersionError::Invalid(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PluginVersionError {}
+
+impl From<VersionParseError> for PluginVersionError {
+    fn from(e: VersionParseError) -> Self {
+        PluginVersionError::Invalid(e)
+    }
+}
+
+/// Check a loaded plugin's declaration against this crate's API version
+///
+/// Convenience wrapper for hosts: reads the plugin's `api_version` string and
+/// compares it with [`ApiVersion::current`]. Only `api_version` is read,
+/// which is the first field in every declaration layout, so this is safe to
+/// call on declarations of any version before
+/// [`PluginDeclaration::read`](crate::PluginDeclaration::read).
+///
+/// Returns [`PluginVersionError::Missing`] if `decl` or its `api_version` is
+/// null.
+///
+/// # Safety
+///
+/// `decl` must be null or point to a `plugin_declaration` static exported by
+/// a plugin, whose `api_version` is null or a valid null-terminated string.
+///
+/// # Example
+///
+/// ```ignore
+/// let symbol: *const PluginDeclaration = *library.get(b"plugin_declaration")?;
+/// if !unsafe { compat::check_plugin(symbol)? }.is_compatible() {
+///     return Err("incompatible plugin".into());
+/// }
+/// ```
+pub unsafe fn check_plugin(
+    decl: *const crate::PluginDeclaration,
+) -> Result<Compatibility, PluginVersionError> {
+    if decl.is_null() {
+        return Err(PluginVersionError::Missing);
+    }
+
+    // Never create a reference: older declarations are shorter than ours
+    let api_version = std::ptr::addr_of!((*decl).api_version).read();
+    if api_version.is_null() {
+        return Err(PluginVersionError::Missing);
+    }
+
+    let raw = std::ffi::CStr::from_ptr(api_version as *const std::os::raw::c_char);
+    let plugin = ApiVersion::parse(&raw.to_string_lossy())?;
+    Ok(check_compatibility(&plugin, &ApiVersion::current()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(plugin: &str, host: &str) -> Compatibility {
+        check_compatibility(&plugin.parse().unwrap(), &host.parse().unwrap())
+    }
+
+    #[test]
+    fn rules_table() {
+        let table = [
+            // plugin,        host,           compatible, warnings
+            ("0.1.0",         "0.1.0",        true,  false),
+            ("0.1.0",         "0.1.5",        true,  false),
+            ("0.1.5",         "0.1.0",        false, false),
+            ("0.1.0",         "0.2.0",        false, false),
+            ("0.2.0",         "0.1.9",        false, false),
+            ("0.1.0",         "1.0.0",        false, false),
+            ("1.0.0",         "1.4.2",        true,  false),
+            ("1.4.2",         "1.4.1",        false, false),
+            ("1.5.0",         "1.4.9",        false, false),
+            ("1.0.0",         "2.0.0",        false, false),
+            ("0.1.0-beta.1",  "0.1.0-beta.1", true,  false),
+            ("0.1.0-beta.1",  "0.1.0",        true,  true),
+            ("0.1.0+build.7", "0.1.0",        true,  false),
+        ];
+
+        for (plugin, host, compatible, warnings) in table {
+            let result = check(plugin, host);
+            assert_eq!(result.is_compatible(), compatible, "{} on {}", plugin, host);
+            assert_eq!(
+                matches!(result, Compatibility::CompatibleWithWarnings(_)),
+                warnings,
+                "{} on {}",
+                plugin,
+                host
+            );
+        }
+    }
+
+    #[test]
+    fn older_host_is_reported_as_such() {
+        assert!(matches!(
+            check("0.1.5", "0.1.0"),
+            Compatibility::Incompatible(Incompatibility::HostOlder { .. })
+        ));
+        assert!(matches!(
+            check("0.2.0", "0.1.9"),
+            Compatibility::Incompatible(Incompatibility::BreakingChange { .. })
+        ));
+    }
+
+    #[test]
+    fn declared_version_is_checked() {
+        let decl = crate::tests::declaration();
+        assert_eq!(unsafe { check_plugin(&decl) }, Ok(Compatibility::Compatible));
+
+        let decl = crate::PluginDeclaration {
+            api_version: std::ptr::null(),
+            ..decl
+        };
+        assert_eq!(unsafe { check_plugin(&decl) }, Err(PluginVersionError::Missing));
+
+        let decl = crate::PluginDeclaration {
+            api_version: c"0.1".as_ptr() as *const u8,
+            ..decl
+        };
+        assert!(matches!(
+            unsafe { check_plugin(&decl) },
+            Err(PluginVersionError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn declarations_of_any_size_are_checked() {
+        // Only api_version is read, so a single field is enough
+        let old: [*const u8; 1] = [c"0.1.0".as_ptr() as *const u8];
+        let result = unsafe { check_plugin(old.as_ptr() as *const crate::PluginDeclaration) };
+        assert!(matches!(
+            result,
+            Ok(Compatibility::Incompatible(Incompatibility::BreakingChange { .. }))
+        ));
+
+        assert_eq!(
+            unsafe { check_plugin(std::ptr::null()) },
+            Err(PluginVersionError::Missing)
+        );
+    }
+
+    #[test]
+    fn invalid_versions_are_rejected() {
+        for bad in ["", "1", "1.2", "1.2.x", "01.2.3", "1.2.3.4", "1.2.3-", "1.2.3+"] {
+            assert!(bad.parse::<ApiVersion>().is_err(), "{:?}", bad);
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! Argument completion (MCP `completion/complete`)
+//!
+//! Clients ask for completions of a single argument while the user is typing.
+//! Providers are attached to the arguments they complete:
+//!
+//! - Tool parameters: [`ToolBuilder::complete`](crate::ToolBuilder::complete)
+//! - Prompt arguments: [`PromptBuilder::complete`](crate::PromptBuilder::complete)
+//! - Template variables: [`ResourceTemplateBuilder::complete`](crate::ResourceTemplateBuilder::complete)
+//!
+//! ## Request
+//!
+//! The [`CompleteFn`](crate::CompleteFn) receives the MCP request parameters:
+//! ```json
+//! {
+//!   "ref": { "type": "ref/prompt", "name": "summarize_order" },
+//!   "argument": { "name": "order_id", "value": "A-1" }
+//! }
+//! ```
+//!
+//! `ref` is one of `{"type": "ref/prompt", "name": ...}`,
+//! `{"type": "ref/resource", "uri": <template>}` or, as an extension of the
+//! MCP spec for tool parameters, `{"type": "ref/tool", "name": ...}`.
+//! Tools of a namespaced plugin can be referenced by their listed
+//! (prefixed) name or by their short name.
+//!
+//! ## Result
+//!
+//! ```json
+//! {
+//!   "completion": { "values": ["A-100", "A-101"], "total": 2, "hasMore": false }
+//! }
+//! ```
+
+use serde_json::{json, Value};
+
+/// Completion provider function type
+///
+/// Receives the partial value typed so far and returns ranked suggestions,
+/// best match first. [`Completion::from_candidates`] implements a sensible
+/// default ranking.
+pub type CompletionProvider = fn(&str) -> Completion;
+
+/// Ranked completion values for one argument
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Completion {
+    /// Suggested values, best match first (at most [`Completion::MAX_VALUES`])
+    pub values: Vec<String>,
+    /// Total number of matches, if known (may exceed `values.len()`)
+    pub total: Option<usize>,
+    /// Whether more matches exist than were returned
+    pub has_more: bool,
+}
+
+impl Completion {
+    /// Maximum number of values in a response, as required by the MCP spec
+    pub const MAX_VALUES: usize = 100;
+
+    /// A completion without suggestions
+    pub fn empty() -> Self {
+        Completion::default()
+    }
+
+    /// Create a completion from already ranked values
+    ///
+    /// Values beyond [`Completion::MAX_VALUES`] are dropped; `total` and
+    /// `has_more` are set accordingly.
+    pub fn new(mut values: Vec<String>) -> Self {
+        let total = values.len();
+        values.truncate(Self::MAX_VALUES);
+
+        Completion {
+            has_more: total > values.len(),
+            total: Some(total),
+            values,
+        }
+    }
+
+    /// Filter and rank candidates against a partial value
+    ///
+    /// Matching is case-insensitive. Candidates starting with `partial` rank
+    /// before candidates merely containing it; within each group the original
+    /// order is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use mcp_plugin_api::Completion;
+    ///
+    /// let completion = Completion::from_candidates("py", ["ruby", "happy", "Python", "pypy"]);
+    /// assert_eq!(completion.values, ["Python", "pypy", "happy"]);
+    /// assert_eq!(completion.total, Some(3));
+    /// assert!(!completion.has_more);
+    /// ```
+    pub fn from_candidates<I, S>(partial: &str, candidates: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let needle = partial.to_lowercase();
+        let mut prefix_matches = Vec::new();
+        let mut other_matches = Vec::new();
+
+        for candidate in candidates {
+            let candidate = candidate.into();
+            let haystack = candidate.to_lowercase();
+
+            if haystack.starts_with(&needle) {
+                prefix_matches.push(candidate);
+            } else if haystack.contains(&needle) {
+                other_matches.push(candidate);
+            }
+        }
+
+        prefix_matches.extend(other_matches);
+        Completion::new(prefix_matches)
+    }
+
+    /// Convert to the MCP `completion/complete` result format
+    pub fn to_json(&self) -> Value {
+        let mut completion = json!({
+            "values": self.values,
+            "hasMore": self.has_more
+        });
+
+        if let Some(total) = self.total {
+            completion["total"] = json!(total);
+        }
+
+        json!({ "completion": completion })
+    }
+}
+
+/// What a completion request refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletionRef {
+    /// A prompt argument (`ref/prompt`)
+    Prompt(String),
+    /// A resource template variable (`ref/resource`, by template string)
+    ResourceTemplate(String),
+    /// A tool parameter (`ref/tool`, extension of the MCP spec)
+    Tool(String),
+}
+
+/// A parsed `completion/complete` request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionRequest {
+    pub reference: CompletionRef,
+    /// Name of the argument being completed
+    pub argument: String,
+    /// Partial value typed so far
+    pub value: String,
+}
+
+impl CompletionRequest {
+    /// Parse the MCP request parameters
+    pub fn from_json(params: &Value) -> Result<Self, String> {
+        let reference = &params["ref"];
+        let field = |name: &str| {
+            reference[name]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("Completion reference is missing '{}'", name))
+        };
+
+        let reference = match reference["type"].as_str() {
+            Some("ref/prompt") => CompletionRef::Prompt(field("name")?),
+            Some("ref/resource") => CompletionRef::ResourceTemplate(field("uri")?),
+            Some("ref/tool") => CompletionRef::Tool(field("name")?),
+            Some(other) => return Err(format!("Unknown completion reference type: {}", other)),
+            None => return Err("Completion request is missing 'ref.type'".to_string()),
+        };
+
+        let argument = params["argument"]["name"]
+            .as_str()
+            .ok_or("Completion request is missing 'argument.name'")?
+            .to_string();
+        let value = params["argument"]["value"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+
+        Ok(CompletionRequest {
+            reference,
+            argument,
+            value,
+        })
+    }
+}
+
+/// A collection of declarations that may carry completion providers
+///
+/// Implemented for the registries generated by `declare_tools!`,
+/// `declare_prompts!` and `declare_resources!`, so that
+/// `declare_completions!` can search them.
+pub trait CompletionSource {
+    /// Look up the provider for an argument of the referenced declaration
+    ///
+    /// Returns `None` if this source does not contain the referenced
+    /// declaration, and `Some(None)` if it does but the argument has no
+    /// provider.
+    fn find_completion(
+        &self,
+        reference: &CompletionRef,
+        argument: &str,
+    ) -> Option<Option<CompletionProvider>>;
+}
+
+/// Tools are found by the name they are listed under and, for a namespaced
+/// registry, also by their short name.
+impl CompletionSource for crate::registry::ToolRegistry {
+    fn find_completion(
+        &self,
+        reference: &CompletionRef,
+        argument: &str,
+    ) -> Option<Option<CompletionProvider>> {
+        match reference {
+            CompletionRef::Tool(name) => self
+                .resolve(name)
+                .or_else(|| self.get(name))
+                .map(|tool| tool.completion_provider(argument)),
+            _ => None,
+        }
+    }
+}
+
+impl CompletionSource for &[crate::prompt::Prompt] {
+    fn find_completion(
+        &self,
+        reference: &CompletionRef,
+        argument: &str,
+    ) -> Option<Option<CompletionProvider>> {
+        match reference {
+            CompletionRef::Prompt(name) => self
+                .iter()
+                .find(|prompt| &prompt.name == name)
+                .map(|prompt| prompt.completion_provider(argument)),
+            _ => None,
+        }
+    }
+}
+
+impl CompletionSource for crate::resource::ResourceRouter {
+    fn find_completion(
+        &self,
+        reference: &CompletionRef,
+        argument: &str,
+    ) -> Option<Option<CompletionProvider>> {
+        match reference {
+            CompletionRef::ResourceTemplate(uri) => self
+                .templates()
+                .iter()
+                .find(|template| template.uri_template.as_str() == uri)
+                .map(|template| template.completion_provider(argument)),
+            _ => None,
+        }
+    }
+}
+
+/// Answer a completion request from a provider lookup
+///
+/// `found` is the combined result of [`CompletionSource::find_completion`]
+/// over all sources. Unknown declarations are errors; arguments without a
+/// provider complete to nothing.
+pub fn complete(
+    request: &CompletionRequest,
+    found: Option<Option<CompletionProvider>>,
+) -> Result<Completion, String> {
+    match found {
+        Some(Some(provider)) => {
+            let mut completion = provider(&request.value);
+            if completion.values.len() > Completion::MAX_VALUES {
+                completion.values.truncate(Completion::MAX_VALUES);
+                completion.has_more = true;
+            }
+            Ok(completion)
+        }
+        Some(None) => Ok(Completion::empty()),
+        None => Err(unknown_reference(&request.reference)),
+    }
+}
+
+/// Error message for a completion reference no registry knows
+pub fn unknown_reference(reference: &CompletionRef) -> String {
+    match reference {
+        CompletionRef::Prompt(name) => format!("Unknown prompt: {}", name),
+        CompletionRef::ResourceTemplate(uri) => format!("Unknown resource template: {}", uri),
+        CompletionRef::Tool(name) => format!("Unknown tool: {}", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::ToolRegistry;
+    use crate::tool::Tool;
+
+    fn handle_noop(_args: &Value) -> Result<Value, String> {
+        Ok(Value::Null)
+    }
+
+    fn complete_sku(partial: &str) -> Completion {
+        Completion::from_candidates(partial, ["SKU-100", "SKU-200"])
+    }
+
+    #[test]
+    fn namespaced_tools_complete_under_both_names() {
+        let tools = ToolRegistry::new(vec![Tool::builder("get_price", "Get a price")
+            .param_string("sku", "Product SKU", true)
+            .param_string("currency", "Currency", false)
+            .complete("sku", complete_sku)
+            .handler(handle_noop)])
+        .with_namespace("orders");
+        let find = |name: &str, argument: &str| {
+            tools.find_completion(&CompletionRef::Tool(name.to_string()), argument)
+        };
+
+        assert!(matches!(find("orders__get_price", "sku"), Some(Some(_))));
+        assert!(matches!(find("get_price", "sku"), Some(Some(_))));
+        assert!(matches!(find("get_price", "currency"), Some(None)));
+        assert!(find("other__get_price", "sku").is_none());
+        assert!(find("unknown", "sku").is_none());
+    }
+}
@@ -0,0 +1,353 @@
+//! Per-call context for tool handlers
+//!
+//! A [`ToolContext`] is created by the generated dispatcher for every tool
+//! call and handed to handlers registered with
+//! [`ToolBuilder::handler_with_context`](crate::ToolBuilder::handler_with_context).
+//! It carries the call context JSON provided by the host and gives access to
+//! the host callbacks (see [`crate::host`]).
+//!
+//! ## Deadlines
+//!
+//! The host can limit how long a call may run with `"deadlineMs"` in the call
+//! context (milliseconds from the start of the call); tools can set their own
+//! limit with [`ToolBuilder::timeout`](crate::ToolBuilder::timeout). The
+//! earlier of both applies. When it passes, the dispatcher returns
+//! `PluginStatus::Timeout` to the host right away and cancels the call's
+//! [`CancellationToken`]; long-running handlers should check
+//! [`ToolContext::check_cancelled`] regularly and stop early.
+//!
+//! ## Caller
+//!
+//! A `"principal"` entry identifies the authenticated caller; see
+//! [`crate::auth`]. A `"sessionId"` entry gives access to the per-session
+//! store; see [`crate::session`].
+//!
+//! ## Tracing
+//!
+//! `"traceparent"` and `"tracestate"` carry the W3C trace context of the
+//! request; see [`crate::trace`].
+
+use crate::auth::{self, Principal};
+use crate::elicitation::{ElicitationResponse, ElicitationSchema};
+use crate::error::ToolError;
+use crate::host;
+use crate::instance::Instance;
+use crate::sampling::{SamplingMessage, SamplingParams, SamplingResult};
+use crate::session::{self, Session};
+use crate::trace::TraceContext;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Call context key carrying the host's deadline
+pub const DEADLINE_KEY: &str = "deadlineMs";
+
+/// Shared flag telling a handler that its call was cancelled
+///
+/// Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancellationState>,
+}
+
+#[derive(Debug, Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    // Held while a streamed chunk is written, so that no chunk reaches the
+    // host after `cancel` returned
+    gate: Mutex<()>,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Cancel the call
+    ///
+    /// Waits for a chunk write in progress to finish; afterwards no further
+    /// chunks are sent.
+    pub fn cancel(&self) {
+        let _gate = self.gate();
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether the call was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn gate(&self) -> MutexGuard<'_, ()> {
+        self.inner.gate.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Context of a single tool call
+#[derive(Debug, Clone, Default)]
+pub struct ToolContext {
+    raw: Value,
+    deadline: Option<Instant>,
+    cancellation: CancellationToken,
+    principal: Option<Principal>,
+    instance: Option<Arc<Instance>>,
+    trace: Option<TraceContext>,
+}
+
+impl ToolContext {
+    /// Create an empty context
+    ///
+    /// Used for calls through the plain `execute_tool` function and in tests.
+    pub fn new() -> Self {
+        ToolContext::default()
+    }
+
+    /// Create a context from the JSON object passed by the host
+    ///
+    /// A `"deadlineMs"` entry is converted into a [`deadline`](Self::deadline)
+    /// relative to now. An invalid `"principal"` is ignored, so the call is
+    /// treated as unauthenticated; an invalid `"traceparent"` is ignored too.
+    pub fn from_json(raw: Value) -> Self {
+        let deadline = raw[DEADLINE_KEY]
+            .as_u64()
+            .map(|ms| Instant::now() + Duration::from_millis(ms));
+        let principal = raw
+            .get(auth::CONTEXT_KEY)
+            .and_then(|p| Principal::from_json(p).ok());
+        let trace = TraceContext::from_json(&raw);
+
+        ToolContext {
+            raw,
+            deadline,
+            cancellation: CancellationToken::new(),
+            principal,
+            instance: None,
+            trace,
+        }
+    }
+
+    /// Set the plugin instance the call is made on
+    ///
+    /// Done by the generated `instance_execute_tool` dispatcher.
+    pub fn with_instance(mut self, instance: Arc<Instance>) -> Self {
+        self.instance = Some(instance);
+        self
+    }
+
+    /// The plugin instance the call is made on, if any (see [`crate::instance`])
+    pub fn instance(&self) -> Option<&Arc<Instance>> {
+        self.instance.as_ref()
+    }
+
+    /// Set the authenticated caller
+    ///
+    /// Hosts pass it in the call context; this is mainly useful in tests.
+    pub fn with_principal(mut self, principal: Principal) -> Self {
+        self.principal = Some(principal);
+        self
+    }
+
+    /// The authenticated caller, if the host provided one
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// fn handle_list_orders(ctx: &ToolContext, _args: &Value) -> Result<Value, String> {
+    ///     let caller = ctx.principal().ok_or("Not authenticated")?;
+    ///     let orders = load_orders(caller.tenant.as_deref(), &caller.subject)?;
+    ///     Ok(json_content(orders))
+    /// }
+    /// ```
+    pub fn principal(&self) -> Option<&Principal> {
+        self.principal.as_ref()
+    }
+
+    /// Set the trace context of the request
+    ///
+    /// Hosts pass it in the call context; this is mainly useful in tests.
+    pub fn with_trace(mut self, trace: TraceContext) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// The W3C trace context of the request, if the host provided one
+    ///
+    /// See [`crate::trace`].
+    pub fn trace(&self) -> Option<&TraceContext> {
+        self.trace.as_ref()
+    }
+
+    /// Set the deadline, keeping an earlier existing one
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(self.deadline.map_or(deadline, |d| d.min(deadline)));
+        self
+    }
+
+    /// Point in time by which the call has to finish, if limited
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Time left until the deadline, if limited
+    ///
+    /// Returns `Some(Duration::ZERO)` once the deadline has passed.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|d| d.saturating_duration_since(Instant::now()))
+    }
+
+    /// The cancellation token of this call
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Whether the call was cancelled or its deadline has passed
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled() || self.remaining() == Some(Duration::ZERO)
+    }
+
+    /// Return [`ToolError::Cancelled`] if the call should stop
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// fn handle_reindex(ctx: &ToolContext, _args: &Value) -> Result<Value, String> {
+    ///     for batch in batches()? {
+    ///         ctx.check_cancelled()?;
+    ///         reindex(batch)?;
+    ///     }
+    ///     Ok(text_content("Reindex complete"))
+    /// }
+    /// ```
+    pub fn check_cancelled(&self) -> Result<(), ToolError> {
+        if self.is_cancelled() {
+            Err(ToolError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The call context JSON as passed by the host
+    pub fn raw(&self) -> &Value {
+        &self.raw
+    }
+
+    /// Identifier of the MCP request being served, if the host provided one
+    pub fn request_id(&self) -> Option<&Value> {
+        self.raw.get("requestId")
+    }
+
+    /// The session id provided by the host, if any
+    pub fn session_id(&self) -> Option<&str> {
+        self.raw[session::CONTEXT_KEY].as_str()
+    }
+
+    /// The session this call belongs to
+    ///
+    /// Returns `None` without a session id, and for ids the host has not
+    /// started with `on_session_start` or has already ended (see
+    /// [`crate::session`]).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// fn handle_select_project(ctx: &ToolContext, args: &Value) -> Result<Value, String> {
+    ///     let project = args["project"].as_str().ok_or("Missing project")?;
+    ///     ctx.session().ok_or("No session")?.insert("project", project.to_string());
+    ///     Ok(text_content(format!("Selected {}", project)))
+    /// }
+    /// ```
+    pub fn session(&self) -> Option<Arc<Session>> {
+        self.session_id().and_then(session::get)
+    }
+
+    /// Whether the host accepts results with binary attachments
+    ///
+    /// Set by the host through `"binaryAttachments": true`; see
+    /// [`crate::attachment`].
+    pub fn accepts_attachments(&self) -> bool {
+        self.raw[crate::attachment::CONTEXT_KEY].as_bool() == Some(true)
+    }
+
+    /// Ask the client's LLM to generate a message (MCP `sampling/createMessage`)
+    ///
+    /// The request is routed through the host's `create_message` callback;
+    /// the call context is forwarded so the host can route it to the right
+    /// client session.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// fn handle_classify(ctx: &ToolContext, args: &Value) -> Result<Value, String> {
+    ///     let review = args["review"].as_str().unwrap_or_default();
+    ///     let result = ctx.sample(
+    ///         vec![SamplingMessage::user(format!("Classify the sentiment: {}", review))],
+    ///         SamplingParams::new(16).temperature(0.0),
+    ///     )?;
+    ///     Ok(text_content(result.text().unwrap_or_default()))
+    /// }
+    /// ```
+    pub fn sample(
+        &self,
+        messages: Vec<SamplingMessage>,
+        params: SamplingParams,
+    ) -> Result<SamplingResult, ToolError> {
+        let mut request =
+            serde_json::to_value(params).expect("sampling parameters serialize to JSON");
+        request["messages"] =
+            serde_json::to_value(messages).expect("sampling messages serialize to JSON");
+
+        let response = host::request(|cb| cb.create_message, "sampling", &self.raw, request)?;
+
+        serde_json::from_value(response).map_err(|e| ToolError::InvalidResponse(e.to_string()))
+    }
+
+    /// Ask the user for structured input (MCP `elicitation/create`)
+    ///
+    /// Shows `message` together with a form for the fields in `schema` and
+    /// returns the user's answer. The request is routed through the host's
+    /// `elicit` callback.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// fn handle_delete_order(ctx: &ToolContext, args: &Value) -> Result<Value, String> {
+    ///     let order_id = args["order_id"].as_i64().ok_or("order_id required")?;
+    ///     let schema = ElicitationSchema::new()
+    ///         .param_string("reason", "Why is the order being deleted?", true);
+    ///
+    ///     match ctx.elicit(&format!("Delete order {}?", order_id), &schema)? {
+    ///         ElicitationResponse::Accept(fields) => {
+    ///             delete_order(order_id, fields["reason"].as_str().unwrap_or_default())?;
+    ///             Ok(text_content("Order deleted"))
+    ///         }
+    ///         ElicitationResponse::Decline | ElicitationResponse::Cancel => {
+    ///             Ok(text_content("Deletion cancelled by the user"))
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn elicit(
+        &self,
+        message: &str,
+        schema: &ElicitationSchema,
+    ) -> Result<ElicitationResponse, ToolError> {
+        let request = serde_json::json!({
+            "message": message,
+            "requestedSchema": schema.to_json_schema()
+        });
+
+        let response = host::request(|cb| cb.elicit, "elicitation", &self.raw, request)?;
+
+        ElicitationResponse::from_json(&response).map_err(ToolError::InvalidResponse)
+    }
+
+    /// Ask the user to confirm an action
+    ///
+    /// Shorthand for [`elicit`](Self::elicit) with an empty schema. Returns
+    /// `true` only if the user accepted.
+    pub fn confirm(&self, message: &str) -> Result<bool, ToolError> {
+        Ok(self.elicit(message, &ElicitationSchema::new())?.is_accepted())
+    }
+}
@@ -0,0 +1,115 @@
+//! Types for elicitation requests (MCP `elicitation/create`)
+//!
+//! Tools can ask the user to confirm an action or supply missing fields in
+//! the middle of a call through
+//! [`ToolContext::elicit`](crate::ToolContext::elicit). The requested fields
+//! are described with the same [`ToolParam`] model used by `ToolBuilder`.
+
+use crate::tool::{params_to_schema, ParamType, ToolParam};
+use serde_json::Value;
+
+/// Schema of the fields requested from the user
+///
+/// MCP elicitation only supports flat objects with primitive fields, so only
+/// string, integer, number and boolean parameters can be added.
+///
+/// # Example
+///
+/// ```ignore
+/// let schema = ElicitationSchema::new()
+///     .param_string("reason", "Why is the order being deleted?", true)
+///     .param_bool("notify_customer", "Send a notification to the customer", false);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ElicitationSchema {
+    params: Vec<ToolParam>,
+}
+
+impl ElicitationSchema {
+    /// Create an empty schema
+    ///
+    /// An empty schema asks the user for a plain accept/decline decision.
+    pub fn new() -> Self {
+        ElicitationSchema::default()
+    }
+
+    /// Add a string field
+    ///
+    /// # Arguments
+    /// * `name` - Field name
+    /// * `description` - Field description shown to the user
+    /// * `required` - Whether the field is required
+    pub fn param_string(self, name: &str, description: &str, required: bool) -> Self {
+        self.param(name, description, ParamType::String, required)
+    }
+
+    /// Add an integer field (i64)
+    pub fn param_i64(self, name: &str, description: &str, required: bool) -> Self {
+        self.param(name, description, ParamType::Integer, required)
+    }
+
+    /// Add a number field (f64)
+    pub fn param_f64(self, name: &str, description: &str, required: bool) -> Self {
+        self.param(name, description, ParamType::Number, required)
+    }
+
+    /// Add a boolean field
+    pub fn param_bool(self, name: &str, description: &str, required: bool) -> Self {
+        self.param(name, description, ParamType::Boolean, required)
+    }
+
+    fn param(mut self, name: &str, description: &str, param_type: ParamType, required: bool) -> Self {
+        self.params.push(ToolParam {
+            name: name.to_string(),
+            description: description.to_string(),
+            param_type,
+            required,
+            completion: None,
+        });
+        self
+    }
+
+    /// The requested fields
+    pub fn params(&self) -> &[ToolParam] {
+        &self.params
+    }
+
+    /// Convert to the `requestedSchema` JSON Schema object
+    pub fn to_json_schema(&self) -> Value {
+        params_to_schema(&self.params)
+    }
+}
+
+/// The user's answer to an elicitation request
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElicitationResponse {
+    /// The user submitted the form; contains the field values as a JSON object
+    Accept(Value),
+    /// The user explicitly declined
+    Decline,
+    /// The user dismissed the request without choosing
+    Cancel,
+}
+
+impl ElicitationResponse {
+    /// Parse the MCP `elicitation/create` result
+    pub fn from_json(result: &Value) -> Result<Self, String> {
+        match result["action"].as_str() {
+            Some("accept") => Ok(ElicitationResponse::Accept(
+                result
+                    .get("content")
+                    .cloned()
+                    .unwrap_or_else(|| Value::Object(Default::default())),
+            )),
+            Some("decline") => Ok(ElicitationResponse::Decline),
+            Some("cancel") => Ok(ElicitationResponse::Cancel),
+            Some(other) => Err(format!("Unknown elicitation action: {}", other)),
+            None => Err("Elicitation result is missing 'action'".to_string()),
+        }
+    }
+
+    /// Whether the user accepted
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, ElicitationResponse::Accept(_))
+    }
+}
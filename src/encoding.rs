@@ -0,0 +1,183 @@
+//! Wire encoding of ABI payloads
+//!
+//! By default all tool arguments, call contexts and results cross the ABI as
+//! JSON text. For high-volume numeric tools, plugins built with the `msgpack`
+//! or `cbor` cargo feature can use a binary encoding instead:
+//!
+//! 1. The plugin declares the encodings it supports in
+//!    [`PluginDeclaration::wire_encodings`](crate::PluginDeclaration::wire_encodings)
+//!    (filled in by `declare_plugin!` from the enabled features).
+//! 2. The host picks one of them and calls `set_wire_encoding` once, before
+//!    any other call.
+//! 3. From then on, the generated dispatchers decode arguments and call
+//!    contexts, and the result helpers in [`crate::utils`] encode results,
+//!    in that encoding.
+//!
+//! The payload is always the same JSON data model; only its serialization
+//! changes. Configuration (`configure`, `get_config_schema`), host callback
+//! envelopes, streamed chunks and the envelope of multipart results stay
+//! JSON.
+
+use crate::{Capabilities, PluginStatus};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Function signature for selecting the wire encoding
+///
+/// # Parameters
+/// - `encoding`: A [`WireEncoding`] discriminant
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero if the plugin does not support the encoding
+pub type SetWireEncodingFn = unsafe extern "C" fn(u32) -> i32;
+
+/// Serialization used for ABI payloads
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WireEncoding {
+    /// JSON text (always supported)
+    #[default]
+    Json = 0,
+    /// MessagePack (`msgpack` feature)
+    MessagePack = 1,
+    /// CBOR (`cbor` feature)
+    Cbor = 2,
+}
+
+impl WireEncoding {
+    /// Bit of this encoding in [`PluginDeclaration::wire_encodings`](crate::PluginDeclaration::wire_encodings)
+    pub const fn bit(self) -> u64 {
+        1 << self as u32
+    }
+
+    /// Look up an encoding by its discriminant
+    pub fn from_u32(value: u32) -> Option<WireEncoding> {
+        match value {
+            0 => Some(WireEncoding::Json),
+            1 => Some(WireEncoding::MessagePack),
+            2 => Some(WireEncoding::Cbor),
+            _ => None,
+        }
+    }
+
+    /// Whether this build of the crate can encode and decode this encoding
+    pub const fn is_supported(self) -> bool {
+        SUPPORTED & self.bit() != 0
+    }
+
+    /// Serialize a value
+    ///
+    /// Accepts any `Serialize` type, so typed results are written straight
+    /// into the output buffer without building a `Value` first.
+    pub fn encode<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<u8>, String> {
+        let mut buffer = Vec::new();
+        match self {
+            WireEncoding::Json => {
+                serde_json::to_writer(&mut buffer, value).map_err(|e| e.to_string())?
+            }
+            #[cfg(feature = "msgpack")]
+            WireEncoding::MessagePack => {
+                rmp_serde::encode::write_named(&mut buffer, value).map_err(|e| e.to_string())?
+            }
+            #[cfg(feature = "cbor")]
+            WireEncoding::Cbor => {
+                ciborium::into_writer(value, &mut buffer).map_err(|e| e.to_string())?
+            }
+            #[allow(unreachable_patterns)]
+            other => return Err(format!("{:?} encoding is not enabled", other)),
+        }
+        Ok(buffer)
+    }
+
+    /// Deserialize a value
+    pub fn decode(self, bytes: &[u8]) -> Result<Value, String> {
+        match self {
+            WireEncoding::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            #[cfg(feature = "msgpack")]
+            WireEncoding::MessagePack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
+            #[cfg(feature = "cbor")]
+            WireEncoding::Cbor => ciborium::from_reader(bytes).map_err(|e| e.to_string()),
+            #[allow(unreachable_patterns)]
+            other => Err(format!("{:?} encoding is not enabled", other)),
+        }
+    }
+}
+
+/// Encodings supported by this build, as declared in `PluginDeclaration`
+pub const SUPPORTED: u64 = WireEncoding::Json.bit()
+    | if cfg!(feature = "msgpack") { WireEncoding::MessagePack.bit() } else { 0 }
+    | if cfg!(feature = "cbor") { WireEncoding::Cbor.bit() } else { 0 };
+
+static CURRENT: AtomicU32 = AtomicU32::new(WireEncoding::Json as u32);
+
+/// The encoding selected by the host
+pub fn current() -> WireEncoding {
+    WireEncoding::from_u32(CURRENT.load(Ordering::Relaxed)).unwrap_or_default()
+}
+
+/// Select the encoding used for all further calls
+///
+/// Returns an error if the encoding is not enabled in this build.
+pub fn set_current(encoding: WireEncoding) -> Result<(), String> {
+    if !encoding.is_supported() {
+        return Err(format!("{:?} encoding is not enabled", encoding));
+    }
+    CURRENT.store(encoding as u32, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Serialize a value with the selected encoding
+pub fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, String> {
+    current().encode(value)
+}
+
+/// Deserialize a value with the selected encoding
+pub fn decode(bytes: &[u8]) -> Result<Value, String> {
+    current().decode(bytes)
+}
+
+/// `set_wire_encoding` entry filled in by `declare_plugin!`
+///
+/// Only present with the `msgpack` or `cbor` feature; a JSON-only plugin has
+/// no encoding for the host to select.
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+pub const SET_WIRE_ENCODING: Option<SetWireEncodingFn> = Some(standard_set_wire_encoding);
+
+/// `set_wire_encoding` entry filled in by `declare_plugin!`
+///
+/// Only present with the `msgpack` or `cbor` feature; a JSON-only plugin has
+/// no encoding for the host to select.
+#[cfg(not(any(feature = "msgpack", feature = "cbor")))]
+pub const SET_WIRE_ENCODING: Option<SetWireEncodingFn> = None;
+
+/// Capability declared by `declare_plugin!` along with [`SET_WIRE_ENCODING`]
+pub const CAPABILITY: Capabilities = if SET_WIRE_ENCODING.is_some() {
+    Capabilities::WIRE_ENCODING
+} else {
+    Capabilities::NONE
+};
+
+/// Standard set_wire_encoding implementation
+///
+/// `declare_plugin!` uses this automatically when a binary encoding is
+/// enabled (see [`SET_WIRE_ENCODING`]).
+///
+/// # Safety
+///
+/// Always safe to call; `unsafe extern "C"` only to match [`SetWireEncodingFn`].
+pub unsafe extern "C" fn standard_set_wire_encoding(encoding: u32) -> i32 {
+    crate::utils::clear_last_error();
+    let result = WireEncoding::from_u32(encoding)
+        .ok_or_else(|| format!("Unknown wire encoding: {}", encoding))
+        .and_then(set_current);
+
+    match result {
+        Ok(()) => PluginStatus::Ok.code(),
+        Err(e) => {
+            crate::utils::set_last_error(e);
+            PluginStatus::Unsupported.code()
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Errors for host interactions inside tool handlers
+
+use std::fmt;
+
+/// Error returned by [`ToolContext`](crate::ToolContext) and
+/// [`ChunkWriter`](crate::stream::ChunkWriter) operations
+///
+/// Converts into `String`, so it can be propagated with `?` from a handler
+/// returning `Result<Value, String>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolError {
+    /// The host did not register the callback needed for this operation
+    Unsupported(String),
+    /// The host reported an error
+    Host(String),
+    /// The host's response could not be parsed
+    InvalidResponse(String),
+    /// The host stopped the call (e.g. the client closed a stream)
+    Cancelled,
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolError::Unsupported(what) => write!(f, "Host does not support {}", what),
+            ToolError::Host(msg) => write!(f, "Host error: {}", msg),
+            ToolError::InvalidResponse(msg) => write!(f, "Invalid host response: {}", msg),
+            ToolError::Cancelled => write!(f, "Call was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for ToolError {}
+
+impl From<ToolError> for String {
+    fn from(error: ToolError) -> String {
+        error.to_string()
+    }
+}
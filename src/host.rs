@@ -0,0 +1,577 @@
+//! Callbacks from plugins into the host
+//!
+//! Some MCP features run in the opposite direction: the server (plugin) asks
+//! the client for something, e.g. an LLM completion via `sampling/createMessage`
+//! or user input via `elicitation/create`.
+//! The host passes a [`HostCallbacks`] table to the plugin once after loading,
+//! through the optional `set_host_callbacks` function of the declaration.
+//! Handlers then use these callbacks through [`ToolContext`](crate::ToolContext).
+//!
+//! ## Protocol
+//!
+//! Every request callback takes a JSON envelope and returns a JSON result,
+//! following the same conventions as the plugin functions:
+//!
+//! ```json
+//! {
+//!   "context": { ... },  // the call context the host passed to execute_tool_with_context
+//!   "params": { ... }    // the MCP request parameters
+//! }
+//! ```
+//!
+//! On success the callback returns 0 and the MCP result; on failure it returns
+//! non-zero and `{"error": "message"}`. The result buffer is allocated by the
+//! host and released by the plugin through `free_buffer`.
+//!
+//! [`HostCallbacks`] follows the same layout evolution rules as
+//! [`PluginDeclaration`](crate::PluginDeclaration): it is size-prefixed and
+//! only ever grows at the end.
+
+use crate::error::ToolError;
+use crate::PluginStatus;
+use serde_json::Value;
+use std::os::raw::c_void;
+use std::sync::RwLock;
+
+/// Function signature for a host request callback
+///
+/// # Parameters
+/// - `host_data`: The `host_data` pointer from [`HostCallbacks`]
+/// - `request_json`: JSON request envelope as byte array
+/// - `request_len`: Length of request_json
+/// - `result_buf`: Output pointer for result (allocated by host)
+/// - `result_len`: Output capacity of result buffer
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero error code on failure
+pub type HostRequestFn = unsafe extern "C" fn(
+    *mut c_void,  // host data
+    *const u8,    // request JSON
+    usize,        // request length
+    *mut *mut u8, // result buffer (allocated by host)
+    *mut usize,   // result capacity
+) -> i32;
+
+/// Function signature for a host notification callback
+///
+/// Notifications are fire-and-forget messages from the plugin to the host:
+/// `{"method": "notifications/tools/list_changed", "params": {}}`.
+///
+/// # Parameters
+/// - `host_data`: The `host_data` pointer from [`HostCallbacks`]
+/// - `notification_json`: JSON notification as byte array
+/// - `notification_len`: Length of notification_json
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero error code on failure
+pub type HostNotifyFn = unsafe extern "C" fn(
+    *mut c_void, // host data
+    *const u8,   // notification JSON
+    usize,       // notification length
+) -> i32;
+
+/// Function signature for freeing memory allocated by the host
+///
+/// # Parameters
+/// - `host_data`: The `host_data` pointer from [`HostCallbacks`]
+/// - `ptr`: Pointer to memory to free
+/// - `capacity`: Capacity of the allocation
+pub type HostFreeFn = unsafe extern "C" fn(*mut c_void, *mut u8, usize);
+
+/// Function signature for handing the host callbacks to a plugin
+///
+/// # Parameters
+/// - `callbacks`: Pointer to the host's callback table, or null to remove
+///   previously set callbacks. The table is copied; it need not outlive the call.
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero if the table is too small to be valid
+pub type SetHostCallbacksFn = unsafe extern "C" fn(*const HostCallbacks) -> i32;
+
+/// Table of host callbacks available to plugins
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct HostCallbacks {
+    /// Size in bytes of the table as compiled into the host
+    pub struct_size: usize,
+
+    /// Opaque pointer passed back to every callback
+    pub host_data: *mut c_void,
+
+    /// Frees result buffers returned by the request callbacks
+    pub free_buffer: HostFreeFn,
+
+    /// Handles MCP `sampling/createMessage` requests
+    pub create_message: Option<HostRequestFn>,
+
+    /// Handles MCP `elicitation/create` requests
+    pub elicit: Option<HostRequestFn>,
+
+    /// Forwards notifications (e.g. `notifications/tools/list_changed`)
+    pub notify: Option<HostNotifyFn>,
+
+    /// Receives finished spans and events of the plugin (see [`crate::trace`])
+    pub export_trace: Option<HostNotifyFn>,
+}
+
+// Safety: The host guarantees that its callbacks and `host_data` may be used
+// from any thread, just like plugins guarantee it for `execute_tool`.
+unsafe impl Send for HostCallbacks {}
+unsafe impl Sync for HostCallbacks {}
+
+impl HostCallbacks {
+    /// Smallest valid table: the size, host data and `free_buffer`
+    pub const MIN_SIZE: usize = std::mem::offset_of!(HostCallbacks, create_message);
+
+    /// Size of the table in this version of the crate
+    pub const SIZE: usize = std::mem::size_of::<HostCallbacks>();
+
+    /// Read a host's callback table, tolerating older (shorter) layouts
+    ///
+    /// Callbacks the host does not have read as `None`. Returns `None` if
+    /// the pointer is null or the table is smaller than [`Self::MIN_SIZE`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be null or valid for reads of its reported `struct_size` bytes.
+    pub unsafe fn read(ptr: *const HostCallbacks) -> Option<HostCallbacks> {
+        if ptr.is_null() {
+            return None;
+        }
+
+        let struct_size = std::ptr::read_unaligned(std::ptr::addr_of!((*ptr).struct_size));
+        if struct_size < Self::MIN_SIZE {
+            return None;
+        }
+
+        // All fields past MIN_SIZE are valid when zeroed
+        let mut callbacks = std::mem::MaybeUninit::<HostCallbacks>::zeroed();
+        std::ptr::copy_nonoverlapping(
+            ptr as *const u8,
+            callbacks.as_mut_ptr() as *mut u8,
+            struct_size.min(Self::SIZE),
+        );
+        Some(callbacks.assume_init())
+    }
+}
+
+// Layout assertions, see `PluginDeclaration` for the rules
+const _: () = {
+    use std::mem::{offset_of, size_of};
+
+    assert!(offset_of!(HostCallbacks, struct_size) == 0);
+    assert!(offset_of!(HostCallbacks, host_data) == size_of::<usize>());
+    assert!(offset_of!(HostCallbacks, free_buffer) == 2 * size_of::<usize>());
+    assert!(size_of::<Option<HostRequestFn>>() == size_of::<usize>());
+
+    // Initial layout
+    assert!(offset_of!(HostCallbacks, create_message) == 3 * size_of::<usize>());
+
+    // Elicitation
+    assert!(
+        offset_of!(HostCallbacks, elicit)
+            == offset_of!(HostCallbacks, create_message) + size_of::<usize>()
+    );
+
+    // Notifications
+    assert!(size_of::<Option<HostNotifyFn>>() == size_of::<usize>());
+    assert!(
+        offset_of!(HostCallbacks, notify)
+            == offset_of!(HostCallbacks, elicit) + size_of::<usize>()
+    );
+
+    // Trace export
+    assert!(
+        offset_of!(HostCallbacks, export_trace)
+            == offset_of!(HostCallbacks, notify) + size_of::<usize>()
+    );
+
+    // End of the current layout
+    assert!(HostCallbacks::SIZE == offset_of!(HostCallbacks, export_trace) + size_of::<usize>());
+};
+
+static HOST_CALLBACKS: RwLock<Option<HostCallbacks>> = RwLock::new(None);
+
+/// Standard set_host_callbacks implementation
+///
+/// This can be used directly in the `declare_plugin!` macro.
+///
+/// # Safety
+///
+/// `callbacks` must be null or point to a valid [`HostCallbacks`] table.
+///
+/// # Example
+///
+/// ```ignore
+/// declare_plugin! {
+///     list_tools: generated_list_tools,
+///     execute_tool: generated_execute_tool,
+///     free_string: mcp_plugin_api::utils::standard_free_string,
+///     set_host_callbacks: mcp_plugin_api::host::standard_set_host_callbacks,
+///     execute_tool_with_context: generated_execute_tool_with_context
+/// }
+/// ```
+pub unsafe extern "C" fn standard_set_host_callbacks(callbacks: *const HostCallbacks) -> i32 {
+    crate::utils::clear_last_error();
+    if callbacks.is_null() {
+        clear_host_callbacks();
+        return PluginStatus::Ok.code();
+    }
+
+    match HostCallbacks::read(callbacks) {
+        Some(callbacks) => {
+            set_host_callbacks(callbacks);
+            PluginStatus::Ok.code()
+        }
+        None => {
+            crate::utils::set_last_error(format!(
+                "Host callback table is smaller than {} bytes",
+                HostCallbacks::MIN_SIZE
+            ));
+            PluginStatus::InvalidArgument.code()
+        }
+    }
+}
+
+/// Install host callbacks from Rust
+///
+/// Used by [`mock::MockHost`] and by hosts embedding a plugin statically.
+/// With the `tracing` feature, a host with `export_trace` also gets the
+/// plugin's spans (see [`crate::trace::install`]).
+pub fn set_host_callbacks(callbacks: HostCallbacks) {
+    *HOST_CALLBACKS.write().unwrap_or_else(|e| e.into_inner()) = Some(callbacks);
+
+    #[cfg(feature = "tracing")]
+    if callbacks.export_trace.is_some() {
+        crate::trace::install();
+    }
+}
+
+/// Remove previously installed host callbacks
+pub fn clear_host_callbacks() {
+    *HOST_CALLBACKS.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Get a copy of the installed host callbacks
+pub fn host_callbacks() -> Option<HostCallbacks> {
+    *HOST_CALLBACKS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Send a request to the host through one of its callbacks
+///
+/// `select` picks the callback from the table; `what` names the feature for
+/// [`ToolError::Unsupported`].
+pub(crate) fn request(
+    select: fn(&HostCallbacks) -> Option<HostRequestFn>,
+    what: &str,
+    context: &Value,
+    params: Value,
+) -> Result<Value, ToolError> {
+    let callbacks =
+        host_callbacks().ok_or_else(|| ToolError::Unsupported(what.to_string()))?;
+    let callback = select(&callbacks).ok_or_else(|| ToolError::Unsupported(what.to_string()))?;
+
+    let envelope = serde_json::json!({
+        "context": context,
+        "params": params
+    });
+    let request = envelope.to_string();
+
+    let mut result_buf: *mut u8 = std::ptr::null_mut();
+    let mut result_len: usize = 0;
+    let code = unsafe {
+        callback(
+            callbacks.host_data,
+            request.as_ptr(),
+            request.len(),
+            &mut result_buf,
+            &mut result_len,
+        )
+    };
+
+    let response: Result<Value, String> = if result_buf.is_null() {
+        Err("empty response".to_string())
+    } else {
+        let bytes = unsafe { std::slice::from_raw_parts(result_buf, result_len) };
+        let parsed = serde_json::from_slice(bytes).map_err(|e| e.to_string());
+        unsafe { (callbacks.free_buffer)(callbacks.host_data, result_buf, result_len) };
+        parsed
+    };
+
+    match (code, response) {
+        (0, Ok(value)) => Ok(value),
+        (0, Err(e)) => Err(ToolError::InvalidResponse(e)),
+        (_, Ok(value)) => Err(ToolError::Host(
+            value["error"]
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(|| value.to_string()),
+        )),
+        (code, Err(_)) => Err(ToolError::Host(format!("error code {}", code))),
+    }
+}
+
+/// Send a notification to the host
+///
+/// Returns [`ToolError::Unsupported`] if the host did not register a
+/// `notify` callback.
+pub fn notify(method: &str, params: Value) -> Result<(), ToolError> {
+    let callbacks =
+        host_callbacks().ok_or_else(|| ToolError::Unsupported("notifications".to_string()))?;
+    let callback = callbacks
+        .notify
+        .ok_or_else(|| ToolError::Unsupported("notifications".to_string()))?;
+
+    let notification = serde_json::json!({
+        "method": method,
+        "params": params
+    })
+    .to_string();
+
+    let code = unsafe { callback(callbacks.host_data, notification.as_ptr(), notification.len()) };
+    if code == 0 {
+        Ok(())
+    } else {
+        Err(ToolError::Host(format!("notification failed with code {}", code)))
+    }
+}
+
+/// Send a finished span or event to the host
+///
+/// Returns [`ToolError::Unsupported`] if the host did not register an
+/// `export_trace` callback. See [`crate::trace`] for the record format.
+pub fn export_trace(record: &Value) -> Result<(), ToolError> {
+    let callbacks =
+        host_callbacks().ok_or_else(|| ToolError::Unsupported("trace export".to_string()))?;
+    let callback = callbacks
+        .export_trace
+        .ok_or_else(|| ToolError::Unsupported("trace export".to_string()))?;
+
+    let record = record.to_string();
+    let code = unsafe { callback(callbacks.host_data, record.as_ptr(), record.len()) };
+    if code == 0 {
+        Ok(())
+    } else {
+        Err(ToolError::Host(format!("trace export failed with code {}", code)))
+    }
+}
+
+/// Tell the host that the plugin's tool list changed
+///
+/// The host should re-read `list_tools` and emit
+/// `notifications/tools/list_changed` to its clients.
+pub fn notify_tools_list_changed() -> Result<(), ToolError> {
+    notify("notifications/tools/list_changed", serde_json::json!({}))
+}
+
+/// In-process host for testing plugins offline
+///
+/// # Example
+///
+/// ```
+/// use mcp_plugin_api::host::mock::MockHost;
+/// use mcp_plugin_api::sampling::{SamplingMessage, SamplingParams};
+/// use mcp_plugin_api::ToolContext;
+/// use serde_json::json;
+///
+/// MockHost::new()
+///     .on_sampling(|params| {
+///         assert_eq!(params["maxTokens"], 64);
+///         Ok(json!({
+///             "role": "assistant",
+///             "content": { "type": "text", "text": "positive" },
+///             "model": "mock-model"
+///         }))
+///     })
+///     .install();
+///
+/// let ctx = ToolContext::new();
+/// let result = ctx
+///     .sample(vec![SamplingMessage::user("Classify: great product!")], SamplingParams::new(64))
+///     .unwrap();
+/// assert_eq!(result.text(), Some("positive"));
+/// ```
+pub mod mock {
+    use super::{HostCallbacks, HostNotifyFn, HostRequestFn};
+    use crate::PluginStatus;
+    use serde_json::Value;
+    use std::os::raw::c_void;
+
+    type Responder = Box<dyn Fn(&Value) -> Result<Value, String> + Send + Sync>;
+    type Listener = Box<dyn Fn(&Value) + Send + Sync>;
+
+    /// A host whose callbacks are answered by Rust closures
+    ///
+    /// Each closure receives the MCP request parameters and returns the MCP
+    /// result or an error message. Callbacks without a closure are absent
+    /// from the table, so the plugin sees them as unsupported.
+    #[derive(Default)]
+    pub struct MockHost {
+        create_message: Option<Responder>,
+        elicit: Option<Responder>,
+        notify: Option<Listener>,
+        export_trace: Option<Listener>,
+    }
+
+    impl MockHost {
+        /// Create a mock host without any callbacks
+        pub fn new() -> Self {
+            MockHost::default()
+        }
+
+        /// Answer `sampling/createMessage` requests
+        pub fn on_sampling(
+            mut self,
+            responder: impl Fn(&Value) -> Result<Value, String> + Send + Sync + 'static,
+        ) -> Self {
+            self.create_message = Some(Box::new(responder));
+            self
+        }
+
+        /// Answer `elicitation/create` requests
+        pub fn on_elicitation(
+            mut self,
+            responder: impl Fn(&Value) -> Result<Value, String> + Send + Sync + 'static,
+        ) -> Self {
+            self.elicit = Some(Box::new(responder));
+            self
+        }
+
+        /// Receive notifications (`{"method": ..., "params": ...}`)
+        pub fn on_notification(mut self, listener: impl Fn(&Value) + Send + Sync + 'static) -> Self {
+            self.notify = Some(Box::new(listener));
+            self
+        }
+
+        /// Receive exported spans and events (see [`crate::trace`])
+        pub fn on_trace(mut self, listener: impl Fn(&Value) + Send + Sync + 'static) -> Self {
+            self.export_trace = Some(Box::new(listener));
+            self
+        }
+
+        /// Build the callback table
+        ///
+        /// The mock host is leaked so the table stays valid for the rest of
+        /// the process, which is what tests need.
+        pub fn callbacks(self) -> HostCallbacks {
+            let create_message: Option<HostRequestFn> =
+                self.create_message.is_some().then_some(mock_create_message);
+            let elicit: Option<HostRequestFn> = self.elicit.is_some().then_some(mock_elicit);
+            let notify: Option<HostNotifyFn> = self.notify.is_some().then_some(mock_notify);
+            let export_trace: Option<HostNotifyFn> =
+                self.export_trace.is_some().then_some(mock_export_trace);
+
+            HostCallbacks {
+                struct_size: HostCallbacks::SIZE,
+                host_data: Box::into_raw(Box::new(self)) as *mut c_void,
+                free_buffer: mock_free_buffer,
+                create_message,
+                elicit,
+                notify,
+                export_trace,
+            }
+        }
+
+        /// Install the mock host's callbacks in this plugin
+        pub fn install(self) {
+            super::set_host_callbacks(self.callbacks());
+        }
+    }
+
+    /// Run a responder and return its result through the callback protocol
+    unsafe fn respond(
+        responder: Option<&Responder>,
+        request_json: *const u8,
+        request_len: usize,
+        result_buf: *mut *mut u8,
+        result_len: *mut usize,
+    ) -> i32 {
+        let request = std::slice::from_raw_parts(request_json, request_len);
+        let outcome = match (responder, serde_json::from_slice::<Value>(request)) {
+            (Some(responder), Ok(envelope)) => responder(&envelope["params"]),
+            (None, _) => Err("callback not registered".to_string()),
+            (_, Err(e)) => Err(format!("Invalid JSON request: {}", e)),
+        };
+
+        let (status, reply) = match outcome {
+            Ok(value) => (PluginStatus::Ok, value),
+            Err(e) => (PluginStatus::HandlerError, serde_json::json!({ "error": e })),
+        };
+        // Callback envelopes are JSON regardless of the wire encoding
+        let reply = serde_json::to_vec(&reply).expect("JSON values are always encodable");
+        crate::utils::return_encoded(reply, result_buf, result_len);
+        status.code()
+    }
+
+    unsafe extern "C" fn mock_create_message(
+        host_data: *mut c_void,
+        request_json: *const u8,
+        request_len: usize,
+        result_buf: *mut *mut u8,
+        result_len: *mut usize,
+    ) -> i32 {
+        let host = &*(host_data as *const MockHost);
+        respond(
+            host.create_message.as_ref(),
+            request_json,
+            request_len,
+            result_buf,
+            result_len,
+        )
+    }
+
+    unsafe extern "C" fn mock_elicit(
+        host_data: *mut c_void,
+        request_json: *const u8,
+        request_len: usize,
+        result_buf: *mut *mut u8,
+        result_len: *mut usize,
+    ) -> i32 {
+        let host = &*(host_data as *const MockHost);
+        respond(
+            host.elicit.as_ref(),
+            request_json,
+            request_len,
+            result_buf,
+            result_len,
+        )
+    }
+
+    unsafe extern "C" fn mock_notify(
+        host_data: *mut c_void,
+        notification_json: *const u8,
+        notification_len: usize,
+    ) -> i32 {
+        let host = &*(host_data as *const MockHost);
+        listen(host.notify.as_ref(), notification_json, notification_len)
+    }
+
+    unsafe extern "C" fn mock_export_trace(
+        host_data: *mut c_void,
+        record_json: *const u8,
+        record_len: usize,
+    ) -> i32 {
+        let host = &*(host_data as *const MockHost);
+        listen(host.export_trace.as_ref(), record_json, record_len)
+    }
+
+    /// Pass a JSON message to a listener
+    unsafe fn listen(listener: Option<&Listener>, message_json: *const u8, message_len: usize) -> i32 {
+        let message = std::slice::from_raw_parts(message_json, message_len);
+
+        match (listener, serde_json::from_slice::<Value>(message)) {
+            (Some(listener), Ok(message)) => {
+                listener(&message);
+                0
+            }
+            _ => 1,
+        }
+    }
+
+    unsafe extern "C" fn mock_free_buffer(_host_data: *mut c_void, ptr: *mut u8, capacity: usize) {
+        crate::utils::standard_free_string(ptr, capacity);
+    }
+}
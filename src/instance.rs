@@ -0,0 +1,194 @@
+//! Plugin instances
+//!
+//! By default a plugin is configured once per process: `configure` stores the
+//! configuration in a global, and `declare_tools!` keeps its tools in a
+//! global registry. Hosts that need the same plugin twice with different
+//! settings (e.g. one per database) create instances instead:
+//!
+//! 1. `create_instance(config_json, config_len)` parses the configuration and
+//!    returns an opaque `*mut Instance` handle (null on failure, message via
+//!    `last_error`).
+//! 2. `instance_list_tools` and `instance_execute_tool` take the handle as
+//!    their first argument.
+//! 3. `destroy_instance` releases it. Calls still running keep the instance
+//!    alive until they finish.
+//!
+//! While a tool handler runs, its instance is the [current](current) one:
+//! `get_instance_config()` from `declare_plugin_config!` returns the
+//! instance's configuration, and `register_tool` / `unregister_tool` /
+//! `invalidate_tool_cache` from `declare_tools!` work on the instance's own
+//! tool set. `get_config()` keeps returning the global configuration.
+//!
+//! ## Limitations
+//!
+//! Only tools, their [caches](crate::cache) and the configuration are kept
+//! per instance. Everything else is shared by all instances of the process:
+//!
+//! - Resources, prompts, completions and session hooks have no instance
+//!   entry points; they run without a current instance and see the global
+//!   configuration.
+//! - [Sessions](crate::session) are looked up by session id only, so hosts
+//!   must not reuse ids across instances.
+//! - [Metrics](crate::metrics) add up the calls of all instances per tool
+//!   name.
+//! - The [wire encoding](crate::encoding) and the host callbacks are
+//!   process-wide, and `last_error` is kept per thread.
+
+use crate::registry::ToolRegistry;
+use std::any::Any;
+use std::cell::RefCell;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// Function signature for creating a plugin instance
+///
+/// # Parameters
+/// - `config_json`: JSON configuration as byte array
+/// - `config_len`: Length of config_json
+///
+/// # Returns
+/// - An instance handle, to be released with `destroy_instance`
+/// - Null on failure (message via `last_error`)
+pub type CreateInstanceFn = unsafe extern "C" fn(*const u8, usize) -> *mut Instance;
+
+/// Function signature for releasing a plugin instance
+///
+/// Null handles are ignored.
+pub type DestroyInstanceFn = unsafe extern "C" fn(*mut Instance);
+
+/// Function signature for listing the tools of an instance
+///
+/// Like [`ListToolsFn`](crate::ListToolsFn), with the instance handle as
+/// first argument.
+pub type InstanceListToolsFn = unsafe extern "C" fn(
+    *mut Instance, // instance
+    *mut *mut u8,  // result buffer (allocated by plugin)
+    *mut usize,    // result capacity
+) -> i32;
+
+/// Function signature for executing a tool of an instance
+///
+/// Like [`ExecuteToolWithContextFn`](crate::ExecuteToolWithContextFn), with
+/// the instance handle as first argument.
+pub type InstanceExecuteToolFn = unsafe extern "C" fn(
+    *mut Instance, // instance
+    *const c_char, // tool name
+    *const u8,     // args JSON
+    usize,         // args length
+    *const u8,     // context JSON
+    usize,         // context length
+    *mut *mut u8,  // result buffer (allocated by plugin)
+    *mut usize,    // result capacity
+) -> i32;
+
+/// One configured copy of a plugin
+///
+/// Hosts only see it as an opaque pointer.
+pub struct Instance {
+    id: u64,
+    config: Arc<dyn Any + Send + Sync>,
+    tools: OnceLock<ToolRegistry>,
+}
+
+impl Instance {
+    /// Create an instance with its configuration
+    pub fn new<T: Send + Sync + 'static>(config: T) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+        Instance {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            config: Arc::new(config),
+            tools: OnceLock::new(),
+        }
+    }
+
+    /// Process-unique number of this instance
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The instance's configuration, if it has type `T`
+    pub fn config<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.config.clone().downcast::<T>().ok()
+    }
+
+    /// The instance's tools, created with `create` on first use
+    pub fn tools(&self, create: impl FnOnce() -> ToolRegistry) -> &ToolRegistry {
+        self.tools.get_or_init(create)
+    }
+
+    /// Turn the instance into a handle for the host
+    pub fn into_raw(self) -> *mut Instance {
+        Arc::into_raw(Arc::new(self)) as *mut Instance
+    }
+
+    /// Get a reference-counted instance from a handle
+    ///
+    /// Returns `None` for null handles.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be null or come from [`into_raw`](Self::into_raw) and not
+    /// have been passed to [`release`](Self::release) yet.
+    pub unsafe fn from_handle(handle: *mut Instance) -> Option<Arc<Instance>> {
+        if handle.is_null() {
+            return None;
+        }
+
+        Arc::increment_strong_count(handle as *const Instance);
+        Some(Arc::from_raw(handle as *const Instance))
+    }
+
+    /// Release a handle
+    ///
+    /// # Safety
+    ///
+    /// Same as [`from_handle`](Self::from_handle); the handle must not be used
+    /// afterwards.
+    pub unsafe fn release(handle: *mut Instance) {
+        if !handle.is_null() {
+            drop(Arc::from_raw(handle as *const Instance));
+        }
+    }
+}
+
+impl std::fmt::Debug for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Instance").field("id", &self.id).finish()
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<Instance>>> = const { RefCell::new(None) };
+}
+
+/// The instance whose call is running on this thread, if any
+pub fn current() -> Option<Arc<Instance>> {
+    CURRENT.with(|c| c.borrow().clone())
+}
+
+/// Run `f` with `instance` as the current instance
+///
+/// Used by [`Tool::run`](crate::Tool::run) around each handler call. The
+/// previous instance is restored afterwards, even if `f` panics.
+pub fn enter<R>(instance: Option<Arc<Instance>>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Arc<Instance>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CURRENT.with(|c| *c.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(CURRENT.with(|c| c.replace(instance)));
+    f()
+}
+
+/// Configuration of the current instance, if there is one of type `T`
+///
+/// Used by `get_instance_config()` from `declare_plugin_config!`.
+pub fn current_config<T: Send + Sync + 'static>() -> Option<Arc<T>> {
+    current().and_then(|instance| instance.config::<T>())
+}
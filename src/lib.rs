@@ -42,6 +42,107 @@
 //! - `execute_tool`: Executes a tool by name
 //! - `free_string`: Deallocates plugin-allocated memory
 //!
+//! ## Resources
+//!
+//! Plugins can also expose MCP resources with the `Resource` builder and the
+//! `declare_resources!` macro, which generates `generated_list_resources` and
+//! `generated_read_resource` for `declare_plugin!`. Parameterized resources
+//! such as `product://{id}/spec` are declared with `ResourceTemplate`.
+//!
+//! ## Prompts
+//!
+//! Curated prompt templates are declared with the `Prompt` builder and the
+//! `declare_prompts!` macro, which generates `generated_list_prompts` and
+//! `generated_get_prompt`.
+//!
+//! ## Completions
+//!
+//! Tool parameters, prompt arguments and resource template variables can have
+//! a completion provider attached with `.complete(...)` on their builders.
+//! `declare_completions!` generates the matching `generated_complete` function.
+//!
+//! ## Host Callbacks
+//!
+//! Handlers registered with `ToolBuilder::handler_with_context` receive a
+//! `ToolContext`, through which they can call back into the host, e.g.
+//! `ctx.sample(...)` for MCP sampling or `ctx.elicit(...)` to ask the user
+//! for input. See the `host` module for the protocol and
+//! `host::mock::MockHost` for testing without a host.
+//!
+//! ## Streaming
+//!
+//! Tools with large or incremental output use
+//! `ToolBuilder::streaming_handler` and write chunks through a `ChunkWriter`
+//! (see `utils::stream_text` and `utils::stream_json_line`). Hosts receive
+//! the chunks through `execute_tool_stream`; see the `stream` module.
+//!
+//! ## Middleware
+//!
+//! Logging, timing and similar cross-cutting code is written once as a
+//! `Middleware` and registered in `declare_tools!` or per tool with
+//! `ToolBuilder::middleware`; see the `middleware` module.
+//!
+//! ## Metrics
+//!
+//! Calls dispatched by `declare_tools!` are counted per tool, with errors and
+//! a latency histogram. Hosts read them as JSON (or Prometheus text with the
+//! `prometheus` feature) through `get_metrics`; see the `metrics` module.
+//!
+//! ## Tracing
+//!
+//! Hosts pass the W3C `traceparent` and `tracestate` of the request in the
+//! call context (`ToolContext::trace`). With the `tracing` feature, every
+//! tool call runs in a `tracing` span continuing that trace, and the plugin's
+//! spans are shipped to the host's `export_trace` callback; see the `trace`
+//! module.
+//!
+//! ## Caching
+//!
+//! Idempotent tools opt into result caching with `ToolBuilder::cache`;
+//! repeated calls of the same caller with the same arguments are answered
+//! from the cache until it expires or `invalidate_tool_cache` drops the
+//! results. See the `cache` module.
+//!
+//! ## Deadlines
+//!
+//! `ToolBuilder::timeout` and a `"deadlineMs"` entry in the call context
+//! limit how long a call may run. Late calls return `PluginStatus::Timeout`,
+//! and handlers can stop early with `ctx.check_cancelled()`; see the
+//! `context` module.
+//!
+//! ## Authorization
+//!
+//! Hosts pass the authenticated caller as `"principal"` in the call context
+//! (`ToolContext::principal`). Tools declaring `ToolBuilder::require_scopes`
+//! are only run for callers holding those scopes; see the `auth` module.
+//!
+//! ## Sessions
+//!
+//! Handlers keep state across calls of the same MCP session through
+//! `ToolContext::session`. Hosts announce sessions with `on_session_start`
+//! and `on_session_end`; see the `session` module and
+//! `declare_session_hooks!`.
+//!
+//! ## Instances
+//!
+//! A host can load the same plugin several times with different settings
+//! through `create_instance`, which returns an `Instance` handle taken by
+//! `instance_list_tools` and `instance_execute_tool`. `get_instance_config()`
+//! returns the configuration of the instance a handler runs for; see the
+//! `instance` module.
+//!
+//! ## Binary Content
+//!
+//! `utils::image_bytes_content` and `utils::audio_bytes_content` take raw
+//! bytes. Hosts that set `"binaryAttachments": true` in the call context
+//! receive them unencoded in a multipart result; see the `attachment` module.
+//!
+//! ## Wire Encoding
+//!
+//! Payloads are JSON by default. With the `msgpack` or `cbor` feature, the
+//! host can switch arguments and results to a binary encoding through
+//! `set_wire_encoding`; see the `encoding` module.
+//!
 //! ## Memory Management
 //!
 //! The `utils` module provides safe wrappers for memory management:
@@ -63,15 +164,154 @@ pub use serde_json;
 pub use once_cell;
 
 // Export sub-modules
+pub mod attachment;
+pub mod auth;
+pub mod cache;
+pub mod compat;
+pub mod completion;
+pub mod context;
+pub mod elicitation;
+pub mod encoding;
+pub mod error;
+pub mod host;
+pub mod instance;
+pub mod metrics;
+pub mod middleware;
+pub mod prompt;
+pub mod registry;
+pub mod resource;
+pub mod sampling;
+pub mod session;
+pub mod stream;
 pub mod tool;
+pub mod trace;
 pub mod utils;
+mod worker;
 
 // Don't make macros a public module - macros are exported at crate root
 #[macro_use]
 mod macros;
 
 // Re-export commonly used items
-pub use tool::{ParamType, Tool, ToolBuilder, ToolHandler, ToolParam};
+pub use auth::Principal;
+pub use cache::{CacheStats, ToolCache};
+pub use completion::{Completion, CompletionProvider, CompletionRef, CompletionRequest};
+pub use context::{CancellationToken, ToolContext};
+pub use elicitation::{ElicitationResponse, ElicitationSchema};
+pub use encoding::{SetWireEncodingFn, WireEncoding};
+pub use error::ToolError;
+pub use host::{HostCallbacks, SetHostCallbacksFn};
+pub use instance::{
+    CreateInstanceFn, DestroyInstanceFn, Instance, InstanceExecuteToolFn, InstanceListToolsFn,
+};
+pub use metrics::{GetMetricsFn, MetricsFormat};
+pub use middleware::{Middleware, Next, ToolCall, ToolResult};
+pub use prompt::{Prompt, PromptArgument, PromptBuilder, PromptHandler};
+pub use registry::ToolRegistry;
+pub use resource::{
+    Resource, ResourceBuilder, ResourceHandler, ResourceRouter, ResourceTemplate,
+    ResourceTemplateBuilder, ResourceTemplateHandler, UriTemplate,
+};
+pub use session::Session;
+pub use stream::{ChunkWriter, StreamSink, StreamWriteFn};
+pub use tool::{
+    ContextToolHandler, ParamType, RawToolHandler, StreamingToolHandler, Tool, ToolArgs,
+    ToolBuilder, ToolHandler, ToolHandlerKind, ToolOutcome, ToolOutput, ToolParam,
+    TypedToolHandler,
+};
+pub use trace::TraceContext;
+
+// ============================================================================
+// Status Codes
+// ============================================================================
+
+/// Return code of the plugin ABI functions
+///
+/// Every generated function returns one of these values as `i32`. Functions
+/// with an output buffer also return `{"error": "message"}` on failure; for
+/// the others the message is available through `last_error`.
+///
+/// Hosts built against an older version of this crate only distinguish
+/// `0` (success) from non-zero (failure), which remains valid.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PluginStatus {
+    /// Success
+    Ok = 0,
+    /// The handler (or a hand-written ABI function) reported an error
+    HandlerError = 1,
+    /// `configure` was called more than once
+    AlreadyConfigured = 2,
+    /// Arguments, call context, request or configuration could not be parsed
+    InvalidJson = 3,
+    /// A name or URI passed as C string is not valid UTF-8
+    InvalidUtf8 = 4,
+    /// Unknown tool, prompt, resource or completion reference
+    NotFound = 5,
+    /// The handler panicked; the panic was caught at the ABI boundary
+    Panic = 6,
+    /// The requested feature or encoding is not supported
+    Unsupported = 7,
+    /// A pointer or table passed by the host is invalid
+    InvalidArgument = 8,
+    /// Internal failure of the plugin (e.g. a result failed to serialize)
+    Internal = 9,
+    /// The call did not finish before its deadline and was cancelled
+    Timeout = 10,
+    /// The caller is not authenticated or lacks a required scope
+    Forbidden = 11,
+}
+
+impl PluginStatus {
+    /// Look up a status by its code
+    pub fn from_i32(code: i32) -> Option<PluginStatus> {
+        match code {
+            0 => Some(PluginStatus::Ok),
+            1 => Some(PluginStatus::HandlerError),
+            2 => Some(PluginStatus::AlreadyConfigured),
+            3 => Some(PluginStatus::InvalidJson),
+            4 => Some(PluginStatus::InvalidUtf8),
+            5 => Some(PluginStatus::NotFound),
+            6 => Some(PluginStatus::Panic),
+            7 => Some(PluginStatus::Unsupported),
+            8 => Some(PluginStatus::InvalidArgument),
+            9 => Some(PluginStatus::Internal),
+            10 => Some(PluginStatus::Timeout),
+            11 => Some(PluginStatus::Forbidden),
+            _ => None,
+        }
+    }
+
+    /// The code returned across the ABI
+    pub const fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Whether this is [`PluginStatus::Ok`]
+    pub const fn is_ok(self) -> bool {
+        matches!(self, PluginStatus::Ok)
+    }
+}
+
+impl std::fmt::Display for PluginStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            PluginStatus::Ok => "success",
+            PluginStatus::HandlerError => "handler error",
+            PluginStatus::AlreadyConfigured => "already configured",
+            PluginStatus::InvalidJson => "invalid JSON",
+            PluginStatus::InvalidUtf8 => "invalid UTF-8",
+            PluginStatus::NotFound => "not found",
+            PluginStatus::Panic => "panic",
+            PluginStatus::Unsupported => "unsupported",
+            PluginStatus::InvalidArgument => "invalid argument",
+            PluginStatus::Internal => "internal error",
+            PluginStatus::Timeout => "timeout",
+            PluginStatus::Forbidden => "forbidden",
+        };
+        f.write_str(text)
+    }
+}
 
 // ============================================================================
 // ABI Type Aliases - Single Source of Truth
@@ -87,7 +327,7 @@ pub use tool::{ParamType, Tool, ToolBuilder, ToolHandler, ToolParam};
 ///
 /// # Returns
 /// - 0 on success
-/// - Non-zero error code on failure
+/// - Non-zero [`PluginStatus`] on failure
 pub type ListToolsFn = unsafe extern "C" fn(*mut *mut u8, *mut usize) -> i32;
 
 /// Function signature for executing a tool by name
@@ -101,7 +341,7 @@ pub type ListToolsFn = unsafe extern "C" fn(*mut *mut u8, *mut usize) -> i32;
 ///
 /// # Returns
 /// - 0 on success
-/// - Non-zero error code on failure
+/// - Non-zero [`PluginStatus`] on failure
 pub type ExecuteToolFn = unsafe extern "C" fn(
     *const c_char, // tool name
     *const u8,     // args JSON
@@ -125,7 +365,7 @@ pub type FreeStringFn = unsafe extern "C" fn(*mut u8, usize);
 ///
 /// # Returns
 /// - 0 on success
-/// - Non-zero error code on failure
+/// - Non-zero [`PluginStatus`] on failure
 pub type ConfigureFn = unsafe extern "C" fn(*const u8, usize) -> i32;
 
 /// Function signature for plugin initialization
@@ -147,7 +387,7 @@ pub type ConfigureFn = unsafe extern "C" fn(*const u8, usize) -> i32;
 ///
 /// # Returns
 /// - 0 on success
-/// - Non-zero error code on failure
+/// - Non-zero [`PluginStatus`] on failure
 ///
 /// If initialization fails, the plugin should allocate an error message,
 /// write the pointer and length to the output parameters, and return non-zero.
@@ -179,23 +419,325 @@ pub type GetConfigSchemaFn = unsafe extern "C" fn(
     schema_len: *mut usize,
 ) -> i32;
 
+/// Function signature for listing available resources
+///
+/// Returns a JSON array of MCP resource definitions
+/// (`uri`, `name`, `description`, `mimeType`).
+///
+/// # Parameters
+/// - `result_buf`: Output pointer for JSON array (allocated by plugin)
+/// - `result_len`: Output capacity of buffer
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero [`PluginStatus`] on failure
+pub type ListResourcesFn = unsafe extern "C" fn(*mut *mut u8, *mut usize) -> i32;
+
+/// Function signature for reading a resource by URI
+///
+/// Returns a JSON object in the MCP `resources/read` result format:
+/// `{"contents": [{"uri": ..., "mimeType": ..., "text": ...}]}`.
+///
+/// # Parameters
+/// - `uri`: Null-terminated C string with the resource URI
+/// - `result_buf`: Output pointer for result (allocated by plugin)
+/// - `result_len`: Output capacity of result buffer
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero [`PluginStatus`] on failure
+pub type ReadResourceFn = unsafe extern "C" fn(
+    *const c_char, // resource URI
+    *mut *mut u8,  // result buffer (allocated by plugin)
+    *mut usize,    // result capacity
+) -> i32;
+
+/// Function signature for listing resource templates
+///
+/// Returns a JSON array of MCP resource template definitions
+/// (`uriTemplate`, `name`, `description`, `mimeType`). URIs matching a
+/// template are read through [`ReadResourceFn`].
+///
+/// # Parameters
+/// - `result_buf`: Output pointer for JSON array (allocated by plugin)
+/// - `result_len`: Output capacity of buffer
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero [`PluginStatus`] on failure
+pub type ListResourceTemplatesFn = unsafe extern "C" fn(*mut *mut u8, *mut usize) -> i32;
+
+/// Function signature for listing available prompts
+///
+/// Returns a JSON array of MCP prompt definitions
+/// (`name`, `description`, `arguments`).
+///
+/// # Parameters
+/// - `result_buf`: Output pointer for JSON array (allocated by plugin)
+/// - `result_len`: Output capacity of buffer
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero [`PluginStatus`] on failure
+pub type ListPromptsFn = unsafe extern "C" fn(*mut *mut u8, *mut usize) -> i32;
+
+/// Function signature for getting a prompt by name
+///
+/// Returns a JSON object in the MCP `prompts/get` result format:
+/// `{"description": ..., "messages": [{"role": ..., "content": {...}}]}`.
+///
+/// # Parameters
+/// - `prompt_name`: Null-terminated C string with prompt name
+/// - `args_json`: JSON object mapping argument names to string values
+/// - `args_len`: Length of args_json
+/// - `result_buf`: Output pointer for result (allocated by plugin)
+/// - `result_len`: Output capacity of result buffer
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero [`PluginStatus`] on failure
+pub type GetPromptFn = unsafe extern "C" fn(
+    *const c_char, // prompt name
+    *const u8,     // args JSON
+    usize,         // args length
+    *mut *mut u8,  // result buffer (allocated by plugin)
+    *mut usize,    // result capacity
+) -> i32;
+
+/// Function signature for argument completion
+///
+/// Takes the MCP `completion/complete` request parameters and returns the
+/// result, see [`completion`] for the formats.
+///
+/// # Parameters
+/// - `request_json`: JSON request parameters as byte array
+/// - `request_len`: Length of request_json
+/// - `result_buf`: Output pointer for result (allocated by plugin)
+/// - `result_len`: Output capacity of result buffer
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero [`PluginStatus`] on failure
+pub type CompleteFn = unsafe extern "C" fn(
+    *const u8,    // request JSON
+    usize,        // request length
+    *mut *mut u8, // result buffer (allocated by plugin)
+    *mut usize,   // result capacity
+) -> i32;
+
+/// Function signature for executing a tool with a call context
+///
+/// Like [`ExecuteToolFn`], with an additional JSON object describing the
+/// call. Handlers see it as a [`ToolContext`]; the host may put any routing
+/// information in it (e.g. `requestId`), which is passed back verbatim in
+/// host callbacks (see [`host`]).
+///
+/// # Parameters
+/// - `tool_name`: Null-terminated C string with tool name
+/// - `args_json`: JSON arguments as byte array
+/// - `args_len`: Length of args_json
+/// - `context_json`: JSON call context as byte array (may be empty)
+/// - `context_len`: Length of context_json
+/// - `result_buf`: Output pointer for result (allocated by plugin)
+/// - `result_len`: Output capacity of result buffer
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero [`PluginStatus`] on failure
+pub type ExecuteToolWithContextFn = unsafe extern "C" fn(
+    *const c_char, // tool name
+    *const u8,     // args JSON
+    usize,         // args length
+    *const u8,     // context JSON
+    usize,         // context length
+    *mut *mut u8,  // result buffer (allocated by plugin)
+    *mut usize,    // result capacity
+) -> i32;
+
+/// Function signature for executing a tool with a streamed result
+///
+/// Like [`ExecuteToolWithContextFn`], but chunks produced by the tool are
+/// pushed to `sink` while it runs (see [`stream`]). The final value or error
+/// is returned through the result buffer. Tools without a streaming handler
+/// write no chunks.
+///
+/// # Parameters
+/// - `tool_name`: Null-terminated C string with tool name
+/// - `args_json`: JSON arguments as byte array
+/// - `args_len`: Length of args_json
+/// - `context_json`: JSON call context as byte array (may be empty)
+/// - `context_len`: Length of context_json
+/// - `sink`: Destination for chunks; must stay valid for the whole call
+/// - `result_buf`: Output pointer for result (allocated by plugin)
+/// - `result_len`: Output capacity of result buffer
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero [`PluginStatus`] on failure
+pub type ExecuteToolStreamFn = unsafe extern "C" fn(
+    *const c_char,     // tool name
+    *const u8,         // args JSON
+    usize,             // args length
+    *const u8,         // context JSON
+    usize,             // context length
+    *const StreamSink, // chunk sink
+    *mut *mut u8,      // result buffer (allocated by plugin)
+    *mut usize,        // result capacity
+) -> i32;
+
+/// Function signature for getting the hash of the tool list
+///
+/// Returns a value that changes whenever the output of `list_tools` changes
+/// (an etag). Hosts can compare it to skip re-reading the tool list; `0`
+/// means the plugin cannot tell.
+pub type ToolsEtagFn = unsafe extern "C" fn() -> u64;
+
+/// Function signature for getting the last error message
+///
+/// Returns the message of the last failure on the calling thread, for entry
+/// points without an output buffer (e.g. `configure`, `set_host_callbacks`).
+/// Every other entry point clears it when called, so the message always
+/// belongs to the latest call on that thread.
+///
+/// # Parameters
+/// - `result_buf`: Output pointer for the UTF-8 message (allocated by plugin)
+/// - `result_len`: Output capacity of buffer
+///
+/// # Returns
+/// - 0 ([`PluginStatus::Ok`]) with the message
+/// - [`PluginStatus::NotFound`] if there was no error; nothing is allocated
+pub type LastErrorFn = unsafe extern "C" fn(*mut *mut u8, *mut usize) -> i32;
+
+/// Function signature for session lifecycle hooks
+///
+/// Called with the session id (the `"sessionId"` of later call contexts)
+/// when a session starts or ends; see [`session`].
+///
+/// # Parameters
+/// - `session_id`: Null-terminated C string with the session id
+///
+/// # Returns
+/// - 0 on success
+/// - Non-zero [`PluginStatus`] on failure (message via `last_error`)
+pub type SessionHookFn = unsafe extern "C" fn(*const c_char) -> i32;
+
 // ============================================================================
 // Plugin Declaration
 // ============================================================================
 
+/// Revision of the [`PluginDeclaration`] layout
+///
+/// Incremented every time a field is appended to the declaration. A plugin
+/// records the revision it was built with in [`PluginDeclaration::abi_revision`].
+pub const ABI_REVISION: u32 = 13;
+
+/// Bitset of optional features a plugin provides
+///
+/// Each optional field of [`PluginDeclaration`] has a matching bit, so a host
+/// can check what a plugin supports without touching the fields themselves.
+/// Bits are never reused or reassigned.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Capabilities(pub u64);
+
+impl Capabilities {
+    /// No optional features
+    pub const NONE: Capabilities = Capabilities(0);
+    /// `configure` is present
+    pub const CONFIGURE: Capabilities = Capabilities(1 << 0);
+    /// `init` is present
+    pub const INIT: Capabilities = Capabilities(1 << 1);
+    /// `get_config_schema` is present
+    pub const CONFIG_SCHEMA: Capabilities = Capabilities(1 << 2);
+    /// `list_resources` and `read_resource` are present
+    pub const RESOURCES: Capabilities = Capabilities(1 << 3);
+    /// `list_resource_templates` is present
+    pub const RESOURCE_TEMPLATES: Capabilities = Capabilities(1 << 4);
+    /// `list_prompts` and `get_prompt` are present
+    pub const PROMPTS: Capabilities = Capabilities(1 << 5);
+    /// `complete` is present
+    pub const COMPLETIONS: Capabilities = Capabilities(1 << 6);
+    /// `set_host_callbacks` is present
+    pub const HOST_CALLBACKS: Capabilities = Capabilities(1 << 7);
+    /// `execute_tool_with_context` is present
+    pub const CALL_CONTEXT: Capabilities = Capabilities(1 << 8);
+    /// `execute_tool_stream` is present
+    pub const STREAMING: Capabilities = Capabilities(1 << 9);
+    /// `set_wire_encoding` is present
+    pub const WIRE_ENCODING: Capabilities = Capabilities(1 << 10);
+    /// `tools_etag` is present
+    pub const TOOLS_ETAG: Capabilities = Capabilities(1 << 11);
+    /// `last_error` is present
+    pub const LAST_ERROR: Capabilities = Capabilities(1 << 12);
+    /// `on_session_start` and `on_session_end` are present
+    pub const SESSIONS: Capabilities = Capabilities(1 << 13);
+    /// `create_instance`, `destroy_instance`, `instance_list_tools` and
+    /// `instance_execute_tool` are present
+    pub const INSTANCES: Capabilities = Capabilities(1 << 14);
+    /// `get_metrics` is present
+    pub const METRICS: Capabilities = Capabilities(1 << 15);
+
+    /// Check whether all bits of `other` are set
+    pub const fn contains(self, other: Capabilities) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Combine two capability sets
+    pub const fn union(self, other: Capabilities) -> Capabilities {
+        Capabilities(self.0 | other.0)
+    }
+}
+
+impl std::ops::BitOr for Capabilities {
+    type Output = Capabilities;
+
+    fn bitor(self, rhs: Capabilities) -> Capabilities {
+        self.union(rhs)
+    }
+}
+
 /// Plugin declaration exported by each plugin
 ///
 /// This structure must be exported as a static with the name `plugin_declaration`.
 /// Use the `declare_plugin!` macro for automatic version management.
+///
+/// # Layout Evolution
+///
+/// The declaration is self-describing so that hosts and plugins built against
+/// different versions of this crate can still talk to each other:
+///
+/// 1. The header (`api_version`, `struct_size`, `abi_revision`, `capabilities`)
+///    is frozen. Its offsets never change.
+/// 2. Existing fields are never removed, reordered or resized.
+/// 3. New fields are only appended at the end, and must be valid when all
+///    bytes are zero (`Option<fn>`, integers, nullable pointers).
+/// 4. Every append bumps [`ABI_REVISION`] and, for optional features, adds a
+///    new [`Capabilities`] bit.
+///
+/// Hosts should never dereference a loaded declaration directly, because an
+/// older plugin's static may be shorter than this struct. Use
+/// [`PluginDeclaration::read`], which copies only the bytes the plugin
+/// actually has and leaves the remaining (newer) fields zeroed, i.e. `None`.
 #[repr(C)]
 pub struct PluginDeclaration {
-    /// MCP Plugin API version the plugin was built against (e.g., "0.1.0")
+    /// MCP Plugin API version the plugin was built against (e.g., "0.2.0")
     ///
     /// This is automatically set from the mcp-plugin-api crate version.
     /// The C ABI is stable across Rust compiler versions, so only the API
     /// version matters for compatibility checking.
     pub api_version: *const u8,
 
+    /// Size in bytes of the declaration as compiled into the plugin
+    ///
+    /// Set to `size_of::<PluginDeclaration>()` by `declare_plugin!`.
+    pub struct_size: usize,
+
+    /// Layout revision the plugin was built with (see [`ABI_REVISION`])
+    pub abi_revision: u32,
+
+    /// Optional features provided by the plugin (see [`Capabilities`])
+    pub capabilities: Capabilities,
+
     /// Returns list of tools as JSON array
     ///
     /// See [`ListToolsFn`] for details.
@@ -225,11 +767,396 @@ pub struct PluginDeclaration {
     ///
     /// See [`GetConfigSchemaFn`] for details.
     pub get_config_schema: Option<GetConfigSchemaFn>,
+
+    /// Optional function listing the plugin's resources (revision 2)
+    ///
+    /// See [`ListResourcesFn`] for details.
+    pub list_resources: Option<ListResourcesFn>,
+
+    /// Optional function reading a resource by URI (revision 2)
+    ///
+    /// See [`ReadResourceFn`] for details.
+    pub read_resource: Option<ReadResourceFn>,
+
+    /// Optional function listing the plugin's resource templates (revision 3)
+    ///
+    /// See [`ListResourceTemplatesFn`] for details.
+    pub list_resource_templates: Option<ListResourceTemplatesFn>,
+
+    /// Optional function listing the plugin's prompts (revision 4)
+    ///
+    /// See [`ListPromptsFn`] for details.
+    pub list_prompts: Option<ListPromptsFn>,
+
+    /// Optional function getting a prompt by name (revision 4)
+    ///
+    /// See [`GetPromptFn`] for details.
+    pub get_prompt: Option<GetPromptFn>,
+
+    /// Optional argument completion function (revision 5)
+    ///
+    /// See [`CompleteFn`] for details.
+    pub complete: Option<CompleteFn>,
+
+    /// Optional function receiving the host callbacks (revision 6)
+    ///
+    /// See [`SetHostCallbacksFn`] for details.
+    pub set_host_callbacks: Option<SetHostCallbacksFn>,
+
+    /// Optional tool execution with a call context (revision 6)
+    ///
+    /// Hosts should prefer this over `execute_tool` when present.
+    /// See [`ExecuteToolWithContextFn`] for details.
+    pub execute_tool_with_context: Option<ExecuteToolWithContextFn>,
+
+    /// Optional tool execution with a streamed result (revision 7)
+    ///
+    /// See [`ExecuteToolStreamFn`] for details.
+    pub execute_tool_stream: Option<ExecuteToolStreamFn>,
+
+    /// Bitset of supported wire encodings (revision 8)
+    ///
+    /// One bit per [`WireEncoding`], see [`WireEncoding::bit`]. Zero (an
+    /// older plugin) means JSON only.
+    pub wire_encodings: u64,
+
+    /// Optional function selecting the wire encoding (revision 8)
+    ///
+    /// `None` for plugins built without a binary encoding. See
+    /// [`SetWireEncodingFn`] for details.
+    pub set_wire_encoding: Option<SetWireEncodingFn>,
+
+    /// Optional function returning the hash of the tool list (revision 9)
+    ///
+    /// See [`ToolsEtagFn`] for details.
+    pub tools_etag: Option<ToolsEtagFn>,
+
+    /// Optional function returning the last error message (revision 10)
+    ///
+    /// See [`LastErrorFn`] for details.
+    pub last_error: Option<LastErrorFn>,
+
+    /// Optional hook called when a session starts (revision 11)
+    ///
+    /// See [`SessionHookFn`] for details.
+    pub on_session_start: Option<SessionHookFn>,
+
+    /// Optional hook called when a session ends (revision 11)
+    ///
+    /// See [`SessionHookFn`] for details.
+    pub on_session_end: Option<SessionHookFn>,
+
+    /// Optional function creating a configured instance (revision 12)
+    ///
+    /// See [`CreateInstanceFn`] for details.
+    pub create_instance: Option<CreateInstanceFn>,
+
+    /// Optional function releasing an instance (revision 12)
+    ///
+    /// See [`DestroyInstanceFn`] for details.
+    pub destroy_instance: Option<DestroyInstanceFn>,
+
+    /// Optional function listing the tools of an instance (revision 12)
+    ///
+    /// See [`InstanceListToolsFn`] for details.
+    pub instance_list_tools: Option<InstanceListToolsFn>,
+
+    /// Optional function executing a tool of an instance (revision 12)
+    ///
+    /// See [`InstanceExecuteToolFn`] for details.
+    pub instance_execute_tool: Option<InstanceExecuteToolFn>,
+
+    /// Optional function returning the plugin's metrics (revision 13)
+    ///
+    /// See [`GetMetricsFn`] for details.
+    pub get_metrics: Option<GetMetricsFn>,
 }
 
 // Safety: The static is initialized with constant values and never modified
 unsafe impl Sync for PluginDeclaration {}
 
+/// Error returned by [`PluginDeclaration::read`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeclarationError {
+    /// The declaration pointer was null
+    NullPointer,
+    /// The declaration is too small to contain the mandatory fields
+    TooSmall {
+        /// Size reported by the plugin
+        struct_size: usize,
+    },
+    /// The reported size is implausibly large, e.g. not a size at all
+    TooLarge {
+        /// Size reported by the plugin
+        struct_size: usize,
+    },
+}
+
+impl std::fmt::Display for DeclarationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeclarationError::NullPointer => write!(f, "plugin declaration is null"),
+            DeclarationError::TooSmall { struct_size } => write!(
+                f,
+                "plugin declaration is {} bytes, at least {} required",
+                struct_size,
+                PluginDeclaration::MIN_SIZE
+            ),
+            DeclarationError::TooLarge { struct_size } => write!(
+                f,
+                "plugin declaration claims {} bytes, at most {} accepted",
+                struct_size,
+                PluginDeclaration::MAX_SIZE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DeclarationError {}
+
+impl PluginDeclaration {
+    /// Size of the frozen header shared by all revisions
+    pub const HEADER_SIZE: usize = std::mem::offset_of!(PluginDeclaration, list_tools);
+
+    /// Smallest valid declaration: the header plus the mandatory functions
+    pub const MIN_SIZE: usize = std::mem::offset_of!(PluginDeclaration, configure);
+
+    /// Size of the declaration in this version of the crate
+    pub const SIZE: usize = std::mem::size_of::<PluginDeclaration>();
+
+    /// Largest `struct_size` accepted by [`read`](Self::read)
+    ///
+    /// Far beyond any foreseeable revision; a larger size is taken to be
+    /// garbage rather than a newer layout.
+    pub const MAX_SIZE: usize = 4096;
+
+    /// Read a plugin's declaration, tolerating older (shorter) layouts
+    ///
+    /// Only the first `struct_size` bytes reported by the plugin are copied.
+    /// Fields the plugin does not have are left zeroed, so optional functions
+    /// appended after the plugin was built read as `None`. Trailing bytes of
+    /// newer plugins are ignored.
+    ///
+    /// Check the plugin's API version before calling this. Declarations of
+    /// API 0.1 predate the size-prefixed layout and have `list_tools` where
+    /// `struct_size` is now. `api_version` is the first field in every layout
+    /// and can be checked on its own with [`compat::check_plugin`], which
+    /// rejects 0.1 plugins. As a last line of defence, sizes outside
+    /// [`MIN_SIZE`](Self::MIN_SIZE)..=[`MAX_SIZE`](Self::MAX_SIZE) are
+    /// rejected.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be null or point to a `plugin_declaration` static exported by
+    /// a plugin with a compatible API version, valid for reads of its reported
+    /// `struct_size` bytes.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let symbol: *const PluginDeclaration = *library.get(b"plugin_declaration")?;
+    /// if !unsafe { compat::check_plugin(symbol)? }.is_compatible() {
+    ///     return Err("incompatible plugin".into());
+    /// }
+    /// let decl = unsafe { PluginDeclaration::read(symbol)? };
+    /// if let Some(configure) = decl.configure {
+    ///     // ...
+    /// }
+    /// ```
+    pub unsafe fn read(ptr: *const PluginDeclaration) -> Result<PluginDeclaration, DeclarationError> {
+        if ptr.is_null() {
+            return Err(DeclarationError::NullPointer);
+        }
+
+        let struct_size = std::ptr::read_unaligned(std::ptr::addr_of!((*ptr).struct_size));
+        if struct_size < Self::MIN_SIZE {
+            return Err(DeclarationError::TooSmall { struct_size });
+        }
+        if struct_size > Self::MAX_SIZE {
+            return Err(DeclarationError::TooLarge { struct_size });
+        }
+
+        // All fields past MIN_SIZE are valid when zeroed (rule 3 above)
+        let mut decl = std::mem::MaybeUninit::<PluginDeclaration>::zeroed();
+        std::ptr::copy_nonoverlapping(
+            ptr as *const u8,
+            decl.as_mut_ptr() as *mut u8,
+            struct_size.min(Self::SIZE),
+        );
+        Ok(decl.assume_init())
+    }
+
+    /// Check whether the plugin's declaration is large enough to contain a field
+    ///
+    /// `field_end` is the offset just past the field, e.g.
+    /// `offset_of!(PluginDeclaration, init) + size_of::<Option<InitFn>>()`.
+    pub fn has_field(&self, field_end: usize) -> bool {
+        self.struct_size >= field_end
+    }
+}
+
+// Layout assertions: these fail the build if a change breaks the evolution
+// rules documented on `PluginDeclaration`. Append new fields below, never
+// edit existing offsets.
+const _: () = {
+    use std::mem::{align_of, offset_of, size_of};
+
+    assert!(offset_of!(PluginDeclaration, api_version) == 0);
+    assert!(offset_of!(PluginDeclaration, struct_size) == size_of::<*const u8>());
+    assert!(
+        offset_of!(PluginDeclaration, abi_revision)
+            == offset_of!(PluginDeclaration, struct_size) + size_of::<usize>()
+    );
+    assert!(offset_of!(PluginDeclaration, capabilities) % align_of::<u64>() == 0);
+    assert!(offset_of!(PluginDeclaration, capabilities) > offset_of!(PluginDeclaration, abi_revision));
+    assert!(size_of::<Option<ConfigureFn>>() == size_of::<usize>());
+    assert!(size_of::<Option<InitFn>>() == size_of::<usize>());
+    assert!(size_of::<Option<GetConfigSchemaFn>>() == size_of::<usize>());
+    assert!(size_of::<Option<ListResourcesFn>>() == size_of::<usize>());
+    assert!(size_of::<Option<ReadResourceFn>>() == size_of::<usize>());
+    assert!(size_of::<Option<ListResourceTemplatesFn>>() == size_of::<usize>());
+    assert!(size_of::<Option<ListPromptsFn>>() == size_of::<usize>());
+    assert!(size_of::<Option<GetPromptFn>>() == size_of::<usize>());
+    assert!(size_of::<Option<CompleteFn>>() == size_of::<usize>());
+    assert!(size_of::<Option<SetHostCallbacksFn>>() == size_of::<usize>());
+    assert!(size_of::<Option<ExecuteToolWithContextFn>>() == size_of::<usize>());
+
+    // Revision 1
+    assert!(offset_of!(PluginDeclaration, configure) == PluginDeclaration::MIN_SIZE);
+    assert!(
+        offset_of!(PluginDeclaration, init)
+            == offset_of!(PluginDeclaration, configure) + size_of::<usize>()
+    );
+    assert!(
+        offset_of!(PluginDeclaration, get_config_schema)
+            == offset_of!(PluginDeclaration, init) + size_of::<usize>()
+    );
+
+    // Revision 2
+    assert!(
+        offset_of!(PluginDeclaration, list_resources)
+            == offset_of!(PluginDeclaration, get_config_schema) + size_of::<usize>()
+    );
+    assert!(
+        offset_of!(PluginDeclaration, read_resource)
+            == offset_of!(PluginDeclaration, list_resources) + size_of::<usize>()
+    );
+
+    // Revision 3
+    assert!(
+        offset_of!(PluginDeclaration, list_resource_templates)
+            == offset_of!(PluginDeclaration, read_resource) + size_of::<usize>()
+    );
+
+    // Revision 4
+    assert!(
+        offset_of!(PluginDeclaration, list_prompts)
+            == offset_of!(PluginDeclaration, list_resource_templates) + size_of::<usize>()
+    );
+    assert!(
+        offset_of!(PluginDeclaration, get_prompt)
+            == offset_of!(PluginDeclaration, list_prompts) + size_of::<usize>()
+    );
+
+    // Revision 5
+    assert!(
+        offset_of!(PluginDeclaration, complete)
+            == offset_of!(PluginDeclaration, get_prompt) + size_of::<usize>()
+    );
+
+    // Revision 6
+    assert!(
+        offset_of!(PluginDeclaration, set_host_callbacks)
+            == offset_of!(PluginDeclaration, complete) + size_of::<usize>()
+    );
+    assert!(
+        offset_of!(PluginDeclaration, execute_tool_with_context)
+            == offset_of!(PluginDeclaration, set_host_callbacks) + size_of::<usize>()
+    );
+
+    // Revision 7
+    assert!(
+        offset_of!(PluginDeclaration, execute_tool_stream)
+            == offset_of!(PluginDeclaration, execute_tool_with_context) + size_of::<usize>()
+    );
+
+    // Revision 8
+    assert!(
+        offset_of!(PluginDeclaration, wire_encodings)
+            == (offset_of!(PluginDeclaration, execute_tool_stream) + size_of::<usize>())
+                .next_multiple_of(align_of::<u64>())
+    );
+    assert!(offset_of!(PluginDeclaration, wire_encodings) % align_of::<u64>() == 0);
+    assert!(
+        offset_of!(PluginDeclaration, set_wire_encoding)
+            == offset_of!(PluginDeclaration, wire_encodings) + size_of::<u64>()
+    );
+
+    // Revision 9
+    assert!(
+        offset_of!(PluginDeclaration, tools_etag)
+            == offset_of!(PluginDeclaration, set_wire_encoding) + size_of::<usize>()
+    );
+
+    // Revision 10
+    assert!(
+        offset_of!(PluginDeclaration, last_error)
+            == offset_of!(PluginDeclaration, tools_etag) + size_of::<usize>()
+    );
+
+    // Revision 11
+    assert!(
+        offset_of!(PluginDeclaration, on_session_start)
+            == offset_of!(PluginDeclaration, last_error) + size_of::<usize>()
+    );
+    assert!(
+        offset_of!(PluginDeclaration, on_session_end)
+            == offset_of!(PluginDeclaration, on_session_start) + size_of::<usize>()
+    );
+
+    // Revision 12
+    assert!(
+        offset_of!(PluginDeclaration, create_instance)
+            == offset_of!(PluginDeclaration, on_session_end) + size_of::<usize>()
+    );
+    assert!(
+        offset_of!(PluginDeclaration, destroy_instance)
+            == offset_of!(PluginDeclaration, create_instance) + size_of::<usize>()
+    );
+    assert!(
+        offset_of!(PluginDeclaration, instance_list_tools)
+            == offset_of!(PluginDeclaration, destroy_instance) + size_of::<usize>()
+    );
+    assert!(
+        offset_of!(PluginDeclaration, instance_execute_tool)
+            == offset_of!(PluginDeclaration, instance_list_tools) + size_of::<usize>()
+    );
+
+    // Revision 13
+    assert!(
+        offset_of!(PluginDeclaration, get_metrics)
+            == offset_of!(PluginDeclaration, instance_execute_tool) + size_of::<usize>()
+    );
+
+    // End of the current revision
+    assert!(
+        PluginDeclaration::SIZE
+            == offset_of!(PluginDeclaration, get_metrics) + size_of::<usize>()
+    );
+};
+
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    use std::mem::offset_of;
+
+    assert!(offset_of!(PluginDeclaration, struct_size) == 8);
+    assert!(offset_of!(PluginDeclaration, abi_revision) == 16);
+    assert!(offset_of!(PluginDeclaration, capabilities) == 24);
+    assert!(PluginDeclaration::HEADER_SIZE == 32);
+    assert!(PluginDeclaration::MIN_SIZE == 56);
+    assert!(PluginDeclaration::SIZE == 240);
+};
+
 /// Current MCP Plugin API version (from Cargo.toml at compile time)
 pub const API_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -238,6 +1165,13 @@ pub const API_VERSION_CSTR: &[u8] = concat!(env!("CARGO_PKG_VERSION"), "\0").as_
 
 /// Helper macro to declare a plugin with automatic version management
 ///
+/// The supported wire encodings and `set_wire_encoding` are filled in
+/// automatically from the enabled cargo features (see [`encoding`]); without
+/// `msgpack` or `cbor`, `set_wire_encoding` is `None` and the
+/// `WIRE_ENCODING` capability is not declared. `last_error` is always set to
+/// `utils::standard_last_error` and `get_metrics` to
+/// `metrics::standard_get_metrics`.
+///
 /// # Example
 ///
 /// ```ignore
@@ -266,6 +1200,72 @@ pub const API_VERSION_CSTR: &[u8] = concat!(env!("CARGO_PKG_VERSION"), "\0").as_
 ///     configure: my_configure,
 ///     init: my_init
 /// }
+///
+/// // With resources (see `declare_resources!`)
+/// declare_plugin! {
+///     list_tools: generated_list_tools,
+///     execute_tool: generated_execute_tool,
+///     free_string: my_free_string,
+///     list_resources: generated_list_resources,
+///     read_resource: generated_read_resource,
+///     list_resource_templates: generated_list_resource_templates
+/// }
+///
+/// // With prompts (see `declare_prompts!`)
+/// declare_plugin! {
+///     list_tools: generated_list_tools,
+///     execute_tool: generated_execute_tool,
+///     free_string: my_free_string,
+///     list_prompts: generated_list_prompts,
+///     get_prompt: generated_get_prompt,
+///     complete: generated_complete  // see `declare_completions!`
+/// }
+///
+/// // With host callbacks and call context (sampling)
+/// declare_plugin! {
+///     list_tools: generated_list_tools,
+///     execute_tool: generated_execute_tool,
+///     free_string: my_free_string,
+///     set_host_callbacks: mcp_plugin_api::host::standard_set_host_callbacks,
+///     execute_tool_with_context: generated_execute_tool_with_context
+/// }
+///
+/// // With streamed results (see `ToolBuilder::streaming_handler`)
+/// declare_plugin! {
+///     list_tools: generated_list_tools,
+///     execute_tool: generated_execute_tool,
+///     free_string: my_free_string,
+///     execute_tool_stream: generated_execute_tool_stream
+/// }
+///
+/// // With a tool list etag (see `declare_tools!`)
+/// declare_plugin! {
+///     list_tools: generated_list_tools,
+///     execute_tool: generated_execute_tool,
+///     free_string: my_free_string,
+///     tools_etag: generated_tools_etag
+/// }
+///
+/// // With session lifecycle hooks (see `declare_session_hooks!`)
+/// declare_plugin! {
+///     list_tools: generated_list_tools,
+///     execute_tool: generated_execute_tool,
+///     free_string: my_free_string,
+///     on_session_start: plugin_session_start,
+///     on_session_end: plugin_session_end
+/// }
+///
+/// // With instances (see `declare_plugin_config!` and `declare_tools!`)
+/// declare_plugin! {
+///     list_tools: generated_list_tools,
+///     execute_tool: generated_execute_tool,
+///     free_string: my_free_string,
+///     configure: plugin_configure,
+///     create_instance: plugin_create_instance,
+///     destroy_instance: plugin_destroy_instance,
+///     instance_list_tools: generated_instance_list_tools,
+///     instance_execute_tool: generated_instance_execute_tool
+/// }
 /// ```
 #[macro_export]
 macro_rules! declare_plugin {
@@ -276,16 +1276,70 @@ macro_rules! declare_plugin {
         $(, configure: $configure_fn:expr)?
         $(, init: $init_fn:expr)?
         $(, get_config_schema: $schema_fn:expr)?
+        $(, list_resources: $list_resources_fn:expr, read_resource: $read_resource_fn:expr)?
+        $(, list_resource_templates: $list_templates_fn:expr)?
+        $(, list_prompts: $list_prompts_fn:expr, get_prompt: $get_prompt_fn:expr)?
+        $(, complete: $complete_fn:expr)?
+        $(, set_host_callbacks: $set_host_fn:expr)?
+        $(, execute_tool_with_context: $execute_ctx_fn:expr)?
+        $(, execute_tool_stream: $execute_stream_fn:expr)?
+        $(, tools_etag: $tools_etag_fn:expr)?
+        $(, on_session_start: $session_start_fn:expr, on_session_end: $session_end_fn:expr)?
+        $(
+            , create_instance: $create_instance_fn:expr
+            , destroy_instance: $destroy_instance_fn:expr
+            , instance_list_tools: $instance_list_fn:expr
+            , instance_execute_tool: $instance_execute_fn:expr
+        )?
     ) => {
         #[no_mangle]
         pub static plugin_declaration: $crate::PluginDeclaration = $crate::PluginDeclaration {
             api_version: $crate::API_VERSION_CSTR.as_ptr(),
+            struct_size: ::std::mem::size_of::<$crate::PluginDeclaration>(),
+            abi_revision: $crate::ABI_REVISION,
+            capabilities: $crate::Capabilities::NONE
+                .union($crate::__declare_plugin_capability!(CONFIGURE; $($configure_fn)?))
+                .union($crate::__declare_plugin_capability!(INIT; $($init_fn)?))
+                .union($crate::__declare_plugin_capability!(CONFIG_SCHEMA; $($schema_fn)?))
+                .union($crate::__declare_plugin_capability!(RESOURCES; $($list_resources_fn)?))
+                .union($crate::__declare_plugin_capability!(RESOURCE_TEMPLATES; $($list_templates_fn)?))
+                .union($crate::__declare_plugin_capability!(PROMPTS; $($list_prompts_fn)?))
+                .union($crate::__declare_plugin_capability!(COMPLETIONS; $($complete_fn)?))
+                .union($crate::__declare_plugin_capability!(HOST_CALLBACKS; $($set_host_fn)?))
+                .union($crate::__declare_plugin_capability!(CALL_CONTEXT; $($execute_ctx_fn)?))
+                .union($crate::__declare_plugin_capability!(STREAMING; $($execute_stream_fn)?))
+                .union($crate::encoding::CAPABILITY)
+                .union($crate::__declare_plugin_capability!(TOOLS_ETAG; $($tools_etag_fn)?))
+                .union($crate::Capabilities::LAST_ERROR)
+                .union($crate::__declare_plugin_capability!(SESSIONS; $($session_start_fn)?))
+                .union($crate::__declare_plugin_capability!(INSTANCES; $($create_instance_fn)?))
+                .union($crate::Capabilities::METRICS),
             list_tools: $list_fn,
             execute_tool: $execute_fn,
             free_string: $free_fn,
             configure: $crate::__declare_plugin_option!($($configure_fn)?),
             init: $crate::__declare_plugin_option!($($init_fn)?),
             get_config_schema: $crate::__declare_plugin_option!($($schema_fn)?),
+            list_resources: $crate::__declare_plugin_option!($($list_resources_fn)?),
+            read_resource: $crate::__declare_plugin_option!($($read_resource_fn)?),
+            list_resource_templates: $crate::__declare_plugin_option!($($list_templates_fn)?),
+            list_prompts: $crate::__declare_plugin_option!($($list_prompts_fn)?),
+            get_prompt: $crate::__declare_plugin_option!($($get_prompt_fn)?),
+            complete: $crate::__declare_plugin_option!($($complete_fn)?),
+            set_host_callbacks: $crate::__declare_plugin_option!($($set_host_fn)?),
+            execute_tool_with_context: $crate::__declare_plugin_option!($($execute_ctx_fn)?),
+            execute_tool_stream: $crate::__declare_plugin_option!($($execute_stream_fn)?),
+            wire_encodings: $crate::encoding::SUPPORTED,
+            set_wire_encoding: $crate::encoding::SET_WIRE_ENCODING,
+            tools_etag: $crate::__declare_plugin_option!($($tools_etag_fn)?),
+            last_error: Some($crate::utils::standard_last_error),
+            on_session_start: $crate::__declare_plugin_option!($($session_start_fn)?),
+            on_session_end: $crate::__declare_plugin_option!($($session_end_fn)?),
+            create_instance: $crate::__declare_plugin_option!($($create_instance_fn)?),
+            destroy_instance: $crate::__declare_plugin_option!($($destroy_instance_fn)?),
+            instance_list_tools: $crate::__declare_plugin_option!($($instance_list_fn)?),
+            instance_execute_tool: $crate::__declare_plugin_option!($($instance_execute_fn)?),
+            get_metrics: Some($crate::metrics::standard_get_metrics),
         };
     };
 }
@@ -302,6 +1356,18 @@ macro_rules! __declare_plugin_option {
     };
 }
 
+/// Helper macro mapping an optional parameter of declare_plugin! to its capability bit
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __declare_plugin_capability {
+    ($cap:ident; $value:expr) => {
+        $crate::Capabilities::$cap
+    };
+    ($cap:ident;) => {
+        $crate::Capabilities::NONE
+    };
+}
+
 /// Declare a plugin initialization function with automatic wrapper generation
 ///
 /// This macro takes a native Rust function and wraps it as an `extern "C"` function
@@ -366,16 +1432,136 @@ macro_rules! declare_plugin_init {
             error_msg_ptr: *mut *mut ::std::primitive::u8,
             error_msg_len: *mut ::std::primitive::usize,
         ) -> ::std::primitive::i32 {
-            match $native_fn() {
-                ::std::result::Result::Ok(_) => 0, // Success
-                ::std::result::Result::Err(e) => {
+            $crate::utils::clear_last_error();
+            match $crate::utils::catch_panic($native_fn) {
+                ::std::result::Result::Ok(::std::result::Result::Ok(_)) => {
+                    $crate::PluginStatus::Ok.code()
+                }
+                ::std::result::Result::Ok(::std::result::Result::Err(e)) => {
                     $crate::utils::return_error(&e, error_msg_ptr, error_msg_len)
                 }
+                ::std::result::Result::Err(panic) => $crate::utils::return_error_status(
+                    $crate::PluginStatus::Panic,
+                    &::std::format!("Plugin init panicked: {}", panic),
+                    error_msg_ptr,
+                    error_msg_len,
+                ),
             }
         }
     };
 }
 
+/// Declare session lifecycle hooks with automatic wrapper generation
+///
+/// Generates `plugin_session_start` and `plugin_session_end` for
+/// `declare_plugin!`. They open and close the [`session::Session`] and run the
+/// native hooks, which are both optional:
+///
+/// - `on_start: fn(&Session) -> Result<(), String>` runs after the session was
+///   opened; on error, the session is closed again.
+/// - `on_end: fn(&Session)` runs before the session is closed, e.g. to
+///   release connections. The stored values are dropped afterwards.
+///
+/// # Example
+///
+/// ```ignore
+/// fn session_started(session: &Session) -> Result<(), String> {
+///     session.insert("scratch_dir", create_scratch_dir(session.id())?);
+///     Ok(())
+/// }
+///
+/// fn session_ended(session: &Session) {
+///     if let Some(dir) = session.get::<PathBuf>("scratch_dir") {
+///         let _ = std::fs::remove_dir_all(dir);
+///     }
+/// }
+///
+/// declare_session_hooks! {
+///     on_start: session_started,
+///     on_end: session_ended
+/// }
+///
+/// declare_plugin! {
+///     list_tools: generated_list_tools,
+///     execute_tool: generated_execute_tool,
+///     free_string: utils::standard_free_string,
+///     on_session_start: plugin_session_start,
+///     on_session_end: plugin_session_end
+/// }
+/// ```
+#[macro_export]
+macro_rules! declare_session_hooks {
+    (on_start: $start_fn:expr, on_end: $end_fn:expr $(,)?) => {
+        $crate::declare_session_hooks!(@generate [$start_fn] [$end_fn]);
+    };
+    (on_start: $start_fn:expr $(,)?) => {
+        $crate::declare_session_hooks!(@generate [$start_fn] []);
+    };
+    (on_end: $end_fn:expr $(,)?) => {
+        $crate::declare_session_hooks!(@generate [] [$end_fn]);
+    };
+    (@generate [$($start_fn:expr)?] [$($end_fn:expr)?]) => {
+        /// Auto-generated on_session_start function
+        #[no_mangle]
+        pub unsafe extern "C" fn plugin_session_start(
+            session_id: *const ::std::os::raw::c_char,
+        ) -> ::std::primitive::i32 {
+            $crate::utils::clear_last_error();
+            let id = match $crate::session::session_id_from_ptr(session_id) {
+                ::std::result::Result::Ok(id) => id,
+                ::std::result::Result::Err(status) => return status.code(),
+            };
+            let _session = $crate::session::open(id);
+
+            $(
+                let result = $crate::utils::catch_panic(|| $start_fn(&_session));
+                let status = match result {
+                    ::std::result::Result::Ok(::std::result::Result::Ok(())) => $crate::PluginStatus::Ok,
+                    ::std::result::Result::Ok(::std::result::Result::Err(e)) => {
+                        $crate::utils::set_last_error(e);
+                        $crate::PluginStatus::HandlerError
+                    }
+                    ::std::result::Result::Err(panic) => {
+                        $crate::utils::set_last_error(::std::format!("Session start hook panicked: {}", panic));
+                        $crate::PluginStatus::Panic
+                    }
+                };
+                if !status.is_ok() {
+                    $crate::session::close(id);
+                    return status.code();
+                }
+            )?
+
+            $crate::PluginStatus::Ok.code()
+        }
+
+        /// Auto-generated on_session_end function
+        #[no_mangle]
+        pub unsafe extern "C" fn plugin_session_end(
+            session_id: *const ::std::os::raw::c_char,
+        ) -> ::std::primitive::i32 {
+            $crate::utils::clear_last_error();
+            let id = match $crate::session::session_id_from_ptr(session_id) {
+                ::std::result::Result::Ok(id) => id,
+                ::std::result::Result::Err(status) => return status.code(),
+            };
+            let _session = match $crate::session::close(id) {
+                ::std::option::Option::Some(session) => session,
+                ::std::option::Option::None => return $crate::PluginStatus::Ok.code(),
+            };
+
+            $(
+                if let ::std::result::Result::Err(panic) = $crate::utils::catch_panic(|| $end_fn(&_session)) {
+                    $crate::utils::set_last_error(::std::format!("Session end hook panicked: {}", panic));
+                    return $crate::PluginStatus::Panic.code();
+                }
+            )?
+
+            $crate::PluginStatus::Ok.code()
+        }
+    };
+}
+
 /// Declare configuration schema export with automatic generation
 ///
 /// This macro generates an `extern "C"` function that exports the plugin's
@@ -430,6 +1616,7 @@ macro_rules! declare_config_schema {
             schema_ptr: *mut *mut ::std::primitive::u8,
             schema_len: *mut ::std::primitive::usize,
         ) -> ::std::primitive::i32 {
+            $crate::utils::clear_last_error();
             use schemars::schema_for;
             
             let schema = schema_for!($config_type);
@@ -437,7 +1624,8 @@ macro_rules! declare_config_schema {
                 ::std::result::Result::Ok(s) => s,
                 ::std::result::Result::Err(e) => {
                     ::std::eprintln!("Failed to serialize schema: {}", e);
-                    return 1;
+                    $crate::utils::set_last_error(::std::format!("Failed to serialize schema: {}", e));
+                    return $crate::PluginStatus::Internal.code();
                 }
             };
             
@@ -449,7 +1637,7 @@ macro_rules! declare_config_schema {
             *schema_ptr = vec.as_mut_ptr();
             let _ = ::std::mem::ManuallyDrop::new(vec);
             
-            0 // Success
+            $crate::PluginStatus::Ok.code()
         }
     };
 }
@@ -460,7 +1648,16 @@ macro_rules! declare_config_schema {
 /// - Static storage for the configuration (`OnceCell`)
 /// - `get_config()` function to access the configuration
 /// - `try_get_config()` function for optional access
+/// - `get_instance_config()` / `try_get_instance_config()` functions that
+///   prefer the configuration of the current [instance](crate::instance)
 /// - `plugin_configure()` C ABI function for the framework
+/// - `plugin_create_instance()` / `plugin_destroy_instance()` C ABI
+///   functions for hosts running several configured instances
+///
+/// `get_config()` always returns the configuration passed to
+/// `plugin_configure()`. Plugins supporting instances read their settings with
+/// `get_instance_config()` instead, which returns the instance's
+/// configuration inside a call made on one.
 ///
 /// # Example
 ///
@@ -495,7 +1692,7 @@ macro_rules! declare_config_schema {
 macro_rules! declare_plugin_config {
     ($config_type:ty) => {
         // Generate static storage
-        static __PLUGIN_CONFIG: $crate::once_cell::sync::OnceCell<$config_type> =
+        static __PLUGIN_CONFIG: $crate::once_cell::sync::OnceCell<::std::sync::Arc<$config_type>> =
             $crate::once_cell::sync::OnceCell::new();
 
         /// Get plugin configuration
@@ -506,8 +1703,7 @@ macro_rules! declare_plugin_config {
         /// `plugin_configure()` during plugin loading, so this should only panic
         /// if called before the plugin is fully loaded.
         pub fn get_config() -> &'static $config_type {
-            __PLUGIN_CONFIG
-                .get()
+            try_get_config()
                 .expect("Plugin not configured - configure() must be called first")
         }
 
@@ -516,7 +1712,47 @@ macro_rules! declare_plugin_config {
         /// Returns `None` if the plugin has not been configured yet.
         /// Use this if you need to check configuration availability.
         pub fn try_get_config() -> ::std::option::Option<&'static $config_type> {
-            __PLUGIN_CONFIG.get()
+            __PLUGIN_CONFIG.get().map(|config| &**config)
+        }
+
+        /// Get the configuration of the current instance
+        ///
+        /// Inside a call made on an instance, returns that instance's
+        /// configuration; otherwise the global one (see `get_config()`).
+        ///
+        /// # Panics
+        ///
+        /// Panics outside of instance calls if the plugin has not been
+        /// configured yet.
+        #[allow(dead_code)]
+        pub fn get_instance_config() -> ::std::sync::Arc<$config_type> {
+            try_get_instance_config()
+                .expect("Plugin not configured - configure() must be called first")
+        }
+
+        /// Try to get the configuration of the current instance
+        ///
+        /// Returns `None` outside of instance calls if the plugin has not been
+        /// configured yet.
+        #[allow(dead_code)]
+        pub fn try_get_instance_config() -> ::std::option::Option<::std::sync::Arc<$config_type>> {
+            $crate::instance::current_config::<$config_type>()
+                .or_else(|| __PLUGIN_CONFIG.get().cloned())
+        }
+
+        unsafe fn __parse_plugin_config(
+            config_json: *const ::std::primitive::u8,
+            config_len: ::std::primitive::usize,
+        ) -> ::std::option::Option<$config_type> {
+            let config_slice = ::std::slice::from_raw_parts(config_json, config_len);
+            match $crate::serde_json::from_slice(config_slice) {
+                ::std::result::Result::Ok(c) => ::std::option::Option::Some(c),
+                ::std::result::Result::Err(e) => {
+                    ::std::eprintln!("Failed to parse plugin config: {}", e);
+                    $crate::utils::set_last_error(::std::format!("Failed to parse plugin config: {}", e));
+                    ::std::option::Option::None
+                }
+            }
         }
 
         /// Auto-generated configuration function
@@ -525,31 +1761,209 @@ macro_rules! declare_plugin_config {
         /// It parses the JSON configuration and stores it in a static.
         ///
         /// # Returns
-        /// - 0 on success
-        /// - 1 on JSON parsing error
-        /// - 2 if plugin is already configured
+        /// - `PluginStatus::Ok` on success
+        /// - `PluginStatus::InvalidJson` on JSON parsing error
+        /// - `PluginStatus::AlreadyConfigured` if plugin is already configured
+        ///
+        /// On failure, the message is available through `last_error`.
         #[no_mangle]
         pub unsafe extern "C" fn plugin_configure(
             config_json: *const ::std::primitive::u8,
             config_len: ::std::primitive::usize,
         ) -> ::std::primitive::i32 {
+            $crate::utils::clear_last_error();
             // Parse configuration
-            let config_slice = ::std::slice::from_raw_parts(config_json, config_len);
-            let config: $config_type = match $crate::serde_json::from_slice(config_slice) {
-                ::std::result::Result::Ok(c) => c,
-                ::std::result::Result::Err(e) => {
-                    ::std::eprintln!("Failed to parse plugin config: {}", e);
-                    return 1; // Error code
-                }
+            let config = match __parse_plugin_config(config_json, config_len) {
+                ::std::option::Option::Some(c) => c,
+                ::std::option::Option::None => return $crate::PluginStatus::InvalidJson.code(),
             };
 
             // Store globally
-            if __PLUGIN_CONFIG.set(config).is_err() {
+            if __PLUGIN_CONFIG.set(::std::sync::Arc::new(config)).is_err() {
                 ::std::eprintln!("Plugin already configured");
-                return 2;
+                $crate::utils::set_last_error("Plugin already configured");
+                return $crate::PluginStatus::AlreadyConfigured.code();
             }
 
-            0 // Success
+            $crate::PluginStatus::Ok.code()
+        }
+
+        /// Auto-generated create_instance function
+        ///
+        /// Parses the JSON configuration into a new instance. Returns null on
+        /// a parsing error; the message is available through `last_error`.
+        #[no_mangle]
+        pub unsafe extern "C" fn plugin_create_instance(
+            config_json: *const ::std::primitive::u8,
+            config_len: ::std::primitive::usize,
+        ) -> *mut $crate::instance::Instance {
+            $crate::utils::clear_last_error();
+            match __parse_plugin_config(config_json, config_len) {
+                ::std::option::Option::Some(config) => {
+                    $crate::instance::Instance::new(config).into_raw()
+                }
+                ::std::option::Option::None => ::std::ptr::null_mut(),
+            }
+        }
+
+        /// Auto-generated destroy_instance function
+        #[no_mangle]
+        pub unsafe extern "C" fn plugin_destroy_instance(instance: *mut $crate::instance::Instance) {
+            $crate::instance::Instance::release(instance);
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" fn list_tools(_buf: *mut *mut u8, _len: *mut usize) -> i32 {
+        PluginStatus::Ok.code()
+    }
+
+    unsafe extern "C" fn execute_tool(
+        _name: *const c_char,
+        _args: *const u8,
+        _args_len: usize,
+        _buf: *mut *mut u8,
+        _len: *mut usize,
+    ) -> i32 {
+        PluginStatus::Ok.code()
+    }
+
+    declare_plugin! {
+        list_tools: list_tools,
+        execute_tool: execute_tool,
+        free_string: utils::standard_free_string
+    }
+
+    /// A declaration as exported by a plugin built with this crate
+    pub(crate) fn declaration() -> PluginDeclaration {
+        PluginDeclaration { ..plugin_declaration }
+    }
+
+    /// Room for fields appended by a newer plugin
+    #[repr(C)]
+    struct NewerDeclaration {
+        decl: PluginDeclaration,
+        extra: [usize; 2],
+    }
+
+    #[test]
+    fn read_copies_the_whole_current_declaration() {
+        let decl = unsafe { PluginDeclaration::read(&plugin_declaration) }.unwrap();
+
+        assert_eq!(decl.struct_size, PluginDeclaration::SIZE);
+        assert_eq!(decl.abi_revision, ABI_REVISION);
+        let binary_encodings = cfg!(any(feature = "msgpack", feature = "cbor"));
+        assert_eq!(decl.set_wire_encoding.is_some(), binary_encodings);
+        assert_eq!(decl.capabilities.contains(Capabilities::WIRE_ENCODING), binary_encodings);
+        assert!(decl.get_metrics.is_some());
+        assert!(decl.has_field(PluginDeclaration::SIZE));
+    }
+
+    /// Copy the first `struct_size` bytes of a declaration into a buffer of
+    /// exactly that size, as exported by an older plugin
+    fn truncated(decl: &PluginDeclaration) -> Vec<usize> {
+        let words = decl.struct_size / std::mem::size_of::<usize>();
+        assert_eq!(words * std::mem::size_of::<usize>(), decl.struct_size);
+        let mut buffer = vec![0usize; words];
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                decl as *const PluginDeclaration as *const u8,
+                buffer.as_mut_ptr() as *mut u8,
+                decl.struct_size,
+            );
+        }
+        buffer
+    }
+
+    #[test]
+    fn read_leaves_fields_of_older_plugins_empty() {
+        // A revision 1 plugin ends after get_config_schema
+        let struct_size = std::mem::offset_of!(PluginDeclaration, list_resources);
+        let template = PluginDeclaration {
+            struct_size,
+            abi_revision: 1,
+            ..declaration()
+        };
+        let older = truncated(&template);
+
+        let decl = unsafe { PluginDeclaration::read(older.as_ptr() as *const PluginDeclaration) }.unwrap();
+
+        assert_eq!(decl.struct_size, struct_size);
+        assert_eq!(decl.abi_revision, 1);
+        assert_eq!(decl.capabilities, template.capabilities);
+        assert_eq!(decl.list_tools as usize, template.list_tools as usize);
+        assert!(decl.set_wire_encoding.is_none());
+        assert_eq!(decl.wire_encodings, 0);
+        assert!(decl.last_error.is_none());
+        assert!(decl.get_metrics.is_none());
+        assert!(!decl.has_field(PluginDeclaration::SIZE));
+    }
+
+    #[test]
+    fn read_ignores_fields_of_newer_plugins() {
+        let newer = NewerDeclaration {
+            decl: PluginDeclaration {
+                struct_size: std::mem::size_of::<NewerDeclaration>(),
+                abi_revision: ABI_REVISION + 1,
+                ..declaration()
+            },
+            extra: [usize::MAX; 2],
+        };
+
+        let decl = unsafe { PluginDeclaration::read(&newer.decl) }.unwrap();
+
+        assert_eq!(decl.struct_size, std::mem::size_of::<NewerDeclaration>());
+        assert_eq!(decl.abi_revision, ABI_REVISION + 1);
+        assert!(decl.get_metrics.is_some());
+    }
+
+    #[test]
+    fn read_rejects_null_and_truncated_declarations() {
+        let truncated = PluginDeclaration {
+            struct_size: PluginDeclaration::HEADER_SIZE,
+            ..declaration()
+        };
+
+        assert_eq!(
+            unsafe { PluginDeclaration::read(std::ptr::null()) }.err(),
+            Some(DeclarationError::NullPointer)
+        );
+        assert_eq!(
+            unsafe { PluginDeclaration::read(&truncated) }.err(),
+            Some(DeclarationError::TooSmall {
+                struct_size: PluginDeclaration::HEADER_SIZE
+            })
+        );
+    }
+
+    /// The declaration of API 0.1, before the size-prefixed header
+    #[repr(C)]
+    struct UnsizedDeclaration {
+        api_version: *const u8,
+        list_tools: ListToolsFn,
+        execute_tool: ExecuteToolFn,
+        free_string: FreeStringFn,
+    }
+
+    #[test]
+    fn read_rejects_declarations_without_a_size() {
+        let old = UnsizedDeclaration {
+            api_version: c"0.1.0".as_ptr() as *const u8,
+            list_tools,
+            execute_tool,
+            free_string: utils::standard_free_string,
+        };
+
+        let result = unsafe { PluginDeclaration::read(&old as *const _ as *const PluginDeclaration) };
+        assert_eq!(
+            result.err(),
+            Some(DeclarationError::TooLarge {
+                struct_size: list_tools as *const () as usize
+            })
+        );
+    }
+}
@@ -3,9 +3,24 @@
 /// Declare tools and auto-generate list_tools and execute_tool functions
 ///
 /// This macro takes a list of Tool definitions and generates:
-/// - A static tool registry (HashMap for O(1) lookup)
-/// - The `generated_list_tools` function
+/// - A static `ToolRegistry` (HashMap for O(1) lookup), mutable at runtime
+/// - `register_tool` / `unregister_tool` functions for dynamic tool sets
+/// - An `invalidate_tool_cache` function for tools with a result
+///   [cache](crate::cache)
+/// - The `generated_list_tools` function (tools in declaration order,
+///   serialized once and cached until the tool set changes)
+/// - The `generated_tools_etag` function, returning a hash of the tool list
 /// - The `generated_execute_tool` function
+/// - The `generated_execute_tool_with_context` function, which passes the
+///   host's call context to handlers registered with `handler_with_context`
+/// - The `generated_execute_tool_stream` function, which streams the output
+///   of handlers registered with `streaming_handler` to a host sink
+/// - The `generated_instance_list_tools` and `generated_instance_execute_tool`
+///   functions, which work on the tool set of a plugin instance (see
+///   [`instance`](crate::instance))
+///
+/// Every executed call is recorded in the built-in [`metrics`](crate::metrics)
+/// and, with the `tracing` feature, runs inside a [span](crate::trace).
 ///
 /// These generated functions can be used directly in the `declare_plugin!` macro.
 ///
@@ -41,40 +56,184 @@
 ///     free_string: mcp_plugin_api::utils::standard_free_string
 /// }
 /// ```
+///
+/// # Namespace
+///
+/// With a namespace, tools are listed as `<namespace>__<name>` and called
+/// under that name, so that tools of different plugins do not collide (see
+/// [`registry`](crate::registry#namespaces)):
+///
+/// ```ignore
+/// declare_tools! {
+///     namespace: "orders",
+///     tools: [
+///         Tool::builder("search", "Search orders")  // listed as `orders__search`
+///             .param_string("query", "Search text", true)
+///             .handler(handle_search),
+///     ]
+/// }
+/// ```
+///
+/// Tool names are checked against the MCP naming rules when the registry is
+/// created and by `register_tool`. If a declared name is invalid, the
+/// generated `list_tools` and `execute_tool` functions fail with
+/// `PluginStatus::Internal` and the problem is available through
+/// `last_error`.
+///
+/// # Middleware
+///
+/// Middleware listed in `declare_tools!` runs around every tool's handler
+/// (see [`middleware`](crate::middleware)):
+///
+/// ```ignore
+/// declare_tools! {
+///     middleware: [LogCalls],
+///     tools: [ ... ]
+/// }
+/// ```
+///
+/// # Dynamic Tools
+///
+/// The tool set can change after initialization, e.g. one tool per
+/// configured database. `register_tool` and `unregister_tool` update the
+/// registry and ask the host to emit `notifications/tools/list_changed`
+/// (requires `set_host_callbacks` in `declare_plugin!`):
+///
+/// ```ignore
+/// fn init() -> Result<(), String> {
+///     for db in &get_config().databases {
+///         register_tool(
+///             Tool::builder(&format!("query_{}", db.name), "Run a query")
+///                 .param_string("sql", "SQL query", true)
+///                 .handler_with_context(handle_query),
+///         )?;
+///     }
+///     Ok(())
+/// }
+/// ```
 #[macro_export]
 macro_rules! declare_tools {
-    (tools: [ $($tool:expr),* $(,)? ]) => {
-        // Generate a static HashMap of tools using OnceLock for thread-safe lazy init
-        static TOOLS: ::std::sync::OnceLock<::std::collections::HashMap<::std::string::String, $crate::tool::Tool>> 
+    (
+        $(namespace: $namespace:expr,)?
+        $(middleware: [ $($middleware:expr),* $(,)? ],)?
+        tools: [ $($tool:expr),* $(,)? ]
+    ) => {
+        // Generate a static tool registry using OnceLock for thread-safe lazy init
+        static TOOLS: ::std::sync::OnceLock<$crate::registry::ToolRegistry>
             = ::std::sync::OnceLock::new();
         
-        fn get_tools() -> &'static ::std::collections::HashMap<::std::string::String, $crate::tool::Tool> {
-            TOOLS.get_or_init(|| {
-                let mut map = ::std::collections::HashMap::new();
+        fn get_tools() -> &'static $crate::registry::ToolRegistry {
+            TOOLS.get_or_init(new_tool_registry)
+        }
+
+        // Instances start with a fresh copy of the declared tools. This runs
+        // inside the generated ABI functions, so invalid declarations (and
+        // panicking builders) end up as an invalid registry, reported to the
+        // host by these functions, rather than unwinding into the host.
+        fn new_tool_registry() -> $crate::registry::ToolRegistry {
+            let registry = $crate::utils::catch_panic(|| {
+                let registry = $crate::registry::ToolRegistry::try_new(::std::vec![$($tool),*]);
+                $(let registry = registry.and_then(|registry| registry.try_with_namespace($namespace));)?
                 $(
-                    let tool = $tool;
-                    map.insert(tool.name.clone(), tool);
-                )*
-                map
+                    let registry = registry.map(|registry| registry.with_middleware(::std::vec![
+                        $(::std::sync::Arc::new($middleware) as ::std::sync::Arc<dyn $crate::middleware::Middleware>),*
+                    ]));
+                )?
+                registry
+            });
+            registry.and_then(|registry| registry).unwrap_or_else(|e| {
+                $crate::registry::ToolRegistry::invalid(format!("Invalid tool declaration: {}", e))
             })
         }
+
+        /// Add a tool at runtime and notify the host
+        ///
+        /// Called during a call on an instance, the tool is added to that
+        /// instance only.
+        ///
+        /// Returns an error if a tool with the same name already exists.
+        /// A host without notification support is not an error; it will see
+        /// the new tool the next time it lists tools.
+        #[allow(dead_code)]
+        pub fn register_tool(tool: $crate::tool::Tool) -> ::std::result::Result<(), ::std::string::String> {
+            match $crate::instance::current() {
+                Some(instance) => instance.tools(new_tool_registry).register(tool)?,
+                None => get_tools().register(tool)?,
+            }
+            let _ = $crate::host::notify_tools_list_changed();
+            Ok(())
+        }
+
+        /// Remove a tool at runtime and notify the host
+        ///
+        /// Called during a call on an instance, the tool is removed from that
+        /// instance only.
+        ///
+        /// Returns `false` if no tool had that name.
+        #[allow(dead_code)]
+        pub fn unregister_tool(name: &str) -> bool {
+            let removed = match $crate::instance::current() {
+                Some(instance) => instance.tools(new_tool_registry).unregister(name).is_some(),
+                None => get_tools().unregister(name).is_some(),
+            };
+            if removed {
+                let _ = $crate::host::notify_tools_list_changed();
+            }
+            removed
+        }
         
+        /// Drop cached results of a tool (see [`cache`]($crate::cache))
+        ///
+        /// With `args`, only the result for those arguments is dropped,
+        /// otherwise all of the tool's results. Called during a call on an
+        /// instance, the instance's cache is used.
+        ///
+        /// Returns `false` if the tool does not exist or has no cache.
+        #[allow(dead_code)]
+        pub fn invalidate_tool_cache(name: &str, args: ::std::option::Option<&$crate::serde_json::Value>) -> bool {
+            let tool = match $crate::instance::current() {
+                Some(instance) => instance.tools(new_tool_registry).get(name),
+                None => get_tools().get(name),
+            };
+            match tool.as_ref().and_then(|tool| tool.cache.as_ref()) {
+                Some(cache) => {
+                    match args {
+                        Some(args) => {
+                            cache.invalidate(args);
+                        }
+                        None => cache.clear(),
+                    }
+                    true
+                }
+                None => false,
+            }
+        }
+
         /// Auto-generated list_tools function
         ///
-        /// Returns a JSON array of all tool definitions.
+        /// Returns a JSON array of all tool definitions, in declaration order.
         #[no_mangle]
         pub unsafe extern "C" fn generated_list_tools(
             result_buf: *mut *mut u8,
             result_len: *mut usize,
         ) -> i32 {
+            $crate::utils::clear_last_error();
+            __list_tools(get_tools(), result_buf, result_len)
+        }
+
+        /// Auto-generated tools_etag function
+        ///
+        /// Returns a hash that changes whenever the tool list changes. For
+        /// invalid tool declarations, this is the hash of an empty list and
+        /// the problem is available through `last_error`.
+        #[no_mangle]
+        pub unsafe extern "C" fn generated_tools_etag() -> u64 {
+            $crate::utils::clear_last_error();
             let tools = get_tools();
-            let tools_json: ::std::vec::Vec<$crate::serde_json::Value> = tools
-                .values()
-                .map(|t| t.to_json_schema())
-                .collect();
-            
-            let json_array = $crate::serde_json::Value::Array(tools_json);
-            $crate::utils::return_success(json_array, result_buf, result_len)
+            if let Some(e) = tools.error() {
+                $crate::utils::set_last_error(e);
+            }
+            tools.etag()
         }
         
         /// Auto-generated execute_tool function
@@ -87,49 +246,306 @@ macro_rules! declare_tools {
             args_len: usize,
             result_buf: *mut *mut u8,
             result_len: *mut usize,
+        ) -> i32 {
+            $crate::utils::clear_last_error();
+            __dispatch_tool(
+                get_tools(),
+                tool_name,
+                args_json,
+                args_len,
+                &$crate::context::ToolContext::new(),
+                None,
+                result_buf,
+                result_len,
+            )
+        }
+
+        /// Auto-generated execute_tool_with_context function
+        ///
+        /// Like `generated_execute_tool`, but passes the host's call context
+        /// to the handler.
+        #[no_mangle]
+        pub unsafe extern "C" fn generated_execute_tool_with_context(
+            tool_name: *const ::std::os::raw::c_char,
+            args_json: *const u8,
+            args_len: usize,
+            context_json: *const u8,
+            context_len: usize,
+            result_buf: *mut *mut u8,
+            result_len: *mut usize,
+        ) -> i32 {
+            $crate::utils::clear_last_error();
+            let ctx = match __parse_call_context(context_json, context_len) {
+                Ok(ctx) => ctx,
+                Err(e) => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::InvalidJson,
+                    &e,
+                    result_buf,
+                    result_len
+                ),
+            };
+
+            __dispatch_tool(
+                get_tools(),
+                tool_name,
+                args_json,
+                args_len,
+                &ctx,
+                None,
+                result_buf,
+                result_len,
+            )
+        }
+
+        /// Auto-generated execute_tool_stream function
+        ///
+        /// Like `generated_execute_tool_with_context`, but streaming handlers
+        /// write their chunks to the host's sink.
+        #[no_mangle]
+        pub unsafe extern "C" fn generated_execute_tool_stream(
+            tool_name: *const ::std::os::raw::c_char,
+            args_json: *const u8,
+            args_len: usize,
+            context_json: *const u8,
+            context_len: usize,
+            sink: *const $crate::stream::StreamSink,
+            result_buf: *mut *mut u8,
+            result_len: *mut usize,
+        ) -> i32 {
+            $crate::utils::clear_last_error();
+            if sink.is_null() {
+                return $crate::utils::return_error_status(
+                    $crate::PluginStatus::InvalidArgument,
+                    "Stream sink is null",
+                    result_buf,
+                    result_len
+                );
+            }
+
+            let ctx = match __parse_call_context(context_json, context_len) {
+                Ok(ctx) => ctx,
+                Err(e) => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::InvalidJson,
+                    &e,
+                    result_buf,
+                    result_len
+                ),
+            };
+
+            __dispatch_tool(
+                get_tools(),
+                tool_name,
+                args_json,
+                args_len,
+                &ctx,
+                Some($crate::stream::ChunkWriter::from_sink(*sink)),
+                result_buf,
+                result_len,
+            )
+        }
+
+        /// Auto-generated instance_list_tools function
+        ///
+        /// Like `generated_list_tools`, for the tools of one instance.
+        #[no_mangle]
+        pub unsafe extern "C" fn generated_instance_list_tools(
+            instance: *mut $crate::instance::Instance,
+            result_buf: *mut *mut u8,
+            result_len: *mut usize,
+        ) -> i32 {
+            $crate::utils::clear_last_error();
+            let instance = match $crate::instance::Instance::from_handle(instance) {
+                Some(instance) => instance,
+                None => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::InvalidArgument,
+                    "Instance is null",
+                    result_buf,
+                    result_len
+                ),
+            };
+
+            __list_tools(instance.tools(new_tool_registry), result_buf, result_len)
+        }
+
+        /// Auto-generated instance_execute_tool function
+        ///
+        /// Like `generated_execute_tool_with_context`, for the tools of one
+        /// instance. Handlers see the instance's configuration.
+        #[no_mangle]
+        pub unsafe extern "C" fn generated_instance_execute_tool(
+            instance: *mut $crate::instance::Instance,
+            tool_name: *const ::std::os::raw::c_char,
+            args_json: *const u8,
+            args_len: usize,
+            context_json: *const u8,
+            context_len: usize,
+            result_buf: *mut *mut u8,
+            result_len: *mut usize,
+        ) -> i32 {
+            $crate::utils::clear_last_error();
+            let instance = match $crate::instance::Instance::from_handle(instance) {
+                Some(instance) => instance,
+                None => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::InvalidArgument,
+                    "Instance is null",
+                    result_buf,
+                    result_len
+                ),
+            };
+
+            let ctx = match __parse_call_context(context_json, context_len) {
+                Ok(ctx) => ctx.with_instance(instance.clone()),
+                Err(e) => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::InvalidJson,
+                    &e,
+                    result_buf,
+                    result_len
+                ),
+            };
+
+            __dispatch_tool(
+                instance.tools(new_tool_registry),
+                tool_name,
+                args_json,
+                args_len,
+                &ctx,
+                None,
+                result_buf,
+                result_len,
+            )
+        }
+
+        unsafe fn __parse_call_context(
+            context_json: *const u8,
+            context_len: usize,
+        ) -> ::std::result::Result<$crate::context::ToolContext, ::std::string::String> {
+            // An empty buffer means no context
+            if context_len == 0 {
+                return Ok($crate::context::ToolContext::new());
+            }
+
+            let context_slice = ::std::slice::from_raw_parts(context_json, context_len);
+            $crate::encoding::decode(context_slice)
+                .map($crate::context::ToolContext::from_json)
+                .map_err(|e| format!("Invalid JSON call context: {}", e))
+        }
+
+        unsafe fn __list_tools(
+            tools: &$crate::registry::ToolRegistry,
+            result_buf: *mut *mut u8,
+            result_len: *mut usize,
+        ) -> i32 {
+            if let Some(e) = tools.error() {
+                return $crate::utils::return_error_status(
+                    $crate::PluginStatus::Internal,
+                    e,
+                    result_buf,
+                    result_len
+                );
+            }
+
+            let list = tools.list_bytes();
+            $crate::utils::return_encoded(list.to_vec(), result_buf, result_len)
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        unsafe fn __dispatch_tool(
+            tools: &$crate::registry::ToolRegistry,
+            tool_name: *const ::std::os::raw::c_char,
+            args_json: *const u8,
+            args_len: usize,
+            ctx: &$crate::context::ToolContext,
+            out: Option<$crate::stream::ChunkWriter>,
+            result_buf: *mut *mut u8,
+            result_len: *mut usize,
         ) -> i32 {
             use ::std::ffi::CStr;
+
+            if let Some(e) = tools.error() {
+                return $crate::utils::return_error_status(
+                    $crate::PluginStatus::Internal,
+                    e,
+                    result_buf,
+                    result_len
+                );
+            }
             
             // Parse tool name
             let name = match CStr::from_ptr(tool_name).to_str() {
                 Ok(s) => s,
-                Err(_) => return $crate::utils::return_error(
+                Err(_) => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::InvalidUtf8,
                     "Invalid tool name encoding",
                     result_buf,
                     result_len
                 ),
             };
             
-            // Parse arguments
+            // Find the tool by its listed name (O(1) HashMap lookup!)
+            let tool = match tools.resolve(name) {
+                Some(tool) => tool,
+                None => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::NotFound,
+                    &format!("Unknown tool: {}", name),
+                    result_buf,
+                    result_len
+                ),
+            };
+
+            // Parse arguments (raw handlers borrow them unparsed)
             let args_slice = ::std::slice::from_raw_parts(args_json, args_len);
-            let args: $crate::serde_json::Value = match $crate::serde_json::from_slice(args_slice) {
-                Ok(v) => v,
-                Err(e) => return $crate::utils::return_error(
+            let args = match $crate::tool::ToolArgs::parse(args_slice, tool.takes_raw_args()) {
+                Ok(args) => args,
+                Err(e) => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::InvalidJson,
                     &format!("Invalid JSON arguments: {}", e),
                     result_buf,
                     result_len
                 ),
             };
-            
-            // Find and execute the tool (O(1) HashMap lookup!)
-            let tools = get_tools();
-            match tools.get(name) {
-                Some(tool) => {
-                    match (tool.handler)(&args) {
-                        Ok(result) => $crate::utils::return_success(
-                            result,
-                            result_buf,
-                            result_len
-                        ),
-                        Err(e) => $crate::utils::return_error(
-                            &e,
-                            result_buf,
-                            result_len
-                        ),
-                    }
+
+            // Execute the tool; panics are caught before they reach the host,
+            // and calls running past their deadline are cancelled
+            if let Some(cache) = &tool.cache {
+                cache.report_metrics(name);
+            }
+            let span = $crate::trace::ToolSpan::new(name, ctx);
+            let started = ::std::time::Instant::now();
+            let outcome = span.in_scope(|| tool.execute(tools.middleware(), ctx, args, out));
+            $crate::metrics::record(name, &outcome, started.elapsed());
+            span.record_outcome(&outcome);
+
+            match outcome {
+                $crate::tool::ToolOutcome::Done(Ok($crate::tool::ToolOutput::Encoded(bytes))) => {
+                    $crate::utils::return_encoded(bytes, result_buf, result_len)
                 }
-                None => $crate::utils::return_error(
-                    &format!("Unknown tool: {}", name),
+                $crate::tool::ToolOutcome::Done(Ok($crate::tool::ToolOutput::Value(result, attachments))) => {
+                    $crate::utils::return_success_with_attachments(
+                        result,
+                        attachments,
+                        ctx.accepts_attachments(),
+                        result_buf,
+                        result_len
+                    )
+                }
+                $crate::tool::ToolOutcome::Done(Err(e)) => {
+                    $crate::utils::return_error(&e, result_buf, result_len)
+                }
+                $crate::tool::ToolOutcome::Panicked(panic) => $crate::utils::return_error_status(
+                    $crate::PluginStatus::Panic,
+                    &format!("Tool '{}' panicked: {}", name, panic),
+                    result_buf,
+                    result_len
+                ),
+                $crate::tool::ToolOutcome::TimedOut(limit) => $crate::utils::return_error_status(
+                    $crate::PluginStatus::Timeout,
+                    &format!("Tool '{}' timed out after {} ms", name, limit.as_micros().div_ceil(1000)),
+                    result_buf,
+                    result_len
+                ),
+                $crate::tool::ToolOutcome::Forbidden(e) => $crate::utils::return_error_status(
+                    $crate::PluginStatus::Forbidden,
+                    &format!("Tool '{}' denied: {}", name, e),
                     result_buf,
                     result_len
                 ),
@@ -137,3 +553,1318 @@ macro_rules! declare_tools {
         }
     };
 }
+
+/// Declare resources and auto-generate the resource ABI functions
+///
+/// This macro takes a list of Resource definitions and, optionally, a list of
+/// ResourceTemplate definitions and generates:
+/// - A static `ResourceRouter` (static resources and templates in declaration order)
+/// - The `generated_list_resources` function
+/// - The `generated_list_resource_templates` function
+/// - The `generated_read_resource` function, which dispatches exact URIs to
+///   resources and everything else to the first matching template
+///
+/// These generated functions can be used directly in the `declare_plugin!` macro.
+///
+/// # Example
+///
+/// ```ignore
+/// use mcp_plugin_api::*;
+/// use mcp_plugin_api::utils::resource_text_contents;
+/// use serde_json::Value;
+/// use std::collections::HashMap;
+///
+/// fn read_readme(uri: &str) -> Result<Value, String> {
+///     Ok(resource_text_contents(uri, Some("text/markdown".to_string()), "# Hello"))
+/// }
+///
+/// fn read_product_spec(uri: &str, vars: &HashMap<String, String>) -> Result<Value, String> {
+///     let spec = load_spec(&vars["id"])?;
+///     Ok(resource_text_contents(uri, Some("application/json".to_string()), spec))
+/// }
+///
+/// declare_resources! {
+///     resources: [
+///         Resource::builder("docs://readme", "README")
+///             .description("Project README")
+///             .mime_type("text/markdown")
+///             .handler(read_readme),
+///     ],
+///     templates: [
+///         ResourceTemplate::builder("product://{id}/spec", "Product specification")
+///             .mime_type("application/json")
+///             .handler(read_product_spec),
+///     ]
+/// }
+///
+/// declare_plugin! {
+///     list_tools: generated_list_tools,
+///     execute_tool: generated_execute_tool,
+///     free_string: mcp_plugin_api::utils::standard_free_string,
+///     list_resources: generated_list_resources,
+///     read_resource: generated_read_resource,
+///     list_resource_templates: generated_list_resource_templates
+/// }
+/// ```
+#[macro_export]
+macro_rules! declare_resources {
+    (
+        resources: [ $($resource:expr),* $(,)? ]
+        $(, templates: [ $($template:expr),* $(,)? ])?
+        $(,)?
+    ) => {
+        // Generate a static resource router using OnceLock for thread-safe lazy init
+        static RESOURCES: ::std::sync::OnceLock<$crate::resource::ResourceRouter>
+            = ::std::sync::OnceLock::new();
+
+        // Invalid declarations (panicking builders) end up as an invalid
+        // router, reported to the host by the generated functions, rather
+        // than unwinding into the host.
+        fn get_resources() -> &'static $crate::resource::ResourceRouter {
+            RESOURCES.get_or_init(|| {
+                let router = $crate::utils::catch_panic(|| {
+                    $crate::resource::ResourceRouter::new(
+                        ::std::vec![$($resource),*],
+                        ::std::vec![$($($template),*)?],
+                    )
+                });
+                router.unwrap_or_else(|e| {
+                    $crate::resource::ResourceRouter::invalid(format!("Invalid resource declaration: {}", e))
+                })
+            })
+        }
+
+        /// Auto-generated list_resources function
+        ///
+        /// Returns a JSON array of all resource definitions.
+        #[no_mangle]
+        pub unsafe extern "C" fn generated_list_resources(
+            result_buf: *mut *mut u8,
+            result_len: *mut usize,
+        ) -> i32 {
+            $crate::utils::clear_last_error();
+            let resources = get_resources();
+            if let Some(e) = resources.error() {
+                return $crate::utils::return_error_status(
+                    $crate::PluginStatus::Internal,
+                    e,
+                    result_buf,
+                    result_len
+                );
+            }
+
+            let json_array = resources.list_resources_json();
+            $crate::utils::return_success(json_array, result_buf, result_len)
+        }
+
+        /// Auto-generated list_resource_templates function
+        ///
+        /// Returns a JSON array of all resource template definitions.
+        #[no_mangle]
+        pub unsafe extern "C" fn generated_list_resource_templates(
+            result_buf: *mut *mut u8,
+            result_len: *mut usize,
+        ) -> i32 {
+            $crate::utils::clear_last_error();
+            let resources = get_resources();
+            if let Some(e) = resources.error() {
+                return $crate::utils::return_error_status(
+                    $crate::PluginStatus::Internal,
+                    e,
+                    result_buf,
+                    result_len
+                );
+            }
+
+            let json_array = resources.list_templates_json();
+            $crate::utils::return_success(json_array, result_buf, result_len)
+        }
+
+        /// Auto-generated read_resource function
+        ///
+        /// Dispatches to the appropriate resource or template handler based on the URI.
+        #[no_mangle]
+        pub unsafe extern "C" fn generated_read_resource(
+            uri: *const ::std::os::raw::c_char,
+            result_buf: *mut *mut u8,
+            result_len: *mut usize,
+        ) -> i32 {
+            $crate::utils::clear_last_error();
+            use ::std::ffi::CStr;
+
+            let resources = get_resources();
+            if let Some(e) = resources.error() {
+                return $crate::utils::return_error_status(
+                    $crate::PluginStatus::Internal,
+                    e,
+                    result_buf,
+                    result_len
+                );
+            }
+
+            // Parse URI
+            let uri = match CStr::from_ptr(uri).to_str() {
+                Ok(s) => s,
+                Err(_) => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::InvalidUtf8,
+                    "Invalid resource URI encoding",
+                    result_buf,
+                    result_len
+                ),
+            };
+
+            if !resources.contains(uri) {
+                return $crate::utils::return_error_status(
+                    $crate::PluginStatus::NotFound,
+                    &format!("Unknown resource: {}", uri),
+                    result_buf,
+                    result_len
+                );
+            }
+
+            match $crate::utils::catch_panic(|| resources.read(uri)) {
+                Ok(Ok(result)) => $crate::utils::return_success(
+                    result,
+                    result_buf,
+                    result_len
+                ),
+                Ok(Err(e)) => $crate::utils::return_error(
+                    &e,
+                    result_buf,
+                    result_len
+                ),
+                Err(panic) => $crate::utils::return_error_status(
+                    $crate::PluginStatus::Panic,
+                    &format!("Resource '{}' panicked: {}", uri, panic),
+                    result_buf,
+                    result_len
+                ),
+            }
+        }
+    };
+}
+
+/// Declare prompts and auto-generate list_prompts and get_prompt functions
+///
+/// This macro takes a list of Prompt definitions and generates:
+/// - A static prompt registry (in declaration order)
+/// - The `generated_list_prompts` function
+/// - The `generated_get_prompt` function
+///
+/// These generated functions can be used directly in the `declare_plugin!` macro.
+///
+/// # Example
+///
+/// ```ignore
+/// use mcp_plugin_api::*;
+/// use mcp_plugin_api::utils::{prompt_result, prompt_text_message};
+/// use serde_json::Value;
+/// use std::collections::HashMap;
+///
+/// fn handle_summarize_order(args: &HashMap<String, String>) -> Result<Value, String> {
+///     let text = format!("Summarize order {} for the customer.", args["order_id"]);
+///     Ok(prompt_result(None, vec![prompt_text_message("user", text)]))
+/// }
+///
+/// declare_prompts! {
+///     prompts: [
+///         Prompt::builder("summarize_order", "Summarize an order")
+///             .argument("order_id", "The order to summarize", true)
+///             .handler(handle_summarize_order),
+///     ]
+/// }
+///
+/// declare_plugin! {
+///     list_tools: generated_list_tools,
+///     execute_tool: generated_execute_tool,
+///     free_string: mcp_plugin_api::utils::standard_free_string,
+///     list_prompts: generated_list_prompts,
+///     get_prompt: generated_get_prompt
+/// }
+/// ```
+#[macro_export]
+macro_rules! declare_prompts {
+    (prompts: [ $($prompt:expr),* $(,)? ]) => {
+        // Generate a static list of prompts using OnceLock for thread-safe lazy init
+        static PROMPTS: ::std::sync::OnceLock<
+            ::std::result::Result<::std::vec::Vec<$crate::prompt::Prompt>, ::std::string::String>
+        > = ::std::sync::OnceLock::new();
+
+        // Invalid declarations (panicking builders) are kept as an error,
+        // reported to the host by the generated functions, rather than
+        // unwinding into the host.
+        fn __declared_prompts() -> &'static ::std::result::Result<
+            ::std::vec::Vec<$crate::prompt::Prompt>,
+            ::std::string::String,
+        > {
+            PROMPTS.get_or_init(|| {
+                $crate::utils::catch_panic(|| ::std::vec![$($prompt),*])
+                    .map_err(|e| format!("Invalid prompt declaration: {}", e))
+            })
+        }
+
+        /// The declared prompts, or none if they are invalid
+        #[allow(dead_code)]
+        fn get_prompts() -> &'static [$crate::prompt::Prompt] {
+            __declared_prompts().as_deref().unwrap_or(&[])
+        }
+
+        /// Auto-generated list_prompts function
+        ///
+        /// Returns a JSON array of all prompt definitions.
+        #[no_mangle]
+        pub unsafe extern "C" fn generated_list_prompts(
+            result_buf: *mut *mut u8,
+            result_len: *mut usize,
+        ) -> i32 {
+            $crate::utils::clear_last_error();
+            let prompts = match __declared_prompts() {
+                Ok(prompts) => prompts,
+                Err(e) => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::Internal,
+                    e,
+                    result_buf,
+                    result_len
+                ),
+            };
+
+            let prompts_json: ::std::vec::Vec<$crate::serde_json::Value> = prompts
+                .iter()
+                .map(|p| p.to_json())
+                .collect();
+
+            let json_array = $crate::serde_json::Value::Array(prompts_json);
+            $crate::utils::return_success(json_array, result_buf, result_len)
+        }
+
+        /// Auto-generated get_prompt function
+        ///
+        /// Dispatches to the appropriate prompt handler based on the prompt name.
+        #[no_mangle]
+        pub unsafe extern "C" fn generated_get_prompt(
+            prompt_name: *const ::std::os::raw::c_char,
+            args_json: *const u8,
+            args_len: usize,
+            result_buf: *mut *mut u8,
+            result_len: *mut usize,
+        ) -> i32 {
+            $crate::utils::clear_last_error();
+            use ::std::ffi::CStr;
+
+            let prompts = match __declared_prompts() {
+                Ok(prompts) => prompts,
+                Err(e) => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::Internal,
+                    e,
+                    result_buf,
+                    result_len
+                ),
+            };
+
+            // Parse prompt name
+            let name = match CStr::from_ptr(prompt_name).to_str() {
+                Ok(s) => s,
+                Err(_) => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::InvalidUtf8,
+                    "Invalid prompt name encoding",
+                    result_buf,
+                    result_len
+                ),
+            };
+
+            // Parse arguments (an empty buffer means no arguments)
+            let args_slice = ::std::slice::from_raw_parts(args_json, args_len);
+            let args: $crate::serde_json::Value = if args_slice.is_empty() {
+                $crate::serde_json::Value::Null
+            } else {
+                match $crate::encoding::decode(args_slice) {
+                    Ok(v) => v,
+                    Err(e) => return $crate::utils::return_error_status(
+                        $crate::PluginStatus::InvalidJson,
+                        &format!("Invalid JSON arguments: {}", e),
+                        result_buf,
+                        result_len
+                    ),
+                }
+            };
+
+            match prompts.iter().find(|p| p.name == name) {
+                Some(prompt) => {
+                    match $crate::utils::catch_panic(|| prompt.get(&args)) {
+                        Ok(Ok(result)) => $crate::utils::return_success(
+                            result,
+                            result_buf,
+                            result_len
+                        ),
+                        Ok(Err(e)) => $crate::utils::return_error(
+                            &e,
+                            result_buf,
+                            result_len
+                        ),
+                        Err(panic) => $crate::utils::return_error_status(
+                            $crate::PluginStatus::Panic,
+                            &format!("Prompt '{}' panicked: {}", name, panic),
+                            result_buf,
+                            result_len
+                        ),
+                    }
+                }
+                None => $crate::utils::return_error_status(
+                    $crate::PluginStatus::NotFound,
+                    &format!("Unknown prompt: {}", name),
+                    result_buf,
+                    result_len
+                ),
+            }
+        }
+    };
+}
+
+/// Declare argument completion and auto-generate the complete function
+///
+/// This macro generates the `generated_complete` function, which looks up
+/// completion providers in the registries generated by the other declaration
+/// macros. List the registries declared in the same module:
+/// - `tools` - from `declare_tools!`
+/// - `prompts` - from `declare_prompts!`
+/// - `resources` - from `declare_resources!` (template variables)
+///
+/// # Example
+///
+/// ```ignore
+/// use mcp_plugin_api::*;
+///
+/// fn complete_sku(partial: &str) -> Completion {
+///     Completion::from_candidates(partial, ["SKU-100", "SKU-200", "SKU-300"])
+/// }
+///
+/// declare_tools! {
+///     tools: [
+///         Tool::builder("get_price", "Get the price of a product")
+///             .param_string("sku", "Product SKU", true)
+///             .complete("sku", complete_sku)
+///             .handler(handle_get_price),
+///     ]
+/// }
+///
+/// declare_completions!(tools);
+///
+/// declare_plugin! {
+///     list_tools: generated_list_tools,
+///     execute_tool: generated_execute_tool,
+///     free_string: mcp_plugin_api::utils::standard_free_string,
+///     complete: generated_complete
+/// }
+/// ```
+#[macro_export]
+macro_rules! declare_completions {
+    ($($source:ident),+ $(,)?) => {
+        /// Auto-generated complete function
+        ///
+        /// Dispatches to the completion provider of the referenced argument.
+        #[no_mangle]
+        pub unsafe extern "C" fn generated_complete(
+            request_json: *const u8,
+            request_len: usize,
+            result_buf: *mut *mut u8,
+            result_len: *mut usize,
+        ) -> i32 {
+            $crate::utils::clear_last_error();
+            // Parse request
+            let request_slice = ::std::slice::from_raw_parts(request_json, request_len);
+            let request = match $crate::encoding::decode(request_slice)
+                .map_err(|e| format!("Invalid JSON request: {}", e))
+                .and_then(|params| $crate::completion::CompletionRequest::from_json(&params))
+            {
+                Ok(r) => r,
+                Err(e) => return $crate::utils::return_error_status(
+                    $crate::PluginStatus::InvalidJson,
+                    &e,
+                    result_buf,
+                    result_len
+                ),
+            };
+
+            // Invalid declarations cannot be searched
+            $(
+                if let Some(e) = $crate::__completion_source_error!($source) {
+                    return $crate::utils::return_error_status(
+                        $crate::PluginStatus::Internal,
+                        e,
+                        result_buf,
+                        result_len
+                    );
+                }
+            )+
+
+            // Search the declared registries in order
+            let mut found = None;
+            $(
+                if found.is_none() {
+                    found = $crate::completion::CompletionSource::find_completion(
+                        &$crate::__completion_source!($source),
+                        &request.reference,
+                        &request.argument,
+                    );
+                }
+            )+
+
+            if found.is_none() {
+                return $crate::utils::return_error_status(
+                    $crate::PluginStatus::NotFound,
+                    &$crate::completion::unknown_reference(&request.reference),
+                    result_buf,
+                    result_len
+                );
+            }
+
+            match $crate::utils::catch_panic(|| $crate::completion::complete(&request, found)) {
+                Ok(Ok(completion)) => $crate::utils::return_success(
+                    completion.to_json(),
+                    result_buf,
+                    result_len
+                ),
+                Ok(Err(e)) => $crate::utils::return_error(
+                    &e,
+                    result_buf,
+                    result_len
+                ),
+                Err(panic) => $crate::utils::return_error_status(
+                    $crate::PluginStatus::Panic,
+                    &format!("Completion provider panicked: {}", panic),
+                    result_buf,
+                    result_len
+                ),
+            }
+        }
+    };
+}
+
+/// Helper macro mapping a declare_completions! source to its registry
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __completion_source {
+    (tools) => {
+        *get_tools()
+    };
+    (prompts) => {
+        get_prompts()
+    };
+    (resources) => {
+        *get_resources()
+    };
+}
+
+/// Helper macro mapping a declare_completions! source to its declaration error
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __completion_source_error {
+    (tools) => {
+        get_tools().error()
+    };
+    (prompts) => {
+        __declared_prompts().as_ref().err().map(|e| e.as_str())
+    };
+    (resources) => {
+        get_resources().error()
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    //! Calls through the functions generated by the declaration macros
+    //!
+    //! Each macro can only be expanded once per test binary, so all tools,
+    //! resources, prompts and hooks under test are declared here. Tests share
+    //! the process-wide metrics and sessions and use distinct names.
+
+    use crate::completion::Completion;
+    use crate::context::ToolContext;
+    use crate::prompt::Prompt;
+    use crate::resource::{Resource, ResourceTemplate};
+    use crate::session::Session;
+    use crate::stream::{ChunkWriter, StreamSink};
+    use crate::utils::{prompt_result, prompt_text_message, resource_text_contents, stream_json_line};
+    use crate::middleware::{Middleware, ToolCall, ToolResult};
+    use crate::metrics;
+    use crate::instance::Instance;
+    use crate::session;
+    use crate::tool::Tool;
+    use crate::PluginStatus;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use std::ffi::CString;
+    use std::collections::HashMap;
+    use std::os::raw::c_void;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    static LOOKUPS: AtomicUsize = AtomicUsize::new(0);
+    static CACHE_MIDDLEWARE_CALLS: AtomicUsize = AtomicUsize::new(0);
+    static MIDDLEWARE_ORDER: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    struct CountCalls(&'static AtomicUsize);
+
+    impl Middleware for CountCalls {
+        fn before(&self, _call: &ToolCall<'_>) -> Result<(), String> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Record when it runs around calls of the `ordered` tool
+    struct RecordOrder(&'static str);
+
+    impl Middleware for RecordOrder {
+        fn before(&self, call: &ToolCall<'_>) -> Result<(), String> {
+            if call.tool.name == "ordered" {
+                MIDDLEWARE_ORDER.lock().unwrap().push(format!("{} before", self.0));
+            }
+            Ok(())
+        }
+
+        fn after(&self, call: &ToolCall<'_>, _result: &mut ToolResult) {
+            if call.tool.name == "ordered" {
+                MIDDLEWARE_ORDER.lock().unwrap().push(format!("{} after", self.0));
+            }
+        }
+    }
+
+    fn handle_ordered(_args: &Value) -> Result<Value, String> {
+        MIDDLEWARE_ORDER.lock().unwrap().push("handler".to_string());
+        Ok(Value::Null)
+    }
+
+    /// Behave as asked by the `outcome` argument
+    fn handle_outcome(args: &Value) -> Result<Value, String> {
+        match args["outcome"].as_str() {
+            Some("error") => Err("failed as asked".to_string()),
+            Some("panic") => panic!("panicked as asked"),
+            Some("slow") => {
+                std::thread::sleep(Duration::from_millis(200));
+                Ok(json!("late"))
+            }
+            _ => Ok(json!("done")),
+        }
+    }
+
+    fn handle_session_visits(ctx: &ToolContext, _args: &Value) -> Result<Value, String> {
+        let session = ctx.session().ok_or("No session")?;
+        let visits = session.update("visits", |visits: &mut u64| {
+            *visits += 1;
+            *visits
+        })?;
+        Ok(json!(visits))
+    }
+
+    fn handle_noop(_args: &Value) -> Result<Value, String> {
+        Ok(Value::Null)
+    }
+
+    fn handle_add_tool(args: &Value) -> Result<Value, String> {
+        let name = args["name"].as_str().ok_or("Missing name")?;
+        register_tool(Tool::builder(name, "Added at runtime").handler(handle_noop))?;
+        Ok(Value::Null)
+    }
+
+    /// Answer with the call count, so cached results can be told apart
+    fn handle_lookup(_args: &Value) -> Result<Value, String> {
+        Ok(json!(LOOKUPS.fetch_add(1, Ordering::SeqCst)))
+    }
+
+    #[derive(Debug, Deserialize, schemars::JsonSchema)]
+    pub struct TestConfig {
+        name: String,
+    }
+
+    crate::declare_plugin_config!(TestConfig);
+    crate::declare_config_schema!(TestConfig);
+
+    static INIT_FAILS: AtomicBool = AtomicBool::new(false);
+
+    fn init() -> Result<(), String> {
+        if INIT_FAILS.load(Ordering::SeqCst) {
+            return Err("not ready".to_string());
+        }
+        Ok(())
+    }
+
+    crate::declare_plugin_init!(init);
+
+    static ENDED_SESSIONS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    fn session_started(session: &Session) -> Result<(), String> {
+        if session.id().starts_with("rejected") {
+            return Err(format!("Session {} rejected", session.id()));
+        }
+        session.insert("greeting", "hello".to_string());
+        Ok(())
+    }
+
+    fn session_ended(session: &Session) {
+        ENDED_SESSIONS.lock().unwrap().push(session.id().to_string());
+    }
+
+    crate::declare_session_hooks! {
+        on_start: session_started,
+        on_end: session_ended
+    }
+
+    fn read_readme(uri: &str) -> Result<Value, String> {
+        Ok(resource_text_contents(uri, Some("text/markdown".to_string()), "# Readme"))
+    }
+
+    fn read_product(uri: &str, vars: &HashMap<String, String>) -> Result<Value, String> {
+        if vars["id"] == "missing" {
+            return Err("No such product".to_string());
+        }
+        Ok(resource_text_contents(uri, None, format!("product {}", vars["id"])))
+    }
+
+    fn complete_product_id(partial: &str) -> Completion {
+        Completion::from_candidates(partial, ["p-100", "p-200", "q-300"])
+    }
+
+    declare_resources! {
+        resources: [
+            Resource::builder("docs://readme", "README")
+                .mime_type("text/markdown")
+                .handler(read_readme),
+        ],
+        templates: [
+            ResourceTemplate::builder("product://{id}.json", "Product")
+                .complete("id", complete_product_id)
+                .handler(read_product),
+        ]
+    }
+
+    fn handle_summarize(args: &HashMap<String, String>) -> Result<Value, String> {
+        let text = format!("Summarize order {}", args["order_id"]);
+        Ok(prompt_result(None, vec![prompt_text_message("user", text)]))
+    }
+
+    fn complete_order_id(partial: &str) -> Completion {
+        Completion::from_candidates(partial, ["A-100", "A-101", "B-200"])
+    }
+
+    declare_prompts! {
+        prompts: [
+            Prompt::builder("summarize_order", "Summarize an order")
+                .argument("order_id", "The order to summarize", true)
+                .complete("order_id", complete_order_id)
+                .handler(handle_summarize),
+        ]
+    }
+
+    fn complete_tool_name(partial: &str) -> Completion {
+        Completion::from_candidates(partial, ["probe_one", "probe_two"])
+    }
+
+    declare_completions!(tools, prompts, resources);
+
+    fn handle_config_names(_args: &Value) -> Result<Value, String> {
+        Ok(json!([get_config().name, get_instance_config().name]))
+    }
+
+    fn handle_panic(_args: &Value) -> Result<Value, String> {
+        panic!("handler exploded")
+    }
+
+    /// Stream one JSON line per row and report how many were written
+    fn handle_export(_ctx: &ToolContext, args: &Value, out: &mut ChunkWriter) -> Result<Value, String> {
+        let rows = args["rows"].as_u64().unwrap_or(0);
+        for row in 0..rows {
+            stream_json_line(out, &json!({ "row": row }))?;
+        }
+        Ok(json!({ "rows": rows }))
+    }
+
+    declare_tools! {
+        middleware: [RecordOrder("global 1"), RecordOrder("global 2")],
+        tools: [
+            Tool::builder("config_names", "Global and instance configuration")
+                .handler(handle_config_names),
+            Tool::builder("panics", "Always panics").handler(handle_panic),
+            Tool::builder("cached_lookup", "Lookup with a result cache")
+                .param_i64("id", "Item id", true)
+                .cache(Duration::from_secs(60), 10)
+                .middleware(CountCalls(&CACHE_MIDDLEWARE_CALLS))
+                .handler(handle_lookup),
+            Tool::builder("ordered", "Records the middleware order")
+                .middleware(RecordOrder("local 1"))
+                .middleware(RecordOrder("local 2"))
+                .handler(handle_ordered),
+            Tool::builder("outcome", "Succeeds, fails, panics or runs late")
+                .param_string("outcome", "What to do", false)
+                .timeout(Duration::from_millis(50))
+                .handler(handle_outcome),
+            Tool::builder("guarded", "Needs the admin scope")
+                .require_scopes(&["admin"])
+                .handler(handle_noop),
+            Tool::builder("session_visits", "Counts calls per session")
+                .handler_with_context(handle_session_visits),
+            Tool::builder("add_tool", "Registers a tool")
+                .param_string("name", "Tool name", true)
+                .complete("name", complete_tool_name)
+                .handler(handle_add_tool),
+            Tool::builder("export", "Streams JSON lines")
+                .param_i64("rows", "Number of rows", true)
+                .streaming_handler(handle_export),
+        ]
+    }
+
+    const DECLARED: [&str; 9] = [
+        "config_names",
+        "panics",
+        "cached_lookup",
+        "ordered",
+        "outcome",
+        "guarded",
+        "session_visits",
+        "add_tool",
+        "export",
+    ];
+
+    /// Stream sink keeping every chunk, stopping after `accept` of them
+    struct RecordingSink {
+        chunks: Vec<String>,
+        accept: usize,
+    }
+
+    unsafe extern "C" fn record_chunk(sink_data: *mut c_void, chunk: *const u8, chunk_len: usize) -> i32 {
+        let sink = &mut *(sink_data as *mut RecordingSink);
+        if sink.chunks.len() == sink.accept {
+            return 1;
+        }
+        let chunk = std::slice::from_raw_parts(chunk, chunk_len);
+        sink.chunks.push(String::from_utf8(chunk.to_vec()).unwrap());
+        0
+    }
+
+    /// Call a tool through `generated_execute_tool_stream`
+    fn call_stream(tool: &str, args: Value, recorder: &mut RecordingSink) -> (i32, Value) {
+        let name = CString::new(tool).unwrap();
+        let args = args.to_string();
+        let sink = StreamSink {
+            sink_data: recorder as *mut RecordingSink as *mut c_void,
+            write: record_chunk,
+        };
+        output(|buf, len| unsafe {
+            generated_execute_tool_stream(
+                name.as_ptr(),
+                args.as_ptr(),
+                args.len(),
+                std::ptr::null(),
+                0,
+                &sink,
+                buf,
+                len,
+            )
+        })
+    }
+
+    /// Call a tool through `generated_execute_tool_with_context`
+    fn call(tool: &str, args: Value, ctx: Value) -> (i32, Value) {
+        let name = CString::new(tool).unwrap();
+        let args = args.to_string();
+        let ctx = ctx.to_string();
+        let mut buf = std::ptr::null_mut();
+        let mut len = 0;
+        unsafe {
+            let status = generated_execute_tool_with_context(
+                name.as_ptr(),
+                args.as_ptr(),
+                args.len(),
+                ctx.as_ptr(),
+                ctx.len(),
+                &mut buf,
+                &mut len,
+            );
+            let result = serde_json::from_slice(std::slice::from_raw_parts(buf, len)).unwrap();
+            crate::utils::standard_free_string(buf, len);
+            (status, result)
+        }
+    }
+
+    /// Status and decoded result of a generated function
+    fn output(call: impl FnOnce(*mut *mut u8, *mut usize) -> i32) -> (i32, Value) {
+        let mut buf = std::ptr::null_mut();
+        let mut len = 0;
+        let status = call(&mut buf, &mut len);
+        unsafe {
+            let result = serde_json::from_slice(std::slice::from_raw_parts(buf, len)).unwrap();
+            crate::utils::standard_free_string(buf, len);
+            (status, result)
+        }
+    }
+
+    /// Names of the tools listed by `generated_list_tools`
+    fn list_tools() -> Vec<String> {
+        let mut buf = std::ptr::null_mut();
+        let mut len = 0;
+        let list: Value = unsafe {
+            assert_eq!(generated_list_tools(&mut buf, &mut len), PluginStatus::Ok.code());
+            let list = serde_json::from_slice(std::slice::from_raw_parts(buf, len)).unwrap();
+            crate::utils::standard_free_string(buf, len);
+            list
+        };
+        list.as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    /// Names of the tools listed by `generated_instance_list_tools`
+    fn list_instance_tools(instance: *mut Instance) -> Vec<String> {
+        let mut buf = std::ptr::null_mut();
+        let mut len = 0;
+        let list: Value = unsafe {
+            let status = generated_instance_list_tools(instance, &mut buf, &mut len);
+            assert_eq!(status, PluginStatus::Ok.code());
+            let list = serde_json::from_slice(std::slice::from_raw_parts(buf, len)).unwrap();
+            crate::utils::standard_free_string(buf, len);
+            list
+        };
+        list.as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    /// Call a tool of an instance through `generated_instance_execute_tool`
+    fn call_instance(instance: *mut Instance, tool: &str, args: Value) -> (i32, Value) {
+        let name = CString::new(tool).unwrap();
+        let args = args.to_string();
+        let mut buf = std::ptr::null_mut();
+        let mut len = 0;
+        unsafe {
+            let status = generated_instance_execute_tool(
+                instance,
+                name.as_ptr(),
+                args.as_ptr(),
+                args.len(),
+                std::ptr::null(),
+                0,
+                &mut buf,
+                &mut len,
+            );
+            let result = serde_json::from_slice(std::slice::from_raw_parts(buf, len)).unwrap();
+            crate::utils::standard_free_string(buf, len);
+            (status, result)
+        }
+    }
+
+    #[test]
+    fn instances_see_their_own_configuration() {
+        let global = br#"{ "name": "global" }"#;
+        let other = br#"{ "name": "other" }"#;
+        unsafe {
+            assert_eq!(plugin_configure(global.as_ptr(), global.len()), PluginStatus::Ok.code());
+            let instance = plugin_create_instance(other.as_ptr(), other.len());
+            assert!(!instance.is_null());
+
+            let global_names = call("config_names", json!({}), json!({})).1;
+            assert_eq!(global_names, json!(["global", "global"]));
+            let instance_names = call_instance(instance, "config_names", json!({})).1;
+            assert_eq!(instance_names, json!(["global", "other"]));
+
+            plugin_destroy_instance(instance);
+        }
+        let config: &'static TestConfig = get_config();
+        assert_eq!(config.name, "global");
+    }
+
+    #[test]
+    fn failures_map_to_status_codes_and_last_error() {
+        let (status, error) = call("no_such_tool", json!({}), json!({}));
+        assert_eq!(status, PluginStatus::NotFound.code());
+        assert_eq!(error["error"], "Unknown tool: no_such_tool");
+        assert_eq!(crate::utils::last_error().as_deref(), Some("Unknown tool: no_such_tool"));
+
+        let (status, error) = call("panics", json!({}), json!({}));
+        assert_eq!(status, PluginStatus::Panic.code());
+        assert!(error["error"].as_str().unwrap().contains("handler exploded"));
+
+        let config = b"{ not json";
+        let status = unsafe { plugin_configure(config.as_ptr(), config.len()) };
+        assert_eq!(status, PluginStatus::InvalidJson.code());
+        assert!(crate::utils::last_error().unwrap().starts_with("Failed to parse plugin config"));
+
+        // A successful call clears the message of the failed one
+        let mut buf = std::ptr::null_mut();
+        let mut len = 0;
+        unsafe {
+            assert_eq!(generated_list_tools(&mut buf, &mut len), PluginStatus::Ok.code());
+            crate::utils::standard_free_string(buf, len);
+        }
+        assert_eq!(crate::utils::last_error(), None);
+    }
+
+    #[test]
+    fn invalid_declarations_are_reported_not_raised() {
+        let tools = crate::registry::ToolRegistry::invalid("Invalid tool declaration: bad name");
+        let name = CString::new("cached_lookup").unwrap();
+        let mut buf = std::ptr::null_mut();
+        let mut len = 0;
+        unsafe {
+            let status = __list_tools(&tools, &mut buf, &mut len);
+            assert_eq!(status, PluginStatus::Internal.code());
+            crate::utils::standard_free_string(buf, len);
+
+            let status = __dispatch_tool(
+                &tools,
+                name.as_ptr(),
+                b"{}".as_ptr(),
+                2,
+                &crate::context::ToolContext::new(),
+                None,
+                &mut buf,
+                &mut len,
+            );
+            assert_eq!(status, PluginStatus::Internal.code());
+            crate::utils::standard_free_string(buf, len);
+        }
+        assert_eq!(
+            crate::utils::last_error().as_deref(),
+            Some("Invalid tool declaration: bad name")
+        );
+    }
+
+    #[test]
+    fn cache_hits_run_middleware_and_are_scoped_per_caller() {
+        let alice = json!({ "principal": { "subject": "alice", "tenant": "a" } });
+        let other_tenant = json!({ "principal": { "subject": "alice", "tenant": "b" } });
+
+        let (status, first) = call("cached_lookup", json!({ "id": 1 }), alice.clone());
+        assert_eq!(status, PluginStatus::Ok.code());
+        assert_eq!(call("cached_lookup", json!({ "id": 1 }), alice.clone()).1, first);
+        assert_ne!(call("cached_lookup", json!({ "id": 1 }), other_tenant).1, first);
+        assert_eq!(CACHE_MIDDLEWARE_CALLS.load(Ordering::SeqCst), 3);
+
+        assert!(invalidate_tool_cache("cached_lookup", Some(&json!({ "id": 1 }))));
+        assert_ne!(call("cached_lookup", json!({ "id": 1 }), alice).1, first);
+        assert!(!invalidate_tool_cache("unknown_tool", None));
+
+        let metrics = &metrics::snapshot()["cached_lookup"];
+        assert_eq!((metrics.cache_hits, metrics.cache_misses), (1, 3));
+        assert_eq!(metrics.calls, 4);
+    }
+
+    #[test]
+    fn tools_are_listed_in_declaration_order() {
+        let listed = list_tools();
+        assert_eq!(&listed[..DECLARED.len()], DECLARED);
+    }
+
+    #[test]
+    fn etag_is_stable_until_the_tool_list_changes() {
+        let etag = unsafe { generated_tools_etag() };
+        assert_eq!(unsafe { generated_tools_etag() }, etag);
+
+        register_tool(Tool::builder("etag_probe", "Added by a test").handler(handle_noop)).unwrap();
+        assert_ne!(unsafe { generated_tools_etag() }, etag);
+        assert!(register_tool(Tool::builder("etag_probe", "Again").handler(handle_noop)).is_err());
+
+        assert!(unregister_tool("etag_probe"));
+        assert_eq!(unsafe { generated_tools_etag() }, etag);
+        assert!(!unregister_tool("etag_probe"));
+    }
+
+    #[test]
+    fn global_middleware_runs_outside_tool_middleware() {
+        assert_eq!(call("ordered", json!({}), json!({})).0, PluginStatus::Ok.code());
+        assert_eq!(
+            *MIDDLEWARE_ORDER.lock().unwrap(),
+            [
+                "global 1 before",
+                "global 2 before",
+                "local 1 before",
+                "local 2 before",
+                "handler",
+                "local 2 after",
+                "local 1 after",
+                "global 2 after",
+                "global 1 after",
+            ]
+        );
+    }
+
+    #[test]
+    fn outcomes_map_to_status_codes_and_metrics() {
+        let admin = json!({ "principal": { "subject": "root", "scopes": ["admin"] } });
+        let guest = json!({ "principal": { "subject": "guest" } });
+
+        let (status, result) = call("outcome", json!({}), json!({}));
+        assert_eq!(status, PluginStatus::Ok.code());
+        assert_eq!(result, json!("done"));
+
+        let (status, error) = call("outcome", json!({ "outcome": "error" }), json!({}));
+        assert_eq!(status, PluginStatus::HandlerError.code());
+        assert_eq!(error["error"], "failed as asked");
+
+        let (status, error) = call("outcome", json!({ "outcome": "panic" }), json!({}));
+        assert_eq!(status, PluginStatus::Panic.code());
+        assert_eq!(error["error"], "Tool 'outcome' panicked: panicked as asked");
+
+        let (status, error) = call("outcome", json!({ "outcome": "slow" }), json!({}));
+        assert_eq!(status, PluginStatus::Timeout.code());
+        assert_eq!(error["error"], "Tool 'outcome' timed out after 50 ms");
+
+        assert_eq!(call("guarded", json!({}), admin).0, PluginStatus::Ok.code());
+        let (status, error) = call("guarded", json!({}), guest);
+        assert_eq!(status, PluginStatus::Forbidden.code());
+        assert_eq!(error["error"], "Tool 'guarded' denied: Missing required scopes: admin");
+
+        let name = CString::new("outcome").unwrap();
+        let mut buf = std::ptr::null_mut();
+        let mut len = 0;
+        unsafe {
+            let status = generated_execute_tool(name.as_ptr(), b"{ oops".as_ptr(), 6, &mut buf, &mut len);
+            assert_eq!(status, PluginStatus::InvalidJson.code());
+            crate::utils::standard_free_string(buf, len);
+        }
+
+        let snapshot = metrics::snapshot();
+        let outcome = &snapshot["outcome"];
+        assert_eq!(outcome.calls, 4);
+        assert_eq!(outcome.errors, 3);
+        assert_eq!(
+            (outcome.handler_errors, outcome.panics, outcome.timeouts, outcome.denied),
+            (1, 1, 1, 0)
+        );
+        assert_eq!(outcome.latency.count, 4);
+        let guarded = &snapshot["guarded"];
+        assert_eq!((guarded.calls, guarded.errors, guarded.denied), (2, 1, 1));
+    }
+
+    #[test]
+    fn sessions_last_from_start_to_end() {
+        let id = CString::new("macros-test-session").unwrap();
+        let ctx = json!({ "sessionId": "macros-test-session" });
+        let visit = || call("session_visits", json!({}), ctx.clone());
+
+        assert_eq!(visit(), (PluginStatus::HandlerError.code(), json!({ "error": "No session" })));
+
+        unsafe {
+            assert_eq!(session::standard_session_start(id.as_ptr()), PluginStatus::Ok.code());
+        }
+        assert_eq!(visit(), (PluginStatus::Ok.code(), json!(1)));
+        assert_eq!(visit(), (PluginStatus::Ok.code(), json!(2)));
+        assert!(session::ids().contains(&"macros-test-session".to_string()));
+
+        unsafe {
+            assert_eq!(session::standard_session_end(id.as_ptr()), PluginStatus::Ok.code());
+        }
+        assert_eq!(visit().0, PluginStatus::HandlerError.code());
+
+        // A restarted session begins empty
+        unsafe {
+            assert_eq!(session::standard_session_start(id.as_ptr()), PluginStatus::Ok.code());
+        }
+        assert_eq!(visit(), (PluginStatus::Ok.code(), json!(1)));
+        unsafe {
+            session::standard_session_end(id.as_ptr());
+        }
+    }
+
+    #[test]
+    fn tools_registered_on_an_instance_stay_there() {
+        let config = br#"{ "name": "isolated" }"#;
+        unsafe {
+            let instance = plugin_create_instance(config.as_ptr(), config.len());
+            let other = plugin_create_instance(config.as_ptr(), config.len());
+            assert!(!instance.is_null() && !other.is_null());
+
+            let (status, _) = call_instance(instance, "add_tool", json!({ "name": "instance_only" }));
+            assert_eq!(status, PluginStatus::Ok.code());
+
+            assert!(list_instance_tools(instance).contains(&"instance_only".to_string()));
+            assert_eq!(call_instance(instance, "instance_only", json!({})).0, PluginStatus::Ok.code());
+
+            assert!(!list_instance_tools(other).contains(&"instance_only".to_string()));
+            assert!(!list_tools().contains(&"instance_only".to_string()));
+            assert_eq!(call("instance_only", json!({}), json!({})).0, PluginStatus::NotFound.code());
+            let (status, _) = call_instance(other, "instance_only", json!({}));
+            assert_eq!(status, PluginStatus::NotFound.code());
+
+            plugin_destroy_instance(instance);
+            plugin_destroy_instance(other);
+        }
+    }
+
+    #[test]
+    fn init_reports_errors_in_the_buffer() {
+        let mut buf = std::ptr::null_mut();
+        let mut len = 0;
+        assert_eq!(unsafe { plugin_init(&mut buf, &mut len) }, PluginStatus::Ok.code());
+        assert!(buf.is_null());
+
+        INIT_FAILS.store(true, Ordering::SeqCst);
+        let (status, error) = output(|buf, len| unsafe { plugin_init(buf, len) });
+        INIT_FAILS.store(false, Ordering::SeqCst);
+        assert_eq!(status, PluginStatus::HandlerError.code());
+        assert_eq!(error, json!({ "error": "not ready" }));
+    }
+
+    #[test]
+    fn config_schema_describes_the_config_type() {
+        let (status, schema) = output(|buf, len| unsafe { plugin_get_config_schema(buf, len) });
+        assert_eq!(status, PluginStatus::Ok.code());
+        assert_eq!(schema["title"], "TestConfig");
+        assert_eq!(schema["properties"]["name"]["type"], "string");
+        assert_eq!(schema["required"], json!(["name"]));
+    }
+
+    #[test]
+    fn session_hooks_can_reject_and_see_the_end() {
+        let rejected = CString::new("rejected-session").unwrap();
+        unsafe {
+            assert_eq!(plugin_session_start(rejected.as_ptr()), PluginStatus::HandlerError.code());
+        }
+        assert_eq!(
+            crate::utils::last_error().as_deref(),
+            Some("Session rejected-session rejected")
+        );
+        assert!(session::get("rejected-session").is_none());
+
+        let id = CString::new("hooked-session").unwrap();
+        unsafe {
+            assert_eq!(plugin_session_start(id.as_ptr()), PluginStatus::Ok.code());
+        }
+        let session = session::get("hooked-session").unwrap();
+        assert_eq!(session.get::<String>("greeting").as_deref(), Some("hello"));
+
+        unsafe {
+            assert_eq!(plugin_session_end(id.as_ptr()), PluginStatus::Ok.code());
+            // Ending it again is not an error, and does not run the hook
+            assert_eq!(plugin_session_end(id.as_ptr()), PluginStatus::Ok.code());
+        }
+        assert!(session::get("hooked-session").is_none());
+        let ended = ENDED_SESSIONS.lock().unwrap();
+        assert_eq!(ended.iter().filter(|id| *id == "hooked-session").count(), 1);
+        assert!(!ended.contains(&"rejected-session".to_string()));
+    }
+
+    #[test]
+    fn resources_are_routed_by_uri() {
+        let read = |uri: &str| {
+            let uri = CString::new(uri).unwrap();
+            output(|buf, len| unsafe { generated_read_resource(uri.as_ptr(), buf, len) })
+        };
+
+        let (status, readme) = read("docs://readme");
+        assert_eq!(status, PluginStatus::Ok.code());
+        assert_eq!(readme["contents"][0]["text"], "# Readme");
+        assert_eq!(readme["contents"][0]["mimeType"], "text/markdown");
+
+        let (status, product) = read("product://a.b.json");
+        assert_eq!(status, PluginStatus::Ok.code());
+        assert_eq!(product["contents"][0]["text"], "product a.b");
+        assert_eq!(product["contents"][0]["uri"], "product://a.b.json");
+
+        let (status, error) = read("product://missing.json");
+        assert_eq!(status, PluginStatus::HandlerError.code());
+        assert_eq!(error["error"], "No such product");
+
+        let (status, error) = read("docs://unknown");
+        assert_eq!(status, PluginStatus::NotFound.code());
+        assert_eq!(error["error"], "Unknown resource: docs://unknown");
+
+        let (_, resources) = output(|buf, len| unsafe { generated_list_resources(buf, len) });
+        assert_eq!(resources[0]["uri"], "docs://readme");
+        let (_, templates) = output(|buf, len| unsafe { generated_list_resource_templates(buf, len) });
+        assert_eq!(templates[0]["uriTemplate"], "product://{id}.json");
+    }
+
+    #[test]
+    fn prompts_check_required_arguments() {
+        let get = |name: &str, args: &[u8]| {
+            let name = CString::new(name).unwrap();
+            output(|buf, len| unsafe {
+                generated_get_prompt(name.as_ptr(), args.as_ptr(), args.len(), buf, len)
+            })
+        };
+
+        let (status, prompt) = get("summarize_order", br#"{ "order_id": "A-100" }"#);
+        assert_eq!(status, PluginStatus::Ok.code());
+        assert_eq!(prompt["messages"][0]["content"]["text"], "Summarize order A-100");
+
+        let (status, error) = get("summarize_order", b"");
+        assert_eq!(status, PluginStatus::HandlerError.code());
+        assert_eq!(error["error"], "Missing required argument: order_id");
+
+        let (status, _) = get("summarize_order", b"{ oops");
+        assert_eq!(status, PluginStatus::InvalidJson.code());
+
+        let (status, error) = get("unknown_prompt", b"");
+        assert_eq!(status, PluginStatus::NotFound.code());
+        assert_eq!(error["error"], "Unknown prompt: unknown_prompt");
+
+        let (_, prompts) = output(|buf, len| unsafe { generated_list_prompts(buf, len) });
+        assert_eq!(prompts[0]["arguments"][0]["required"], true);
+    }
+
+    #[test]
+    fn completions_are_found_for_every_kind_of_reference() {
+        let complete = |reference: Value, argument: &str, value: &str| {
+            let request = json!({ "ref": reference, "argument": { "name": argument, "value": value } });
+            let request = request.to_string();
+            output(|buf, len| unsafe { generated_complete(request.as_ptr(), request.len(), buf, len) })
+        };
+
+        let (status, tool) = complete(json!({ "type": "ref/tool", "name": "add_tool" }), "name", "probe_t");
+        assert_eq!(status, PluginStatus::Ok.code());
+        assert_eq!(tool["completion"]["values"], json!(["probe_two"]));
+
+        let prompt_ref = json!({ "type": "ref/prompt", "name": "summarize_order" });
+        let (status, prompt) = complete(prompt_ref, "order_id", "A-");
+        assert_eq!(status, PluginStatus::Ok.code());
+        assert_eq!(prompt["completion"]["values"], json!(["A-100", "A-101"]));
+
+        let resource_ref = json!({ "type": "ref/resource", "uri": "product://{id}.json" });
+        let (status, resource) = complete(resource_ref, "id", "p-");
+        assert_eq!(status, PluginStatus::Ok.code());
+        assert_eq!(resource["completion"]["values"], json!(["p-100", "p-200"]));
+
+        // Arguments without a provider complete to nothing
+        let (status, none) = complete(json!({ "type": "ref/tool", "name": "outcome" }), "outcome", "");
+        assert_eq!(status, PluginStatus::Ok.code());
+        assert_eq!(none["completion"]["values"], json!([]));
+
+        let (status, error) = complete(json!({ "type": "ref/prompt", "name": "nope" }), "x", "");
+        assert_eq!(status, PluginStatus::NotFound.code());
+        assert_eq!(error["error"], "Unknown prompt: nope");
+    }
+
+    #[test]
+    fn streamed_chunks_arrive_in_order_before_the_result() {
+        let mut recorder = RecordingSink { chunks: Vec::new(), accept: usize::MAX };
+        let (status, result) = call_stream("export", json!({ "rows": 3 }), &mut recorder);
+
+        assert_eq!(status, PluginStatus::Ok.code());
+        assert_eq!(result, json!({ "rows": 3 }));
+        assert_eq!(recorder.chunks, ["{\"row\":0}\n", "{\"row\":1}\n", "{\"row\":2}\n"]);
+    }
+
+    #[test]
+    fn stopped_streams_fail_the_call() {
+        let mut recorder = RecordingSink { chunks: Vec::new(), accept: 2 };
+        let (status, result) = call_stream("export", json!({ "rows": 5 }), &mut recorder);
+
+        assert_eq!(status, PluginStatus::HandlerError.code());
+        assert_eq!(result, json!({ "error": "Call was cancelled" }));
+        assert_eq!(recorder.chunks, ["{\"row\":0}\n", "{\"row\":1}\n"]);
+    }
+
+    #[test]
+    fn streaming_tools_are_buffered_without_a_sink() {
+        let (status, result) = call("export", json!({ "rows": 2 }), json!({}));
+
+        assert_eq!(status, PluginStatus::Ok.code());
+        assert_eq!(result["content"][0]["text"], "{\"row\":0}\n{\"row\":1}\n");
+        assert_eq!(result["structuredContent"], json!({ "rows": 2 }));
+    }
+}
@@ -0,0 +1,487 @@
+//! Built-in tool metrics
+//!
+//! The dispatcher generated by `declare_tools!` records every executed call:
+//!
+//! - `calls`: number of calls
+//! - `errors`: failed calls of any kind, also counted on their own as
+//!   `handlerErrors` (the handler returned an error), `panics`, `timeouts`
+//!   and `denied` calls
+//! - `latency`: a histogram of call durations over [`LATENCY_BUCKETS`]
+//! - `cacheHits`, `cacheMisses`: calls answered from the tool's
+//!   [cache](crate::cache) and calls that ran the handler, for tools with one
+//!
+//! Metrics are kept per listed tool name and process-wide, so calls of all
+//! [instances](crate::instance) add up. Calls of unknown tools and calls with
+//! invalid arguments are not recorded.
+//!
+//! Hosts read them through `get_metrics` (see [`GetMetricsFn`]), which
+//! `declare_plugin!` always sets, and aggregate them across plugins. JSON is
+//! always available:
+//!
+//! ```json
+//! {
+//!   "tools": {
+//!     "search": {
+//!       "calls": 3,
+//!       "errors": 1,
+//!       "handlerErrors": 0,
+//!       "panics": 0,
+//!       "timeouts": 1,
+//!       "denied": 0,
+//!       "cacheHits": 0,
+//!       "cacheMisses": 0,
+//!       "latency": {
+//!         "buckets": [{ "le": 0.001, "count": 0 }, { "le": 0.005, "count": 2 }],
+//!         "sumSeconds": 0.0123,
+//!         "count": 3
+//!       }
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! Bucket counts are cumulative. With the `prometheus` cargo feature,
+//! [`MetricsFormat::Prometheus`] returns the Prometheus text exposition format
+//! instead.
+
+use crate::tool::ToolOutcome;
+use crate::PluginStatus;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+/// Function signature for reading the plugin's metrics
+///
+/// # Parameters
+/// - `format`: A [`MetricsFormat`] discriminant
+/// - `result_buf`: Output pointer for the UTF-8 text (allocated by plugin)
+/// - `result_len`: Output capacity of buffer
+///
+/// # Returns
+/// - 0 on success
+/// - [`PluginStatus::Unsupported`] if the plugin does not support the format
+pub type GetMetricsFn = unsafe extern "C" fn(u32, *mut *mut u8, *mut usize) -> i32;
+
+/// Output format of `get_metrics`
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MetricsFormat {
+    /// JSON document (always supported)
+    #[default]
+    Json = 0,
+    /// Prometheus text exposition format (`prometheus` feature)
+    Prometheus = 1,
+}
+
+impl MetricsFormat {
+    /// Look up a format by its discriminant
+    pub fn from_u32(value: u32) -> Option<MetricsFormat> {
+        match value {
+            0 => Some(MetricsFormat::Json),
+            1 => Some(MetricsFormat::Prometheus),
+            _ => None,
+        }
+    }
+
+    /// Whether this build of the crate can produce the format
+    pub const fn is_supported(self) -> bool {
+        match self {
+            MetricsFormat::Json => true,
+            MetricsFormat::Prometheus => cfg!(feature = "prometheus"),
+        }
+    }
+}
+
+/// Upper bounds of the latency histogram buckets, in seconds
+pub const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Default)]
+struct Counters {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    handler_errors: AtomicU64,
+    panics: AtomicU64,
+    timeouts: AtomicU64,
+    denied: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    // Not cumulative; the last entry counts calls above all bounds
+    buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+}
+
+impl Counters {
+    fn record(&self, outcome: &ToolOutcome, elapsed: Duration) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+
+        if !matches!(outcome, ToolOutcome::Done(Ok(_))) {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        let kind = match outcome {
+            ToolOutcome::Done(Ok(_)) => None,
+            ToolOutcome::Done(Err(_)) => Some(&self.handler_errors),
+            ToolOutcome::Panicked(_) => Some(&self.panics),
+            ToolOutcome::TimedOut(_) => Some(&self.timeouts),
+            ToolOutcome::Forbidden(_) => Some(&self.denied),
+        };
+        if let Some(counter) = kind {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let seconds = elapsed.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| seconds <= bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> ToolMetrics {
+        let mut cumulative = 0;
+        let buckets = LATENCY_BUCKETS
+            .iter()
+            .zip(&self.buckets)
+            .map(|(&le, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                Bucket {
+                    le,
+                    count: cumulative,
+                }
+            })
+            .collect();
+        let count = cumulative + self.buckets[LATENCY_BUCKETS.len()].load(Ordering::Relaxed);
+
+        ToolMetrics {
+            calls: self.calls.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            handler_errors: self.handler_errors.load(Ordering::Relaxed),
+            panics: self.panics.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            denied: self.denied.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            latency: Histogram {
+                buckets,
+                sum_seconds: self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+                count,
+            },
+        }
+    }
+}
+
+/// Metrics of one tool
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolMetrics {
+    /// Number of calls
+    pub calls: u64,
+    /// Failed calls of any kind
+    pub errors: u64,
+    /// Calls whose handler returned an error
+    pub handler_errors: u64,
+    /// Calls whose handler panicked
+    pub panics: u64,
+    /// Calls that ran past their deadline
+    pub timeouts: u64,
+    /// Calls rejected for missing scopes
+    pub denied: u64,
+    /// Calls answered from the tool's cache
+    pub cache_hits: u64,
+    /// Calls of a cached tool that ran the handler
+    pub cache_misses: u64,
+    /// Call durations
+    pub latency: Histogram,
+}
+
+/// Latency histogram
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Histogram {
+    /// Cumulative counts per bucket of [`LATENCY_BUCKETS`]
+    pub buckets: Vec<Bucket>,
+    /// Total duration of all calls, in seconds
+    pub sum_seconds: f64,
+    /// Number of observed calls, including those above the last bucket
+    pub count: u64,
+}
+
+/// Histogram bucket
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Bucket {
+    /// Upper bound in seconds
+    pub le: f64,
+    /// Calls that took at most `le` seconds
+    pub count: u64,
+}
+
+static METRICS: OnceLock<RwLock<BTreeMap<String, Arc<Counters>>>> = OnceLock::new();
+
+fn metrics() -> &'static RwLock<BTreeMap<String, Arc<Counters>>> {
+    METRICS.get_or_init(|| RwLock::new(BTreeMap::new()))
+}
+
+fn counters(tool: &str) -> Arc<Counters> {
+    let counters = {
+        let metrics = metrics().read().unwrap_or_else(|e| e.into_inner());
+        metrics.get(tool).cloned()
+    };
+    counters.unwrap_or_else(|| {
+        let mut metrics = metrics().write().unwrap_or_else(|e| e.into_inner());
+        metrics.entry(tool.to_string()).or_default().clone()
+    })
+}
+
+/// Record a finished call
+///
+/// Called by the dispatcher of `declare_tools!`; plugins with a hand-written
+/// `execute_tool` can call it to report their own tools.
+pub fn record(tool: &str, outcome: &ToolOutcome, elapsed: Duration) {
+    counters(tool).record(outcome, elapsed);
+}
+
+/// Record a cache lookup of a tool
+///
+/// Called by [`ToolCache`](crate::ToolCache) once it
+/// [reports](crate::ToolCache::report_metrics) to the tool's metrics.
+pub fn record_cache(tool: &str, hit: bool) {
+    let counters = counters(tool);
+    let counter = if hit {
+        &counters.cache_hits
+    } else {
+        &counters.cache_misses
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Metrics of all tools called so far, by tool name
+pub fn snapshot() -> BTreeMap<String, ToolMetrics> {
+    let metrics = metrics().read().unwrap_or_else(|e| e.into_inner());
+    metrics
+        .iter()
+        .map(|(tool, counters)| (tool.clone(), counters.snapshot()))
+        .collect()
+}
+
+/// Forget all recorded metrics
+pub fn reset() {
+    metrics().write().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// Metrics as the JSON document returned by `get_metrics`
+pub fn to_json() -> Value {
+    serde_json::json!({ "tools": snapshot() })
+}
+
+/// Metrics in the Prometheus text exposition format
+///
+/// Hosts exporting metrics of several plugins should add a label telling
+/// them apart.
+#[cfg(feature = "prometheus")]
+pub fn to_prometheus() -> String {
+    use std::fmt::Write;
+
+    fn escape(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    }
+
+    let snapshot: Vec<_> = snapshot()
+        .into_iter()
+        .map(|(tool, metrics)| (escape(&tool), metrics))
+        .collect();
+    let mut out = String::new();
+
+    out.push_str("# HELP mcp_tool_calls_total Tool calls.\n");
+    out.push_str("# TYPE mcp_tool_calls_total counter\n");
+    for (tool, metrics) in &snapshot {
+        let _ = writeln!(out, "mcp_tool_calls_total{{tool=\"{}\"}} {}", tool, metrics.calls);
+    }
+
+    out.push_str("# HELP mcp_tool_errors_total Failed tool calls by kind.\n");
+    out.push_str("# TYPE mcp_tool_errors_total counter\n");
+    for (tool, metrics) in &snapshot {
+        for (kind, count) in [
+            ("error", metrics.handler_errors),
+            ("panic", metrics.panics),
+            ("timeout", metrics.timeouts),
+            ("denied", metrics.denied),
+        ] {
+            let _ = writeln!(
+                out,
+                "mcp_tool_errors_total{{tool=\"{}\",kind=\"{}\"}} {}",
+                tool, kind, count
+            );
+        }
+    }
+
+    out.push_str("# HELP mcp_tool_cache_hits_total Tool calls answered from the cache.\n");
+    out.push_str("# TYPE mcp_tool_cache_hits_total counter\n");
+    for (tool, metrics) in &snapshot {
+        let _ = writeln!(out, "mcp_tool_cache_hits_total{{tool=\"{}\"}} {}", tool, metrics.cache_hits);
+    }
+
+    out.push_str("# HELP mcp_tool_cache_misses_total Tool calls that missed the cache.\n");
+    out.push_str("# TYPE mcp_tool_cache_misses_total counter\n");
+    for (tool, metrics) in &snapshot {
+        let _ = writeln!(out, "mcp_tool_cache_misses_total{{tool=\"{}\"}} {}", tool, metrics.cache_misses);
+    }
+
+    out.push_str("# HELP mcp_tool_duration_seconds Tool call duration.\n");
+    out.push_str("# TYPE mcp_tool_duration_seconds histogram\n");
+    for (tool, metrics) in &snapshot {
+        let latency = &metrics.latency;
+        for bucket in &latency.buckets {
+            let _ = writeln!(
+                out,
+                "mcp_tool_duration_seconds_bucket{{tool=\"{}\",le=\"{}\"}} {}",
+                tool, bucket.le, bucket.count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "mcp_tool_duration_seconds_bucket{{tool=\"{}\",le=\"+Inf\"}} {}",
+            tool, latency.count
+        );
+        let _ = writeln!(
+            out,
+            "mcp_tool_duration_seconds_sum{{tool=\"{}\"}} {}",
+            tool, latency.sum_seconds
+        );
+        let _ = writeln!(
+            out,
+            "mcp_tool_duration_seconds_count{{tool=\"{}\"}} {}",
+            tool, latency.count
+        );
+    }
+
+    out
+}
+
+/// Metrics in the given format
+///
+/// Returns an error for formats not supported by this build.
+pub fn render(format: MetricsFormat) -> Result<String, String> {
+    match format {
+        MetricsFormat::Json => Ok(to_json().to_string()),
+        #[cfg(feature = "prometheus")]
+        MetricsFormat::Prometheus => Ok(to_prometheus()),
+        #[cfg(not(feature = "prometheus"))]
+        MetricsFormat::Prometheus => {
+            Err("Prometheus metrics require the `prometheus` feature".to_string())
+        }
+    }
+}
+
+/// Standard get_metrics implementation
+///
+/// `declare_plugin!` uses this automatically. The result is always UTF-8
+/// text, regardless of the selected wire encoding.
+///
+/// # Safety
+///
+/// `result_buf` and `result_len` must be valid for writes.
+pub unsafe extern "C" fn standard_get_metrics(
+    format: u32,
+    result_buf: *mut *mut u8,
+    result_len: *mut usize,
+) -> i32 {
+    crate::utils::clear_last_error();
+    let result = MetricsFormat::from_u32(format)
+        .ok_or_else(|| format!("Unknown metrics format: {}", format))
+        .and_then(render);
+
+    match result {
+        Ok(text) => crate::utils::return_encoded(text.into_bytes(), result_buf, result_len),
+        Err(e) => crate::utils::return_error_status(
+            PluginStatus::Unsupported,
+            &e,
+            result_buf,
+            result_len,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool::ToolOutput;
+
+    // Metrics are process-wide; each test records its own tools.
+    fn record_calls(tool: &str) {
+        let ok = ToolOutcome::Done(Ok(ToolOutput::Value(Value::Null, Vec::new())));
+        record(tool, &ok, Duration::from_millis(3));
+        record(tool, &ToolOutcome::Done(Err("boom".into())), Duration::from_millis(30));
+        record(tool, &ToolOutcome::Panicked("boom".into()), Duration::from_secs(20));
+        record(tool, &ToolOutcome::TimedOut(Duration::ZERO), Duration::ZERO);
+        record(tool, &ToolOutcome::Forbidden("no".into()), Duration::ZERO);
+        record_cache(tool, true);
+    }
+
+    #[test]
+    fn json_counts_calls_by_outcome() {
+        record_calls("metrics_json");
+
+        let json = to_json();
+        let tool = &json["tools"]["metrics_json"];
+        assert_eq!(tool["calls"], 5);
+        assert_eq!(tool["errors"], 4);
+        for kind in ["handlerErrors", "panics", "timeouts", "denied", "cacheHits"] {
+            assert_eq!(tool[kind], 1, "{}", kind);
+        }
+        assert_eq!(tool["cacheMisses"], 0);
+
+        let latency = &tool["latency"];
+        assert_eq!(latency["count"], 5);
+        assert_eq!(latency["buckets"][0], serde_json::json!({ "le": 0.001, "count": 2 }));
+        assert_eq!(latency["buckets"][1]["count"], 3);
+        assert_eq!(latency["buckets"][LATENCY_BUCKETS.len() - 1]["count"], 4);
+        assert!((latency["sumSeconds"].as_f64().unwrap() - 20.033).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unknown_or_unsupported_formats_are_rejected() {
+        assert_eq!(MetricsFormat::from_u32(7), None);
+        assert_eq!(
+            render(MetricsFormat::Prometheus).is_ok(),
+            MetricsFormat::Prometheus.is_supported()
+        );
+
+        let mut buf = std::ptr::null_mut();
+        let mut len = 0;
+        let status = unsafe { standard_get_metrics(7, &mut buf, &mut len) };
+        assert_eq!(status, PluginStatus::Unsupported.code());
+        unsafe { crate::utils::standard_free_string(buf, len) };
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn prometheus_lists_counters_and_histogram() {
+        record_calls("metrics_prom\"quoted\"");
+
+        let text = to_prometheus();
+        let tool = r#"tool="metrics_prom\"quoted\"""#;
+        for line in [
+            format!("mcp_tool_calls_total{{{}}} 5", tool),
+            format!("mcp_tool_errors_total{{{},kind=\"error\"}} 1", tool),
+            format!("mcp_tool_errors_total{{{},kind=\"panic\"}} 1", tool),
+            format!("mcp_tool_errors_total{{{},kind=\"timeout\"}} 1", tool),
+            format!("mcp_tool_errors_total{{{},kind=\"denied\"}} 1", tool),
+            format!("mcp_tool_cache_hits_total{{{}}} 1", tool),
+            format!("mcp_tool_cache_misses_total{{{}}} 0", tool),
+            format!("mcp_tool_duration_seconds_bucket{{{},le=\"0.005\"}} 3", tool),
+            format!("mcp_tool_duration_seconds_bucket{{{},le=\"+Inf\"}} 5", tool),
+            format!("mcp_tool_duration_seconds_count{{{}}} 5", tool),
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {}", line);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Middleware around tool handlers
+//!
+//! Cross-cutting concerns such as logging, timing, extra checks or error
+//! mapping are written once as a [`Middleware`] instead of in every handler.
+//! Middleware is registered globally for all tools of a `declare_tools!`
+//! block and per tool with [`ToolBuilder::middleware`](crate::ToolBuilder::middleware):
+//!
+//! ```ignore
+//! declare_tools! {
+//!     middleware: [LogCalls, MapDbErrors],
+//!     tools: [
+//!         Tool::builder("get_price", "Get the price of a product")
+//!             .param_i64("product_id", "The product ID", true)
+//!             .middleware(RedactArgs(&["api_key"]))
+//!             .handler(handle_get_price),
+//!     ]
+//! }
+//! ```
+//!
+//! Global middleware runs outside per-tool middleware; within each list the
+//! first entry is outermost. The chain runs on the thread executing the
+//! handler, after the [required scopes](crate::ToolBuilder::require_scopes)
+//! were checked, and is covered by the tool's deadline and panic handling.
+
+use crate::context::ToolContext;
+use crate::tool::{Tool, ToolArgs, ToolOutput};
+use std::sync::Arc;
+
+/// A tool call as seen by middleware
+pub struct ToolCall<'a> {
+    /// The tool being called
+    pub tool: &'a Tool,
+    /// The call context
+    pub ctx: &'a ToolContext,
+    /// The call arguments
+    pub args: &'a ToolArgs<'a>,
+}
+
+/// Result of a tool handler as seen by middleware
+pub type ToolResult = Result<ToolOutput, String>;
+
+/// Hooks run around tool handlers
+///
+/// Implement [`before`](Self::before) and [`after`](Self::after) for simple
+/// interceptors, or [`around`](Self::around) for full control, e.g. to skip
+/// the handler or measure its run time. The default `around` calls `before`,
+/// the rest of the chain and then `after`.
+///
+/// # Example
+///
+/// ```ignore
+/// struct LogCalls;
+///
+/// impl Middleware for LogCalls {
+///     fn around(&self, call: &ToolCall<'_>, next: Next<'_>) -> ToolResult {
+///         let started = Instant::now();
+///         let result = next.run(call);
+///         eprintln!("{} took {:?} (ok: {})", call.tool.name, started.elapsed(), result.is_ok());
+///         result
+///     }
+/// }
+///
+/// struct MapDbErrors;
+///
+/// impl Middleware for MapDbErrors {
+///     fn after(&self, _call: &ToolCall<'_>, result: &mut ToolResult) {
+///         if let Err(e) = result {
+///             if e.contains("connection refused") {
+///                 *e = "Database is unavailable, try again later".to_string();
+///             }
+///         }
+///     }
+/// }
+/// ```
+pub trait Middleware: Send + Sync {
+    /// Called before the handler
+    ///
+    /// Returning an error rejects the call: neither the inner middleware and
+    /// the handler nor this middleware's `after` run.
+    fn before(&self, _call: &ToolCall<'_>) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called with the handler's result, which may be changed
+    fn after(&self, _call: &ToolCall<'_>, _result: &mut ToolResult) {}
+
+    /// Wrap the rest of the chain
+    fn around(&self, call: &ToolCall<'_>, next: Next<'_>) -> ToolResult {
+        self.before(call)?;
+        let mut result = next.run(call);
+        self.after(call, &mut result);
+        result
+    }
+}
+
+/// The rest of a middleware chain, ending in the handler
+pub struct Next<'a> {
+    global: &'a [Arc<dyn Middleware>],
+    local: &'a [Arc<dyn Middleware>],
+    handler: &'a mut dyn FnMut(&ToolCall<'_>) -> ToolResult,
+}
+
+impl<'a> Next<'a> {
+    /// Run a handler behind global and per-tool middleware
+    pub fn new(
+        global: &'a [Arc<dyn Middleware>],
+        local: &'a [Arc<dyn Middleware>],
+        handler: &'a mut dyn FnMut(&ToolCall<'_>) -> ToolResult,
+    ) -> Self {
+        Next {
+            global,
+            local,
+            handler,
+        }
+    }
+
+    /// Run the next middleware, or the handler at the end of the chain
+    pub fn run(self, call: &ToolCall<'_>) -> ToolResult {
+        if let Some((first, rest)) = self.global.split_first() {
+            return first.around(call, Next { global: rest, ..self });
+        }
+        if let Some((first, rest)) = self.local.split_first() {
+            return first.around(call, Next { local: rest, ..self });
+        }
+        (self.handler)(call)
+    }
+}
@@ -0,0 +1,268 @@
+//! Type-safe prompt definitions
+//!
+//! This module provides a high-level API for shipping curated MCP prompt
+//! templates together with a plugin's tools.
+
+use crate::completion::CompletionProvider;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// An argument definition for a prompt
+///
+/// MCP prompt arguments are always strings.
+#[derive(Debug, Clone)]
+pub struct PromptArgument {
+    pub name: String,
+    pub description: String,
+    pub required: bool,
+    /// Optional provider for argument completion
+    pub completion: Option<CompletionProvider>,
+}
+
+/// Prompt handler function type
+///
+/// A prompt handler receives the argument values supplied by the client and
+/// returns the MCP `prompts/get` result (see [`crate::utils::prompt_result`])
+/// or an error message. Required arguments are checked before the handler
+/// is called.
+pub type PromptHandler = fn(&HashMap<String, String>) -> Result<Value, String>;
+
+/// A prompt definition
+///
+/// This represents a single prompt with its metadata and handler function.
+pub struct Prompt {
+    pub name: String,
+    pub description: String,
+    pub arguments: Vec<PromptArgument>,
+    pub handler: PromptHandler,
+}
+
+impl Prompt {
+    /// Create a new prompt definition with a builder
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// Prompt::builder("summarize_order", "Summarize an order for the customer")
+    ///     .argument("order_id", "The order to summarize", true)
+    ///     .argument("tone", "Tone of the summary", false)
+    ///     .handler(handle_summarize_order)
+    /// ```
+    pub fn builder(name: &str, description: &str) -> PromptBuilder {
+        PromptBuilder {
+            name: name.to_string(),
+            description: description.to_string(),
+            arguments: Vec::new(),
+        }
+    }
+
+    /// Convert prompt definition to MCP format
+    ///
+    /// Returns a JSON object compatible with MCP protocol:
+    /// ```json
+    /// {
+    ///   "name": "prompt_name",
+    ///   "description": "Prompt description",
+    ///   "arguments": [
+    ///     { "name": "order_id", "description": "...", "required": true }
+    ///   ]
+    /// }
+    /// ```
+    pub fn to_json(&self) -> Value {
+        let arguments: Vec<Value> = self
+            .arguments
+            .iter()
+            .map(|arg| {
+                json!({
+                    "name": arg.name,
+                    "description": arg.description,
+                    "required": arg.required
+                })
+            })
+            .collect();
+
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "arguments": arguments
+        })
+    }
+
+    /// Get the completion provider attached to an argument, if any
+    pub fn completion_provider(&self, argument: &str) -> Option<CompletionProvider> {
+        self.arguments
+            .iter()
+            .find(|a| a.name == argument)
+            .and_then(|a| a.completion)
+    }
+
+    /// Validate the arguments and call the handler
+    ///
+    /// `args` must be a JSON object with string values (or null, which is
+    /// treated as no arguments). Missing required arguments are reported as
+    /// errors without calling the handler.
+    pub fn get(&self, args: &Value) -> Result<Value, String> {
+        let mut values = HashMap::new();
+
+        match args {
+            Value::Null => {}
+            Value::Object(map) => {
+                for (name, value) in map {
+                    let value = value
+                        .as_str()
+                        .ok_or_else(|| format!("Argument '{}' must be a string", name))?;
+                    values.insert(name.clone(), value.to_string());
+                }
+            }
+            _ => return Err("Prompt arguments must be a JSON object".to_string()),
+        }
+
+        for arg in &self.arguments {
+            if arg.required && !values.contains_key(&arg.name) {
+                return Err(format!("Missing required argument: {}", arg.name));
+            }
+        }
+
+        (self.handler)(&values)
+    }
+}
+
+/// Builder for creating prompts with a fluent API
+pub struct PromptBuilder {
+    name: String,
+    description: String,
+    arguments: Vec<PromptArgument>,
+}
+
+impl PromptBuilder {
+    /// Add an argument
+    ///
+    /// # Arguments
+    /// * `name` - Argument name
+    /// * `description` - Argument description
+    /// * `required` - Whether the argument is required
+    pub fn argument(mut self, name: &str, description: &str, required: bool) -> Self {
+        self.arguments.push(PromptArgument {
+            name: name.to_string(),
+            description: description.to_string(),
+            required,
+            completion: None,
+        });
+        self
+    }
+
+    /// Attach a completion provider to an argument
+    ///
+    /// # Panics
+    ///
+    /// Panics if no argument named `argument` has been added yet;
+    /// `declare_prompts!` reports the panic to the host as an error.
+    pub fn complete(mut self, argument: &str, provider: CompletionProvider) -> Self {
+        let prompt = &self.name;
+        let target = self
+            .arguments
+            .iter_mut()
+            .find(|a| a.name == argument)
+            .unwrap_or_else(|| panic!("Prompt '{}' has no argument '{}'", prompt, argument));
+        target.completion = Some(provider);
+        self
+    }
+
+    /// Set the handler function and finalize the prompt
+    ///
+    /// This consumes the builder and returns the completed Prompt.
+    pub fn handler(self, handler: PromptHandler) -> Prompt {
+        Prompt {
+            name: self.name,
+            description: self.description,
+            arguments: self.arguments,
+            handler,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::Completion;
+
+    /// Echo the arguments back, so tests can see what the handler received
+    fn handle_echo(args: &HashMap<String, String>) -> Result<Value, String> {
+        Ok(json!(args))
+    }
+
+    fn complete_tone(partial: &str) -> Completion {
+        Completion::from_candidates(partial, ["formal", "friendly"])
+    }
+
+    fn summarize() -> Prompt {
+        Prompt::builder("summarize_order", "Summarize an order")
+            .argument("order_id", "The order to summarize", true)
+            .argument("tone", "Tone of the summary", false)
+            .complete("tone", complete_tone)
+            .handler(handle_echo)
+    }
+
+    #[test]
+    fn arguments_are_listed_in_order() {
+        assert_eq!(
+            summarize().to_json(),
+            json!({
+                "name": "summarize_order",
+                "description": "Summarize an order",
+                "arguments": [
+                    { "name": "order_id", "description": "The order to summarize", "required": true },
+                    { "name": "tone", "description": "Tone of the summary", "required": false }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn handler_receives_the_arguments() {
+        let prompt = summarize();
+        assert_eq!(prompt.get(&json!({ "order_id": "42" })), Ok(json!({ "order_id": "42" })));
+        assert_eq!(
+            prompt.get(&json!({ "order_id": "42", "tone": "formal", "extra": "kept" })),
+            Ok(json!({ "order_id": "42", "tone": "formal", "extra": "kept" }))
+        );
+    }
+
+    #[test]
+    fn required_arguments_are_checked_before_the_handler() {
+        let prompt = summarize();
+        let missing = Err("Missing required argument: order_id".to_string());
+        assert_eq!(prompt.get(&json!({ "tone": "formal" })), missing);
+        assert_eq!(prompt.get(&Value::Null), missing);
+
+        let optional_only = Prompt::builder("greet", "Greet").argument("name", "Name", false).handler(handle_echo);
+        assert_eq!(optional_only.get(&Value::Null), Ok(json!({})));
+    }
+
+    #[test]
+    fn arguments_must_be_an_object_of_strings() {
+        let prompt = summarize();
+        assert_eq!(
+            prompt.get(&json!({ "order_id": 42 })),
+            Err("Argument 'order_id' must be a string".to_string())
+        );
+        assert_eq!(
+            prompt.get(&json!(["42"])),
+            Err("Prompt arguments must be a JSON object".to_string())
+        );
+    }
+
+    #[test]
+    fn completion_providers_belong_to_their_argument() {
+        let prompt = summarize();
+        assert!(prompt.completion_provider("tone").is_some());
+        assert!(prompt.completion_provider("order_id").is_none());
+        assert!(prompt.completion_provider("unknown").is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Prompt 'greet' has no argument 'tone'")]
+    fn completing_an_undeclared_argument_panics() {
+        let _ = Prompt::builder("greet", "Greet").complete("tone", complete_tone);
+    }
+}
@@ -0,0 +1,444 @@
+//! Runtime tool registry
+//!
+//! `declare_tools!` stores its tools in a [`ToolRegistry`]. The initial set
+//! comes from the macro, but tools can be added and removed at any time after
+//! initialization, e.g. one tool per configured database. Each change should
+//! be followed by [`host::notify_tools_list_changed`](crate::host::notify_tools_list_changed)
+//! so that the host can emit `notifications/tools/list_changed`; the
+//! `register_tool` / `unregister_tool` functions generated by
+//! `declare_tools!` do this automatically.
+//!
+//! Tools are listed in declaration order (registered tools last). The
+//! serialized list is computed once and cached until the set changes,
+//! together with an [etag](ToolRegistry::etag) hosts can compare to detect
+//! changes without re-reading the list.
+//!
+//! ## Namespaces
+//!
+//! When a host loads several plugins, two of them may both offer a `search`
+//! tool. A registry [with a namespace](ToolRegistry::with_namespace) lists
+//! its tools as `<namespace>__<name>` (e.g. `orders__search`) and
+//! [resolves](ToolRegistry::resolve) these names back when they are called.
+//! Tools are still declared and registered under their short names.
+//!
+//! ## Tool Names
+//!
+//! Listed names must follow the MCP rules: 1 to 128 characters out of ASCII
+//! letters, digits, `_`, `-` and `.`. See [`validate_tool_name`].
+//! `declare_tools!` builds its registry lazily inside the generated ABI
+//! functions; if a declared name is invalid, it uses an
+//! [invalid registry](ToolRegistry::invalid) and these functions report the
+//! problem to the host instead of panicking.
+
+use crate::encoding::{self, WireEncoding};
+use crate::middleware::Middleware;
+use crate::tool::Tool;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Separator between namespace and tool name
+pub const NAMESPACE_SEPARATOR: &str = "__";
+
+/// Maximum length of a tool name, including the namespace
+pub const MAX_TOOL_NAME_LEN: usize = 128;
+
+/// Check a tool name against the MCP naming rules
+///
+/// # Example
+///
+/// ```
+/// use mcp_plugin_api::registry::validate_tool_name;
+///
+/// assert!(validate_tool_name("orders__search").is_ok());
+/// assert!(validate_tool_name("get-price.v2").is_ok());
+/// assert!(validate_tool_name("").is_err());
+/// assert!(validate_tool_name("find orders").is_err());
+/// ```
+pub fn validate_tool_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Tool name is empty".to_string());
+    }
+    if name.len() > MAX_TOOL_NAME_LEN {
+        return Err(format!(
+            "Tool name '{}' is longer than {} characters",
+            name, MAX_TOOL_NAME_LEN
+        ));
+    }
+    match name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+    {
+        Some(c) => Err(format!("Tool name '{}' contains invalid character {:?}", name, c)),
+        None => Ok(()),
+    }
+}
+
+/// A thread-safe, mutable set of tools
+///
+/// Lookups return an `Arc<Tool>`, so a tool that is unregistered while a
+/// call is running stays alive until the call finishes.
+#[derive(Default)]
+pub struct ToolRegistry {
+    inner: RwLock<Inner>,
+    namespace: Option<String>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    // Why the declared tools could not be registered
+    error: Option<String>,
+}
+
+#[derive(Default)]
+struct Inner {
+    tools: HashMap<String, Arc<Tool>>,
+    order: Vec<String>,
+    cache: Option<ListCache>,
+}
+
+/// Serialized tool list, valid until the tool set changes
+struct ListCache {
+    encoding: WireEncoding,
+    bytes: Arc<[u8]>,
+    etag: u64,
+}
+
+impl ToolRegistry {
+    /// Create a registry with an initial set of tools
+    ///
+    /// Tools are listed in the given order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a tool name violates the [naming rules](validate_tool_name)
+    /// or is given twice. Use [`try_new`](Self::try_new) to handle that as an
+    /// error.
+    pub fn new(tools: Vec<Tool>) -> Self {
+        Self::try_new(tools).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Create a registry with an initial set of tools
+    ///
+    /// Like [`new`](Self::new), but returns an error if a tool name violates
+    /// the [naming rules](validate_tool_name) or is given twice.
+    pub fn try_new(tools: Vec<Tool>) -> Result<Self, String> {
+        let mut inner = Inner::default();
+        for tool in tools {
+            validate_tool_name(&tool.name)?;
+            if inner.tools.contains_key(&tool.name) {
+                return Err(format!("Duplicate tool name: {}", tool.name));
+            }
+            inner.order.push(tool.name.clone());
+            inner.tools.insert(tool.name.clone(), Arc::new(tool));
+        }
+
+        Ok(ToolRegistry {
+            inner: RwLock::new(inner),
+            ..ToolRegistry::default()
+        })
+    }
+
+    /// A registry without tools standing in for ones that failed to build
+    ///
+    /// Its [`error`](Self::error) is reported by the functions generated by
+    /// `declare_tools!`, and [`register`](Self::register) fails with it.
+    pub fn invalid(error: impl Into<String>) -> Self {
+        ToolRegistry {
+            error: Some(error.into()),
+            ..ToolRegistry::default()
+        }
+    }
+
+    /// Why the declared tools could not be registered, for an
+    /// [invalid](Self::invalid) registry
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// List the tools under a namespace
+    ///
+    /// # Panics
+    ///
+    /// Panics if a namespaced tool name violates the
+    /// [naming rules](validate_tool_name). Use
+    /// [`try_with_namespace`](Self::try_with_namespace) to handle that as an
+    /// error.
+    pub fn with_namespace(self, namespace: &str) -> Self {
+        self.try_with_namespace(namespace)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// List the tools under a namespace
+    ///
+    /// Like [`with_namespace`](Self::with_namespace), but returns an error if
+    /// a namespaced tool name violates the [naming rules](validate_tool_name).
+    pub fn try_with_namespace(mut self, namespace: &str) -> Result<Self, String> {
+        self.namespace = Some(namespace.to_string());
+        for name in &self.read().order {
+            validate_tool_name(&self.qualified_name(name))?;
+        }
+        self.write().cache = None;
+        Ok(self)
+    }
+
+    /// Run middleware around the handlers of all tools
+    ///
+    /// It runs outside the tools' own middleware; see [`crate::middleware`].
+    pub fn with_middleware(mut self, middleware: Vec<Arc<dyn Middleware>>) -> Self {
+        self.middleware.extend(middleware);
+        self
+    }
+
+    /// Middleware run around all tools
+    pub fn middleware(&self) -> &[Arc<dyn Middleware>] {
+        &self.middleware
+    }
+
+    /// The namespace tools are listed under, if any
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// The name a tool is listed under
+    pub fn qualified_name<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match &self.namespace {
+            Some(namespace) => Cow::Owned(format!("{}{}{}", namespace, NAMESPACE_SEPARATOR, name)),
+            None => Cow::Borrowed(name),
+        }
+    }
+
+    /// Look up a tool by name
+    pub fn get(&self, name: &str) -> Option<Arc<Tool>> {
+        self.read().tools.get(name).cloned()
+    }
+
+    /// Look up a tool by the name it is listed under
+    ///
+    /// With a namespace, only names carrying its prefix are found.
+    pub fn resolve(&self, qualified_name: &str) -> Option<Arc<Tool>> {
+        let name = match &self.namespace {
+            Some(namespace) => qualified_name
+                .strip_prefix(namespace.as_str())?
+                .strip_prefix(NAMESPACE_SEPARATOR)?,
+            None => qualified_name,
+        };
+        self.get(name)
+    }
+
+    /// Add a tool
+    ///
+    /// Returns an error if a tool with the same name is already registered,
+    /// or if its name violates the [naming rules](validate_tool_name).
+    pub fn register(&self, tool: Tool) -> Result<(), String> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        validate_tool_name(&self.qualified_name(&tool.name))?;
+
+        let mut inner = self.write();
+        if inner.tools.contains_key(&tool.name) {
+            return Err(format!("Tool already registered: {}", tool.name));
+        }
+
+        inner.order.push(tool.name.clone());
+        inner.tools.insert(tool.name.clone(), Arc::new(tool));
+        inner.cache = None;
+        Ok(())
+    }
+
+    /// Remove a tool
+    ///
+    /// Returns the removed tool, or `None` if no tool had that name.
+    pub fn unregister(&self, name: &str) -> Option<Arc<Tool>> {
+        let mut inner = self.write();
+        let removed = inner.tools.remove(name)?;
+
+        inner.order.retain(|n| n != name);
+        inner.cache = None;
+        Some(removed)
+    }
+
+    /// Names of all registered tools, in declaration order
+    ///
+    /// These are the short names, without namespace.
+    pub fn names(&self) -> Vec<String> {
+        self.read().order.clone()
+    }
+
+    /// Number of registered tools
+    pub fn len(&self) -> usize {
+        self.read().tools.len()
+    }
+
+    /// Whether no tools are registered
+    pub fn is_empty(&self) -> bool {
+        self.read().tools.is_empty()
+    }
+
+    /// JSON array of all tool definitions, in declaration order
+    pub fn list_json(&self) -> Value {
+        self.read().list_json(self)
+    }
+
+    /// The tool list as returned by `list_tools`, in the selected wire encoding
+    ///
+    /// Serialized on first use and cached until the tool set changes.
+    pub fn list_bytes(&self) -> Arc<[u8]> {
+        self.with_cache(|cache| cache.bytes.clone())
+    }
+
+    /// Hash of the tool list
+    ///
+    /// Changes whenever a tool is added, removed or replaced, or another wire
+    /// encoding is selected, and is stable across runs for the same tool
+    /// definitions (64-bit FNV-1a of [`list_bytes`](Self::list_bytes)). Hosts
+    /// can compare it to skip re-reading `list_tools`.
+    pub fn etag(&self) -> u64 {
+        self.with_cache(|cache| cache.etag)
+    }
+
+    fn with_cache<R>(&self, f: impl Fn(&ListCache) -> R) -> R {
+        let encoding = encoding::current();
+        if let Some(cache) = &self.read().cache {
+            if cache.encoding == encoding {
+                return f(cache);
+            }
+        }
+
+        let mut inner = self.write();
+        let bytes = encoding
+            .encode(&inner.list_json(self))
+            .expect("JSON values are always encodable");
+
+        f(inner.cache.insert(ListCache {
+            encoding,
+            etag: fnv1a(&bytes),
+            bytes: bytes.into(),
+        }))
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Inner> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Inner> {
+        self.inner.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Inner {
+    fn list_json(&self, registry: &ToolRegistry) -> Value {
+        Value::Array(
+            self.order
+                .iter()
+                .filter_map(|name| self.tools.get(name))
+                .map(|t| {
+                    let mut schema = t.to_json_schema();
+                    if registry.namespace.is_some() {
+                        schema["name"] = Value::from(registry.qualified_name(&t.name));
+                    }
+                    schema
+                })
+                .collect(),
+        )
+    }
+}
+
+/// 64-bit FNV-1a hash
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle_noop(_args: &Value) -> Result<Value, String> {
+        Ok(Value::Null)
+    }
+
+    fn tool(name: &str) -> Tool {
+        Tool::builder(name, "").handler(handle_noop)
+    }
+
+    #[test]
+    fn invalid_names_are_errors() {
+        assert!(ToolRegistry::try_new(vec![tool("ok"), tool("not ok")]).is_err());
+
+        let long = "n".repeat(MAX_TOOL_NAME_LEN - 2);
+        let registry = ToolRegistry::try_new(vec![tool(&long)]).unwrap();
+        assert!(registry.try_with_namespace("ns").is_err());
+        assert!(ToolRegistry::try_new(vec![tool("a")])
+            .unwrap()
+            .try_with_namespace("bad ns")
+            .is_err());
+    }
+
+    #[test]
+    fn invalid_registry_reports_its_error() {
+        let registry = ToolRegistry::invalid("broken");
+        assert_eq!(registry.error(), Some("broken"));
+        assert_eq!(registry.register(tool("a")), Err("broken".to_string()));
+        assert!(registry.is_empty());
+        assert_eq!(ToolRegistry::new(vec![tool("a")]).error(), None);
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let duplicate = ToolRegistry::try_new(vec![tool("alpha"), tool("beta"), tool("alpha")]);
+        assert_eq!(duplicate.err(), Some("Duplicate tool name: alpha".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate tool name: alpha")]
+    fn new_panics_on_duplicate_names() {
+        let _ = ToolRegistry::new(vec![tool("alpha"), tool("alpha")]);
+    }
+
+    fn listed_names(registry: &ToolRegistry) -> Vec<String> {
+        let list: Value = serde_json::from_slice(&registry.list_bytes()).unwrap();
+        list.as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn tools_are_listed_in_declaration_order() {
+        let registry = ToolRegistry::new(vec![tool("zeta"), tool("alpha"), tool("mid")]);
+        assert_eq!(registry.names(), ["zeta", "alpha", "mid"]);
+        assert_eq!(listed_names(&registry), ["zeta", "alpha", "mid"]);
+
+        registry.register(tool("beta")).unwrap();
+        registry.unregister("alpha");
+        assert_eq!(listed_names(&registry), ["zeta", "mid", "beta"]);
+
+        let registry = registry.with_namespace("ns");
+        assert_eq!(listed_names(&registry), ["ns__zeta", "ns__mid", "ns__beta"]);
+    }
+
+    #[test]
+    fn etag_follows_the_tool_list() {
+        let registry = ToolRegistry::new(vec![tool("a"), tool("b")]);
+        let etag = registry.etag();
+        assert_eq!(registry.etag(), etag);
+        assert_eq!(ToolRegistry::new(vec![tool("a"), tool("b")]).etag(), etag);
+        assert_eq!(etag, fnv1a(&registry.list_bytes()));
+
+        registry.register(tool("c")).unwrap();
+        let added = registry.etag();
+        assert_ne!(added, etag);
+        assert_eq!(registry.etag(), added);
+
+        registry.unregister("c");
+        assert_eq!(registry.etag(), etag);
+
+        assert_ne!(ToolRegistry::new(vec![tool("b"), tool("a")]).etag(), etag);
+        assert_ne!(
+            ToolRegistry::new(vec![Tool::builder("a", "changed").handler(handle_noop), tool("b")]).etag(),
+            etag
+        );
+    }
+}
@@ -0,0 +1,621 @@
+//! Type-safe resource definitions
+//!
+//! This module provides a high-level API for exposing MCP resources
+//! (documents, catalogs, database rows, ...) alongside tools.
+//!
+//! Static resources have a fixed URI. Resource templates describe a family
+//! of URIs with an RFC 6570 level 1 template such as `product://{id}/spec`;
+//! the [`ResourceRouter`] matches incoming URIs against them and passes the
+//! extracted variables to the handler.
+
+use crate::completion::CompletionProvider;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Resource handler function type
+///
+/// A resource handler receives the requested URI and returns the MCP
+/// `resources/read` result (see [`crate::utils::resource_text_contents`])
+/// or an error message.
+pub type ResourceHandler = fn(&str) -> Result<Value, String>;
+
+/// A resource definition
+///
+/// This represents a single resource with its metadata and read handler.
+pub struct Resource {
+    pub uri: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+    pub handler: ResourceHandler,
+}
+
+impl Resource {
+    /// Create a new resource definition with a builder
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// Resource::builder("docs://readme", "README")
+    ///     .description("Project README")
+    ///     .mime_type("text/markdown")
+    ///     .handler(read_readme)
+    /// ```
+    pub fn builder(uri: &str, name: &str) -> ResourceBuilder {
+        ResourceBuilder {
+            uri: uri.to_string(),
+            name: name.to_string(),
+            description: None,
+            mime_type: None,
+        }
+    }
+
+    /// Convert resource definition to MCP format
+    ///
+    /// Returns a JSON object compatible with MCP protocol:
+    /// ```json
+    /// {
+    ///   "uri": "docs://readme",
+    ///   "name": "README",
+    ///   "description": "Project README",
+    ///   "mimeType": "text/markdown"
+    /// }
+    /// ```
+    pub fn to_json(&self) -> Value {
+        let mut resource = json!({
+            "uri": self.uri,
+            "name": self.name
+        });
+
+        if let Some(description) = &self.description {
+            resource["description"] = json!(description);
+        }
+        if let Some(mime_type) = &self.mime_type {
+            resource["mimeType"] = json!(mime_type);
+        }
+
+        resource
+    }
+}
+
+/// Builder for creating resources with a fluent API
+pub struct ResourceBuilder {
+    uri: String,
+    name: String,
+    description: Option<String>,
+    mime_type: Option<String>,
+}
+
+impl ResourceBuilder {
+    /// Set the resource description
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Set the MIME type of the resource contents
+    pub fn mime_type(mut self, mime_type: &str) -> Self {
+        self.mime_type = Some(mime_type.to_string());
+        self
+    }
+
+    /// Set the read handler and finalize the resource
+    ///
+    /// This consumes the builder and returns the completed Resource.
+    pub fn handler(self, handler: ResourceHandler) -> Resource {
+        Resource {
+            uri: self.uri,
+            name: self.name,
+            description: self.description,
+            mime_type: self.mime_type,
+            handler,
+        }
+    }
+}
+
+/// Resource template handler function type
+///
+/// Receives the requested URI and the variables extracted from it by the
+/// template, and returns the MCP `resources/read` result or an error message.
+pub type ResourceTemplateHandler = fn(&str, &HashMap<String, String>) -> Result<Value, String>;
+
+/// A segment of a parsed URI template
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Literal(String),
+    Variable(String),
+}
+
+/// An RFC 6570 level 1 URI template
+///
+/// Only simple string expansion (`{var}`) is supported. When matching, a
+/// variable captures one or more characters up to the next `/`, `?` or `#`,
+/// and the captured value is percent-decoded.
+///
+/// # Example
+///
+/// ```
+/// use mcp_plugin_api::UriTemplate;
+///
+/// let template = UriTemplate::parse("product://{id}/spec").unwrap();
+///
+/// let vars = template.match_uri("product://42/spec").unwrap();
+/// assert_eq!(vars["id"], "42");
+///
+/// let vars = template.match_uri("product://a%20b/spec").unwrap();
+/// assert_eq!(vars["id"], "a b");
+///
+/// assert!(template.match_uri("product://4/2/spec").is_none());
+/// assert!(template.match_uri("product:///spec").is_none());
+/// assert!(UriTemplate::parse("product://{+path}").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UriTemplate {
+    template: String,
+    parts: Vec<TemplatePart>,
+}
+
+impl UriTemplate {
+    /// Parse a URI template
+    ///
+    /// Returns an error for unbalanced braces, empty or invalid variable
+    /// names, operators from higher template levels, and two variables
+    /// without a literal between them (which cannot be matched unambiguously).
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while !rest.is_empty() {
+            match rest.find(['{', '}']) {
+                Some(pos) if rest.as_bytes()[pos] == b'}' => {
+                    return Err(format!("Unbalanced '}}' in URI template: {}", template));
+                }
+                Some(pos) => {
+                    if pos > 0 {
+                        parts.push(TemplatePart::Literal(rest[..pos].to_string()));
+                    }
+                    let end = rest[pos..]
+                        .find('}')
+                        .ok_or_else(|| format!("Unclosed '{{' in URI template: {}", template))?;
+                    let name = &rest[pos + 1..pos + end];
+
+                    let valid = !name.is_empty()
+                        && name
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+                    if !valid {
+                        return Err(format!(
+                            "Invalid variable '{}' in URI template: {} (only level 1 templates are supported)",
+                            name, template
+                        ));
+                    }
+                    if let Some(TemplatePart::Variable(_)) = parts.last() {
+                        return Err(format!(
+                            "Adjacent variables in URI template: {}",
+                            template
+                        ));
+                    }
+
+                    parts.push(TemplatePart::Variable(name.to_string()));
+                    rest = &rest[pos + end + 1..];
+                }
+                None => {
+                    parts.push(TemplatePart::Literal(rest.to_string()));
+                    rest = "";
+                }
+            }
+        }
+
+        Ok(UriTemplate {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    /// The template string as declared
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Names of the template variables, in order of appearance
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            TemplatePart::Variable(name) => Some(name.as_str()),
+            TemplatePart::Literal(_) => None,
+        })
+    }
+
+    /// Match a URI against the template
+    ///
+    /// Returns the extracted (percent-decoded) variables, or `None` if the
+    /// URI does not match. A value never spans a `/`, `?` or `#`. When the
+    /// literal following a variable occurs several times, each occurrence is
+    /// tried, so `{id}.json` matches `a.b.json` with `id` = `a.b`.
+    pub fn match_uri(&self, uri: &str) -> Option<HashMap<String, String>> {
+        let mut vars = HashMap::new();
+        self.match_parts(0, uri, &mut vars).then_some(vars)
+    }
+
+    fn match_parts(&self, index: usize, rest: &str, vars: &mut HashMap<String, String>) -> bool {
+        match self.parts.get(index) {
+            None => rest.is_empty(),
+            Some(TemplatePart::Literal(literal)) => rest
+                .strip_prefix(literal.as_str())
+                .is_some_and(|rest| self.match_parts(index + 1, rest, vars)),
+            Some(TemplatePart::Variable(name)) => {
+                let limit = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+
+                // Parsing guarantees variables are separated by literals, so
+                // a value ends where the next literal starts, or at the limit
+                // for the last part
+                let next = match self.parts.get(index + 1) {
+                    Some(TemplatePart::Literal(next)) => Some(next.as_str()),
+                    _ => None,
+                };
+                let ends = (1..=limit)
+                    .filter(|&end| rest.is_char_boundary(end))
+                    .filter(|&end| match next {
+                        Some(next) => rest[end..].starts_with(next),
+                        None => end == limit,
+                    });
+
+                for end in ends {
+                    let Some(value) = percent_decode(&rest[..end]) else {
+                        continue;
+                    };
+                    if self.match_parts(index + 1, &rest[end..], vars) {
+                        vars.insert(name.clone(), value);
+                        return true;
+                    }
+                }
+                false
+            }
+        }
+    }
+}
+
+/// Decode `%XX` escapes, rejecting malformed escapes and invalid UTF-8
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3)?;
+            if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// A resource template definition
+///
+/// This represents a family of resources addressed by a URI template,
+/// with its metadata and read handler.
+pub struct ResourceTemplate {
+    pub uri_template: UriTemplate,
+    pub name: String,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+    /// Completion providers by variable name
+    pub completions: HashMap<String, CompletionProvider>,
+    pub handler: ResourceTemplateHandler,
+}
+
+impl ResourceTemplate {
+    /// Create a new resource template definition with a builder
+    ///
+    /// # Panics
+    ///
+    /// Panics if `uri_template` is not a valid level 1 URI template. Templates
+    /// are static declarations, so this surfaces mistakes on first use;
+    /// `declare_resources!` reports the panic to the host as an error.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// ResourceTemplate::builder("product://{id}/spec", "Product specification")
+    ///     .mime_type("application/json")
+    ///     .handler(read_product_spec)
+    /// ```
+    pub fn builder(uri_template: &str, name: &str) -> ResourceTemplateBuilder {
+        let uri_template = UriTemplate::parse(uri_template).unwrap_or_else(|e| panic!("{}", e));
+
+        ResourceTemplateBuilder {
+            uri_template,
+            name: name.to_string(),
+            description: None,
+            mime_type: None,
+            completions: HashMap::new(),
+        }
+    }
+
+    /// Get the completion provider attached to a variable, if any
+    pub fn completion_provider(&self, variable: &str) -> Option<CompletionProvider> {
+        self.completions.get(variable).copied()
+    }
+
+    /// Convert resource template definition to MCP format
+    ///
+    /// Returns a JSON object compatible with MCP protocol:
+    /// ```json
+    /// {
+    ///   "uriTemplate": "product://{id}/spec",
+    ///   "name": "Product specification",
+    ///   "description": "...",
+    ///   "mimeType": "application/json"
+    /// }
+    /// ```
+    pub fn to_json(&self) -> Value {
+        let mut template = json!({
+            "uriTemplate": self.uri_template.as_str(),
+            "name": self.name
+        });
+
+        if let Some(description) = &self.description {
+            template["description"] = json!(description);
+        }
+        if let Some(mime_type) = &self.mime_type {
+            template["mimeType"] = json!(mime_type);
+        }
+
+        template
+    }
+}
+
+/// Builder for creating resource templates with a fluent API
+pub struct ResourceTemplateBuilder {
+    uri_template: UriTemplate,
+    name: String,
+    description: Option<String>,
+    mime_type: Option<String>,
+    completions: HashMap<String, CompletionProvider>,
+}
+
+impl ResourceTemplateBuilder {
+    /// Set the resource template description
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Set the MIME type of the resource contents
+    pub fn mime_type(mut self, mime_type: &str) -> Self {
+        self.mime_type = Some(mime_type.to_string());
+        self
+    }
+
+    /// Attach a completion provider to a template variable
+    ///
+    /// # Panics
+    ///
+    /// Panics if the template has no variable named `variable`.
+    pub fn complete(mut self, variable: &str, provider: CompletionProvider) -> Self {
+        if !self.uri_template.variables().any(|v| v == variable) {
+            panic!(
+                "URI template '{}' has no variable '{}'",
+                self.uri_template.as_str(),
+                variable
+            );
+        }
+        self.completions.insert(variable.to_string(), provider);
+        self
+    }
+
+    /// Set the read handler and finalize the resource template
+    ///
+    /// This consumes the builder and returns the completed ResourceTemplate.
+    pub fn handler(self, handler: ResourceTemplateHandler) -> ResourceTemplate {
+        ResourceTemplate {
+            uri_template: self.uri_template,
+            name: self.name,
+            description: self.description,
+            mime_type: self.mime_type,
+            completions: self.completions,
+            handler,
+        }
+    }
+}
+
+/// Dispatches resource reads to static resources and templates
+///
+/// Static resources are matched exactly first; templates are then tried in
+/// declaration order and the first match wins. Used by `declare_resources!`.
+pub struct ResourceRouter {
+    resources: Vec<Resource>,
+    templates: Vec<ResourceTemplate>,
+    // Why the declared resources could not be built
+    error: Option<String>,
+}
+
+impl ResourceRouter {
+    /// Create a router from static resources and templates
+    pub fn new(resources: Vec<Resource>, templates: Vec<ResourceTemplate>) -> Self {
+        ResourceRouter {
+            resources,
+            templates,
+            error: None,
+        }
+    }
+
+    /// A router without resources standing in for ones that failed to build
+    ///
+    /// Its [`error`](Self::error) is reported by the functions generated by
+    /// `declare_resources!`.
+    pub fn invalid(error: impl Into<String>) -> Self {
+        ResourceRouter {
+            resources: Vec::new(),
+            templates: Vec::new(),
+            error: Some(error.into()),
+        }
+    }
+
+    /// Why the declared resources could not be built, for an
+    /// [invalid](Self::invalid) router
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Static resources, in declaration order
+    pub fn resources(&self) -> &[Resource] {
+        &self.resources
+    }
+
+    /// Resource templates, in declaration order
+    pub fn templates(&self) -> &[ResourceTemplate] {
+        &self.templates
+    }
+
+    /// JSON array of all static resource definitions
+    pub fn list_resources_json(&self) -> Value {
+        Value::Array(self.resources.iter().map(|r| r.to_json()).collect())
+    }
+
+    /// JSON array of all resource template definitions
+    pub fn list_templates_json(&self) -> Value {
+        Value::Array(self.templates.iter().map(|t| t.to_json()).collect())
+    }
+
+    /// Whether a static resource or a template matches the URI
+    pub fn contains(&self, uri: &str) -> bool {
+        self.resources.iter().any(|r| r.uri == uri)
+            || self
+                .templates
+                .iter()
+                .any(|t| t.uri_template.match_uri(uri).is_some())
+    }
+
+    /// Read a resource by URI
+    ///
+    /// Returns an `Unknown resource` error if neither a static resource nor
+    /// a template matches.
+    pub fn read(&self, uri: &str) -> Result<Value, String> {
+        if let Some(resource) = self.resources.iter().find(|r| r.uri == uri) {
+            return (resource.handler)(uri);
+        }
+
+        for template in &self.templates {
+            if let Some(vars) = template.uri_template.match_uri(uri) {
+                return (template.handler)(uri, &vars);
+            }
+        }
+
+        Err(format!("Unknown resource: {}", uri))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_static(uri: &str) -> Result<Value, String> {
+        Ok(json!({ "static": uri }))
+    }
+
+    fn read_doc(_uri: &str, vars: &HashMap<String, String>) -> Result<Value, String> {
+        Ok(json!({ "doc": vars["name"] }))
+    }
+
+    fn read_any(_uri: &str, vars: &HashMap<String, String>) -> Result<Value, String> {
+        Ok(json!({ "any": vars["path"] }))
+    }
+
+    fn router() -> ResourceRouter {
+        ResourceRouter::new(
+            vec![Resource::builder("docs://index", "Index").handler(read_static)],
+            vec![
+                ResourceTemplate::builder("docs://{name}", "Document").handler(read_doc),
+                ResourceTemplate::builder("docs://{path}", "Anything").handler(read_any),
+            ],
+        )
+    }
+
+    #[test]
+    fn exact_uris_win_over_templates() {
+        let router = router();
+        assert!(router.contains("docs://index"));
+        assert_eq!(router.read("docs://index"), Ok(json!({ "static": "docs://index" })));
+    }
+
+    #[test]
+    fn first_matching_template_is_read() {
+        let router = router();
+        assert!(router.contains("docs://guide"));
+        assert_eq!(router.read("docs://guide"), Ok(json!({ "doc": "guide" })));
+    }
+
+    #[test]
+    fn unknown_uris_are_errors() {
+        let router = router();
+        for uri in ["files://index", "docs://", "docs://a/b", "docs://index/more"] {
+            assert!(!router.contains(uri), "{}", uri);
+            assert_eq!(router.read(uri), Err(format!("Unknown resource: {}", uri)));
+        }
+    }
+
+    #[test]
+    fn definitions_are_listed_in_declaration_order() {
+        let router = router();
+        assert_eq!(
+            router.list_resources_json(),
+            json!([{ "uri": "docs://index", "name": "Index" }])
+        );
+        assert_eq!(
+            router.list_templates_json(),
+            json!([
+                { "uriTemplate": "docs://{name}", "name": "Document" },
+                { "uriTemplate": "docs://{path}", "name": "Anything" }
+            ])
+        );
+        assert_eq!(ResourceRouter::invalid("broken").error(), Some("broken"));
+        assert_eq!(router.error(), None);
+    }
+
+    fn vars(template: &str, uri: &str) -> Option<Vec<(String, String)>> {
+        let vars = UriTemplate::parse(template).unwrap().match_uri(uri)?;
+        let mut vars: Vec<_> = vars.into_iter().collect();
+        vars.sort();
+        Some(vars)
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+        Some(pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+    }
+
+    #[test]
+    fn repeated_literals_are_backtracked() {
+        assert_eq!(vars("file://{id}.json", "file://a.b.json"), pairs(&[("id", "a.b")]));
+        assert_eq!(vars("file://{id}.json", "file://a.json.json"), pairs(&[("id", "a.json")]));
+        assert_eq!(
+            vars("file://{name}.{ext}", "file://archive.tar.gz"),
+            pairs(&[("ext", "tar.gz"), ("name", "archive")])
+        );
+        assert_eq!(
+            vars("file://{id}.json/meta", "file://a.b.json/meta"),
+            pairs(&[("id", "a.b")])
+        );
+        assert_eq!(vars("file://{id}.json", "file://a.b.jsonx"), None);
+    }
+
+    #[test]
+    fn values_stop_at_delimiters() {
+        assert_eq!(
+            vars("product://{id}/spec", "product://42/spec"),
+            pairs(&[("id", "42")])
+        );
+        assert_eq!(vars("product://{id}", "product://4/2"), None);
+        assert_eq!(vars("product://{id}.json", "product://a/b.json"), None);
+        assert_eq!(vars("search://{q}?page={page}", "search://rust?page=2"), pairs(&[("page", "2"), ("q", "rust")]));
+        assert_eq!(vars("product://{id}/spec", "product:///spec"), None);
+        assert_eq!(vars("product://{id}", "product://%zz"), None);
+    }
+}
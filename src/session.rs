@@ -0,0 +1,272 @@
+//! Per-session state
+//!
+//! MCP sessions are long-lived, and some tools need to remember things
+//! between calls of the same session (the selected project, a cursor into a
+//! result set). Hosts identify the session with `"sessionId"` in the call
+//! context; handlers get its [`Session`] through
+//! [`ToolContext::session`](crate::ToolContext::session), a typed key-value
+//! store shared by all calls of that session.
+//!
+//! ## Lifecycle
+//!
+//! Plugins using sessions export `on_session_start` and `on_session_end`
+//! (which sets the `SESSIONS` capability); the host calls them when a client
+//! connects and disconnects. Sessions only exist between these calls: calls
+//! with an unknown or already ended `"sessionId"` get no session, so late or
+//! forged ids cannot bring sessions back. The session and all values stored
+//! in it are dropped at the end. Plugins that hold per-session resources
+//! (connections, temp files) can react to both with
+//! [`declare_session_hooks!`](crate::declare_session_hooks).
+//!
+//! Without `on_session_end`, sessions live until the plugin is unloaded.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, RwLock};
+
+/// Call context key identifying the session
+pub const CONTEXT_KEY: &str = "sessionId";
+
+type Values = HashMap<String, Box<dyn Any + Send + Sync>>;
+
+/// State of one MCP session
+///
+/// Values of any `Send + Sync` type are stored under string keys. Reading a
+/// key with a different type than it was stored with returns `None`.
+pub struct Session {
+    id: String,
+    values: Mutex<Values>,
+}
+
+impl Session {
+    fn new(id: &str) -> Self {
+        Session {
+            id: id.to_string(),
+            values: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The session id assigned by the host
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Get a copy of a value
+    pub fn get<T: Clone + 'static>(&self, key: &str) -> Option<T> {
+        self.values().get(key)?.downcast_ref::<T>().cloned()
+    }
+
+    /// Store a value, replacing any previous value of the key
+    pub fn insert<T: Send + Sync + 'static>(&self, key: &str, value: T) {
+        self.values().insert(key.to_string(), Box::new(value));
+    }
+
+    /// Remove a value
+    ///
+    /// Returns the value if it was stored with type `T`.
+    pub fn remove<T: 'static>(&self, key: &str) -> Option<T> {
+        let value = self.values().remove(key)?;
+        value.downcast::<T>().ok().map(|value| *value)
+    }
+
+    /// Whether a value is stored under the key
+    pub fn contains(&self, key: &str) -> bool {
+        self.values().contains_key(key)
+    }
+
+    /// Modify a value in place, starting from `T::default()` if it is missing
+    ///
+    /// Returns an error, leaving the value untouched, if the key holds a
+    /// value of another type.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// fn handle_next_page(ctx: &ToolContext, _args: &Value) -> Result<Value, String> {
+    ///     let session = ctx.session().ok_or("No session")?;
+    ///     let page = session.update("page", |page: &mut u32| {
+    ///         *page += 1;
+    ///         *page
+    ///     })?;
+    ///     Ok(json_content(load_page(page)?))
+    /// }
+    /// ```
+    pub fn update<T, R>(&self, key: &str, f: impl FnOnce(&mut T) -> R) -> Result<R, String>
+    where
+        T: Default + Send + Sync + 'static,
+    {
+        let mut values = self.values();
+        let entry = values
+            .entry(key.to_string())
+            .or_insert_with(|| Box::new(T::default()));
+        match entry.downcast_mut::<T>() {
+            Some(value) => Ok(f(value)),
+            None => Err(format!(
+                "Session value '{}' is not a {}",
+                key,
+                std::any::type_name::<T>()
+            )),
+        }
+    }
+
+    /// Remove all values
+    pub fn clear(&self) {
+        self.values().clear();
+    }
+
+    fn values(&self) -> MutexGuard<'_, Values> {
+        self.values.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl std::fmt::Debug for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let values = self.values();
+        f.debug_struct("Session")
+            .field("id", &self.id)
+            .field("keys", &values.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+static SESSIONS: OnceLock<RwLock<HashMap<String, Arc<Session>>>> = OnceLock::new();
+
+fn sessions() -> &'static RwLock<HashMap<String, Arc<Session>>> {
+    SESSIONS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Get a session, creating it if it does not exist yet
+///
+/// Called by `on_session_start`; tool calls only [`get`] existing sessions.
+pub fn open(id: &str) -> Arc<Session> {
+    if let Some(session) = get(id) {
+        return session;
+    }
+
+    let mut sessions = sessions().write().unwrap_or_else(|e| e.into_inner());
+    sessions
+        .entry(id.to_string())
+        .or_insert_with(|| Arc::new(Session::new(id)))
+        .clone()
+}
+
+/// Look up an existing session
+pub fn get(id: &str) -> Option<Arc<Session>> {
+    let sessions = sessions().read().unwrap_or_else(|e| e.into_inner());
+    sessions.get(id).cloned()
+}
+
+/// Remove a session
+///
+/// Returns the session, whose values are dropped once the last call using
+/// it has finished.
+pub fn close(id: &str) -> Option<Arc<Session>> {
+    let mut sessions = sessions().write().unwrap_or_else(|e| e.into_inner());
+    sessions.remove(id)
+}
+
+/// Ids of all open sessions
+pub fn ids() -> Vec<String> {
+    let sessions = sessions().read().unwrap_or_else(|e| e.into_inner());
+    sessions.keys().cloned().collect()
+}
+
+/// Read the session id passed to a session hook
+///
+/// Sets the last error and returns the status to report on failure. Used by
+/// [`declare_session_hooks!`](crate::declare_session_hooks).
+///
+/// # Safety
+///
+/// `session_id` must be null or a valid null-terminated C string.
+pub unsafe fn session_id_from_ptr<'a>(
+    session_id: *const c_char,
+) -> Result<&'a str, crate::PluginStatus> {
+    if session_id.is_null() {
+        crate::utils::set_last_error("Session id is null");
+        return Err(crate::PluginStatus::InvalidArgument);
+    }
+
+    CStr::from_ptr(session_id).to_str().map_err(|_| {
+        crate::utils::set_last_error("Invalid session id encoding");
+        crate::PluginStatus::InvalidUtf8
+    })
+}
+
+/// Standard on_session_start implementation
+///
+/// Opens the session. Use [`declare_session_hooks!`](crate::declare_session_hooks)
+/// to run plugin code as well.
+///
+/// # Safety
+///
+/// `session_id` must be null or a valid null-terminated C string.
+pub unsafe extern "C" fn standard_session_start(session_id: *const c_char) -> i32 {
+    crate::utils::clear_last_error();
+    match session_id_from_ptr(session_id) {
+        Ok(id) => {
+            open(id);
+            crate::PluginStatus::Ok.code()
+        }
+        Err(status) => status.code(),
+    }
+}
+
+/// Standard on_session_end implementation
+///
+/// Closes the session and drops its values. Ending an unknown session is not
+/// an error.
+///
+/// # Safety
+///
+/// `session_id` must be null or a valid null-terminated C string.
+pub unsafe extern "C" fn standard_session_end(session_id: *const c_char) -> i32 {
+    crate::utils::clear_last_error();
+    match session_id_from_ptr(session_id) {
+        Ok(id) => {
+            close(id);
+            crate::PluginStatus::Ok.code()
+        }
+        Err(status) => status.code(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ToolContext;
+    use serde_json::json;
+
+    #[test]
+    fn calls_only_see_started_sessions() {
+        let ctx = ToolContext::from_json(json!({ "sessionId": "session-lifecycle" }));
+        assert!(ctx.session().is_none());
+        assert!(get("session-lifecycle").is_none());
+
+        let session = open("session-lifecycle");
+        session.insert("project", "alpha".to_string());
+        let seen = ctx.session().unwrap();
+        assert_eq!(seen.get::<String>("project").as_deref(), Some("alpha"));
+        assert!(ids().contains(&"session-lifecycle".to_string()));
+
+        assert!(close("session-lifecycle").is_some());
+        assert!(ctx.session().is_none());
+        assert!(close("session-lifecycle").is_none());
+    }
+
+    #[test]
+    fn update_keeps_values_of_other_types() {
+        let session = Session::new("session-update");
+        assert_eq!(session.update("page", |page: &mut u32| {
+            *page += 1;
+            *page
+        }), Ok(1));
+        assert_eq!(session.update("page", |page: &mut u32| *page), Ok(1));
+
+        assert!(session.update("page", |_: &mut String| ()).is_err());
+        assert_eq!(session.get::<u32>("page"), Some(1));
+        assert_eq!(session.get::<String>("page"), None);
+    }
+}
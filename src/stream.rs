@@ -0,0 +1,187 @@
+//! Streaming tool results
+//!
+//! `execute_tool` returns a single buffer, so a tool producing a large export
+//! or a token stream has to hold the whole result in memory, and the client
+//! sees nothing until the call finishes. Tools built with
+//! [`ToolBuilder::streaming_handler`](crate::ToolBuilder::streaming_handler)
+//! instead push chunks through a [`ChunkWriter`] as they are produced.
+//!
+//! ## Protocol
+//!
+//! The host calls `execute_tool_stream` with a [`StreamSink`]. The plugin
+//! calls `sink.write` once per chunk, synchronously and never concurrently;
+//! the chunk is only valid for the duration of the call, so the host copies
+//! what it needs. Calls with a deadline run on a worker thread, so `write`
+//! may be called from a thread other than the caller's, but never after
+//! `execute_tool_stream` has returned. A non-zero return from `write` means
+//! the consumer is gone (e.g. the client cancelled), and the handler's next
+//! write fails with [`ToolError::Cancelled`]. When the handler returns, its
+//! final value (or error) is returned through the result buffer like with
+//! `execute_tool`.
+//!
+//! When a streaming tool is called through plain `execute_tool`, the chunks
+//! are buffered and returned as a single text content item; a non-null final
+//! value is attached as `structuredContent`.
+
+use crate::context::CancellationToken;
+use crate::error::ToolError;
+use serde_json::Value;
+use std::os::raw::c_void;
+
+/// Function signature for receiving a chunk of a streamed result
+///
+/// # Parameters
+/// - `sink_data`: The `sink_data` pointer from [`StreamSink`]
+/// - `chunk`: Chunk bytes (only valid for the duration of the call)
+/// - `chunk_len`: Length of chunk
+///
+/// # Returns
+/// - 0 to continue
+/// - Non-zero to stop the stream
+pub type StreamWriteFn = unsafe extern "C" fn(
+    *mut c_void, // sink data
+    *const u8,   // chunk
+    usize,       // chunk length
+) -> i32;
+
+/// Host-provided destination for streamed chunks
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct StreamSink {
+    /// Opaque pointer passed back to `write`
+    pub sink_data: *mut c_void,
+
+    /// Receives each chunk
+    pub write: StreamWriteFn,
+}
+
+/// Writer handed to streaming tool handlers
+///
+/// Implements [`std::io::Write`], so it can be wrapped in a `BufWriter` or
+/// passed to `serde_json::to_writer`. See [`crate::utils::stream_text`] and
+/// [`crate::utils::stream_json_line`] for the common cases.
+pub struct ChunkWriter {
+    target: Target,
+    bytes_written: usize,
+    cancellation: Option<CancellationToken>,
+}
+
+// The sink is only written to by the thread owning the writer, and the host
+// guarantees that it stays valid until `execute_tool_stream` returns; after
+// that, the cancellation token keeps the writer from using it.
+unsafe impl Send for ChunkWriter {}
+
+enum Target {
+    Sink(StreamSink),
+    Buffer(Vec<u8>),
+    Closed,
+}
+
+impl ChunkWriter {
+    /// Create a writer forwarding chunks to a host sink
+    pub fn from_sink(sink: StreamSink) -> Self {
+        ChunkWriter {
+            target: Target::Sink(sink),
+            bytes_written: 0,
+            cancellation: None,
+        }
+    }
+
+    /// Create a writer collecting all chunks in memory
+    ///
+    /// Used when a streaming tool is called without a sink, and in tests.
+    pub fn buffered() -> Self {
+        ChunkWriter {
+            target: Target::Buffer(Vec::new()),
+            bytes_written: 0,
+            cancellation: None,
+        }
+    }
+
+    /// Stop the stream once `token` is cancelled
+    ///
+    /// No chunk is written after [`CancellationToken::cancel`] returned.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Send one chunk
+    ///
+    /// Empty chunks are skipped. Returns [`ToolError::Cancelled`] once the
+    /// host has stopped the stream or the call was cancelled.
+    pub fn write_chunk(&mut self, chunk: &[u8]) -> Result<(), ToolError> {
+        if chunk.is_empty() {
+            return match self.target {
+                Target::Closed => Err(ToolError::Cancelled),
+                _ => Ok(()),
+            };
+        }
+
+        let token = self.cancellation.clone();
+        let _gate = token.as_ref().map(CancellationToken::gate);
+        if token.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            self.target = Target::Closed;
+        }
+
+        match &mut self.target {
+            Target::Sink(sink) => {
+                let code = unsafe { (sink.write)(sink.sink_data, chunk.as_ptr(), chunk.len()) };
+                if code != 0 {
+                    self.target = Target::Closed;
+                    return Err(ToolError::Cancelled);
+                }
+            }
+            Target::Buffer(buffer) => buffer.extend_from_slice(chunk),
+            Target::Closed => return Err(ToolError::Cancelled),
+        }
+
+        self.bytes_written += chunk.len();
+        Ok(())
+    }
+
+    /// Whether the host has stopped the stream
+    pub fn is_closed(&self) -> bool {
+        matches!(self.target, Target::Closed)
+    }
+
+    /// Total number of bytes written so far
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+
+    /// The collected chunks of a [`buffered`](Self::buffered) writer
+    ///
+    /// Returns `None` for writers forwarding to a sink.
+    pub fn into_buffer(self) -> Option<Vec<u8>> {
+        match self.target {
+            Target::Buffer(buffer) => Some(buffer),
+            _ => None,
+        }
+    }
+
+    /// Build the `execute_tool` result of a buffered streaming call
+    ///
+    /// The collected chunks become a single text content item; a non-null
+    /// `result` is attached as `structuredContent`.
+    pub fn into_buffered_result(self, result: Value) -> Value {
+        let text = String::from_utf8_lossy(&self.into_buffer().unwrap_or_default()).into_owned();
+        let mut response = crate::utils::text_content(text);
+        if !result.is_null() {
+            response["structuredContent"] = result;
+        }
+        response
+    }
+}
+
+impl std::io::Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.write_chunk(buf)
+            .map(|_| buf.len())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::BrokenPipe, e))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -3,7 +3,22 @@
 //! This module provides a high-level API for defining tools with
 //! compile-time type checking and automatic JSON schema generation.
 
+use crate::attachment::{self, Attachment};
+use crate::auth;
+use crate::cache::ToolCache;
+use crate::completion::CompletionProvider;
+use crate::context::ToolContext;
+use crate::encoding::{self, WireEncoding};
+use crate::instance;
+use crate::middleware::{Middleware, Next, ToolCall};
+use crate::stream::ChunkWriter;
+use crate::worker;
+use serde::Serialize;
+use serde_json::value::RawValue;
 use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 
 /// A parameter definition for a tool
 #[derive(Debug, Clone)]
@@ -12,6 +27,8 @@ pub struct ToolParam {
     pub description: String,
     pub param_type: ParamType,
     pub required: bool,
+    /// Optional provider for argument completion
+    pub completion: Option<CompletionProvider>,
 }
 
 /// Parameter type enumeration
@@ -45,6 +62,66 @@ impl ParamType {
 /// or an error message.
 pub type ToolHandler = fn(&Value) -> Result<Value, String>;
 
+/// Tool handler function type with access to the call context
+///
+/// Like [`ToolHandler`], but also receives the [`ToolContext`] of the call,
+/// which gives access to host features such as sampling.
+pub type ContextToolHandler = fn(&ToolContext, &Value) -> Result<Value, String>;
+
+/// Tool handler function type producing a streamed result
+///
+/// Writes chunks to the [`ChunkWriter`] as they are produced and returns
+/// the final value of the call (`Value::Null` if there is none). See
+/// [`crate::stream`] for the protocol.
+pub type StreamingToolHandler =
+    fn(&ToolContext, &Value, &mut ChunkWriter) -> Result<Value, String>;
+
+/// Tool handler receiving the unparsed arguments
+///
+/// The arguments are borrowed straight from the host's buffer, so tools that
+/// forward the payload or only need a few fields avoid building a `Value`
+/// tree. See [`ToolBuilder::raw_handler`].
+pub type RawToolHandler = fn(&ToolContext, &RawValue) -> Result<Value, String>;
+
+/// Tool handler producing an already serialized result
+///
+/// Created by [`ToolBuilder::typed_handler`], which wraps a handler
+/// returning any `Serialize` type. The result is written directly in the
+/// requested wire encoding.
+pub type TypedToolHandler =
+    Arc<dyn Fn(&ToolContext, &Value, WireEncoding) -> Result<Vec<u8>, String> + Send + Sync>;
+
+/// The handler of a tool, in one of the supported forms
+#[derive(Clone)]
+pub enum ToolHandlerKind {
+    /// Handler receiving only the arguments
+    Plain(ToolHandler),
+    /// Handler receiving the call context and the arguments
+    WithContext(ContextToolHandler),
+    /// Handler streaming its result in chunks
+    Streaming(StreamingToolHandler),
+    /// Handler returning a typed result, serialized without a `Value` tree
+    Typed(TypedToolHandler),
+    /// Handler receiving the arguments as borrowed raw JSON
+    Raw(RawToolHandler),
+}
+
+impl std::fmt::Debug for ToolHandlerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolHandlerKind::Plain(handler) => f.debug_tuple("Plain").field(handler).finish(),
+            ToolHandlerKind::WithContext(handler) => {
+                f.debug_tuple("WithContext").field(handler).finish()
+            }
+            ToolHandlerKind::Streaming(handler) => {
+                f.debug_tuple("Streaming").field(handler).finish()
+            }
+            ToolHandlerKind::Typed(_) => f.write_str("Typed(..)"),
+            ToolHandlerKind::Raw(handler) => f.debug_tuple("Raw").field(handler).finish(),
+        }
+    }
+}
+
 /// A tool definition
 ///
 /// This represents a single tool with its metadata and handler function.
@@ -52,7 +129,15 @@ pub struct Tool {
     pub name: String,
     pub description: String,
     pub params: Vec<ToolParam>,
-    pub handler: ToolHandler,
+    pub handler: ToolHandlerKind,
+    /// Maximum run time of a call (see [`ToolBuilder::timeout`])
+    pub timeout: Option<Duration>,
+    /// Scopes the caller must hold (see [`ToolBuilder::require_scopes`])
+    pub required_scopes: Vec<String>,
+    /// Middleware run around the handler (see [`ToolBuilder::middleware`])
+    pub middleware: Vec<Arc<dyn Middleware>>,
+    /// Cache of successful results (see [`ToolBuilder::cache`])
+    pub cache: Option<Arc<ToolCache>>,
 }
 
 impl Tool {
@@ -70,9 +155,264 @@ impl Tool {
             name: name.to_string(),
             description: description.to_string(),
             params: Vec::new(),
+            timeout: None,
+            required_scopes: Vec::new(),
+            middleware: Vec::new(),
+            cache: None,
         }
     }
     
+    /// Call the tool's handler
+    ///
+    /// Streaming handlers write into a buffer, which is returned as a single
+    /// result (see [`ChunkWriter::into_buffered_result`]). Typed handlers are
+    /// converted to a `Value`; the dispatchers use [`call_encoded`](Self::call_encoded)
+    /// for them instead.
+    pub fn call(&self, ctx: &ToolContext, args: &Value) -> Result<Value, String> {
+        match &self.handler {
+            ToolHandlerKind::Plain(handler) => handler(args),
+            ToolHandlerKind::WithContext(handler) => handler(ctx, args),
+            ToolHandlerKind::Streaming(handler) => {
+                let mut out = ChunkWriter::buffered();
+                let result = handler(ctx, args, &mut out)?;
+                Ok(out.into_buffered_result(result))
+            }
+            ToolHandlerKind::Typed(handler) => {
+                let bytes = handler(ctx, args, WireEncoding::Json)?;
+                serde_json::from_slice(&bytes).map_err(|e| e.to_string())
+            }
+            ToolHandlerKind::Raw(handler) => {
+                let raw = serde_json::value::to_raw_value(args).map_err(|e| e.to_string())?;
+                handler(ctx, &raw)
+            }
+        }
+    }
+
+    /// Call the tool's handler with parsed arguments
+    ///
+    /// Raw handlers receive [`ToolArgs::Raw`] unchanged; all other handlers
+    /// get a `Value`, parsed from raw arguments if necessary.
+    pub fn call_args(
+        &self,
+        ctx: &ToolContext,
+        args: &ToolArgs<'_>,
+        out: Option<&mut ChunkWriter>,
+    ) -> Result<Value, String> {
+        let args = match (&self.handler, args) {
+            (ToolHandlerKind::Raw(handler), ToolArgs::Raw(raw)) => return handler(ctx, raw),
+            (_, ToolArgs::Raw(raw)) => {
+                Cow::Owned(serde_json::from_str(raw.get()).map_err(|e| e.to_string())?)
+            }
+            (_, ToolArgs::Value(value)) => Cow::Borrowed(value),
+        };
+
+        match out {
+            Some(out) => self.call_stream(ctx, &args, out),
+            None => self.call(ctx, &args),
+        }
+    }
+
+    /// Run the tool as the generated dispatchers do
+    ///
+    /// Typed handlers produce an encoded result; all others a `Value`
+    /// together with the binary attachments created during the call. The
+    /// context's instance is the [current](instance::current) one meanwhile.
+    /// The handler runs behind `global` and the tool's own
+    /// [middleware](crate::middleware). Tools with a
+    /// [cache](ToolBuilder::cache) answer calls that are not streamed from
+    /// the cache in place of the handler, so middleware runs on hits too.
+    pub fn run(
+        &self,
+        global: &[Arc<dyn Middleware>],
+        ctx: &ToolContext,
+        args: &ToolArgs<'_>,
+        mut out: Option<&mut ChunkWriter>,
+    ) -> Result<ToolOutput, String> {
+        instance::enter(ctx.instance().cloned(), || {
+            let mut handler = |call: &ToolCall<'_>| {
+                let cached = match (&self.cache, &out) {
+                    (Some(cache), None) => cache.key(call.ctx, call.args).map(|key| (cache, key)),
+                    _ => None,
+                };
+                let Some((cache, key)) = cached else {
+                    return self.call_output(call, out.as_deref_mut());
+                };
+
+                if let Some(output) = cache.get(&key) {
+                    return Ok(output);
+                }
+                let result = self.call_output(call, None);
+                if let Ok(output) = &result {
+                    cache.insert(key, output.clone());
+                }
+                result
+            };
+
+            let call = ToolCall {
+                tool: self,
+                ctx,
+                args,
+            };
+            Next::new(global, &self.middleware, &mut handler).run(&call)
+        })
+    }
+
+    /// Call the handler, capturing the attachments it creates
+    fn call_output(
+        &self,
+        call: &ToolCall<'_>,
+        out: Option<&mut ChunkWriter>,
+    ) -> Result<ToolOutput, String> {
+        if let ToolArgs::Value(args) = call.args {
+            if let Some(result) = self.call_encoded(call.ctx, args) {
+                return result.map(ToolOutput::Encoded);
+            }
+        }
+
+        let (result, attachments) =
+            attachment::capture(|| self.call_args(call.ctx, call.args, out));
+        result.map(|value| ToolOutput::Value(value, attachments))
+    }
+
+    /// Run the tool as the generated dispatchers do, enforcing its required
+    /// scopes and deadline
+    ///
+    /// Callers without the [required scopes](ToolBuilder::require_scopes) get
+    /// [`ToolOutcome::Forbidden`] and the handler is not run. Panics are
+    /// caught. Without a deadline (neither a tool
+    /// [`timeout`](ToolBuilder::timeout) nor a `"deadlineMs"` from the host),
+    /// the handler runs on the calling thread. Otherwise it runs on a pooled
+    /// worker thread; if the deadline passes first, the call's
+    /// [`CancellationToken`](crate::context::CancellationToken) is cancelled
+    /// and [`ToolOutcome::TimedOut`] is returned without waiting for the
+    /// handler. Its result is discarded when it eventually returns.
+    ///
+    /// A handler ignoring its cancellation keeps its worker busy until it
+    /// returns, and the pool is capped at 64 threads. When all of them are
+    /// busy, the handler runs on the calling thread instead and can only be
+    /// stopped by its own [cancellation checks](ToolContext::check_cancelled);
+    /// the call still ends as [`ToolOutcome::TimedOut`] if it overran.
+    pub fn execute(
+        self: &Arc<Self>,
+        global: &[Arc<dyn Middleware>],
+        ctx: &ToolContext,
+        args: ToolArgs<'_>,
+        mut out: Option<ChunkWriter>,
+    ) -> ToolOutcome {
+        if let Err(e) = auth::authorize(ctx.principal(), &self.required_scopes) {
+            return ToolOutcome::Forbidden(e);
+        }
+
+        let ctx = match self.timeout {
+            Some(timeout) => ctx.clone().with_deadline(Instant::now() + timeout),
+            None => ctx.clone(),
+        };
+
+        let limit = match ctx.remaining() {
+            Some(limit) => limit,
+            None => {
+                return match crate::utils::catch_panic(|| self.run(global, &ctx, &args, out.as_mut())) {
+                    Ok(result) => ToolOutcome::Done(result),
+                    Err(panic) => ToolOutcome::Panicked(panic),
+                };
+            }
+        };
+
+        if limit.is_zero() {
+            ctx.cancellation().cancel();
+            return ToolOutcome::TimedOut(limit);
+        }
+
+        let deadline = Instant::now() + limit;
+        let token = ctx.cancellation().clone();
+        let mut out = out.map(|out| out.with_cancellation(token.clone()));
+        let args = args.into_owned();
+        let tool = Arc::clone(self);
+        let global = global.to_vec();
+        let (sender, receiver) = mpsc::channel();
+        #[cfg(feature = "tracing")]
+        let span = tracing::Span::current();
+
+        let job: worker::Job = Box::new(move || {
+            // Keep the handler's spans under the dispatcher's call span
+            #[cfg(feature = "tracing")]
+            let _entered = span.enter();
+            let result =
+                crate::utils::catch_panic(|| tool.run(&global, &ctx, &args, out.as_mut()));
+            // The dispatcher is gone if the call timed out
+            let _ = sender.send(result);
+        });
+
+        let received = match worker::spawn(job) {
+            Ok(()) => receiver.recv_timeout(limit),
+            Err(job) => {
+                // All workers are busy; run here and rely on the handler's
+                // cancellation checks
+                job();
+                if Instant::now() < deadline {
+                    receiver.recv_timeout(Duration::ZERO)
+                } else {
+                    Err(mpsc::RecvTimeoutError::Timeout)
+                }
+            }
+        };
+
+        match received {
+            Ok(Ok(result)) => ToolOutcome::Done(result),
+            Ok(Err(panic)) => ToolOutcome::Panicked(panic),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                token.cancel();
+                ToolOutcome::TimedOut(limit)
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                ToolOutcome::Panicked("tool thread exited without a result".to_string())
+            }
+        }
+    }
+
+    /// Whether the handler takes raw (unparsed) arguments
+    pub fn takes_raw_args(&self) -> bool {
+        matches!(self.handler, ToolHandlerKind::Raw(_))
+    }
+
+    /// Call a typed handler, serializing its result in the selected wire encoding
+    ///
+    /// Returns `None` for other handler kinds.
+    pub fn call_encoded(&self, ctx: &ToolContext, args: &Value) -> Option<Result<Vec<u8>, String>> {
+        match &self.handler {
+            ToolHandlerKind::Typed(handler) => Some(handler(ctx, args, encoding::current())),
+            _ => None,
+        }
+    }
+
+    /// Call the tool's handler, streaming its output to `out`
+    ///
+    /// Non-streaming handlers write nothing and return their result as usual.
+    pub fn call_stream(
+        &self,
+        ctx: &ToolContext,
+        args: &Value,
+        out: &mut ChunkWriter,
+    ) -> Result<Value, String> {
+        match &self.handler {
+            ToolHandlerKind::Streaming(handler) => handler(ctx, args, out),
+            _ => self.call(ctx, args),
+        }
+    }
+
+    /// Whether the tool streams its result
+    pub fn is_streaming(&self) -> bool {
+        matches!(self.handler, ToolHandlerKind::Streaming(_))
+    }
+
+    /// Get the completion provider attached to a parameter, if any
+    pub fn completion_provider(&self, param: &str) -> Option<CompletionProvider> {
+        self.params
+            .iter()
+            .find(|p| p.name == param)
+            .and_then(|p| p.completion)
+    }
+
     /// Convert tool definition to JSON Schema format
     ///
     /// Returns a JSON object compatible with MCP protocol:
@@ -88,33 +428,116 @@ impl Tool {
     /// }
     /// ```
     pub fn to_json_schema(&self) -> Value {
-        let mut properties = serde_json::Map::new();
-        let mut required = Vec::new();
-        
-        for param in &self.params {
-            properties.insert(
-                param.name.clone(),
-                json!({
-                    "type": param.param_type.to_json_type(),
-                    "description": param.description
-                })
-            );
-            
-            if param.required {
-                required.push(param.name.clone());
-            }
-        }
-        
-        json!({
+        let mut schema = json!({
             "name": self.name,
             "description": self.description,
-            "inputSchema": {
-                "type": "object",
-                "properties": properties,
-                "required": required
+            "inputSchema": params_to_schema(&self.params)
+        });
+
+        if !self.required_scopes.is_empty() {
+            schema["_meta"] = json!({ "requiredScopes": self.required_scopes });
+        }
+        schema
+    }
+}
+
+/// Successful result of [`Tool::run`]
+#[derive(Debug, Clone)]
+pub enum ToolOutput {
+    /// Result already serialized in the selected wire encoding
+    Encoded(Vec<u8>),
+    /// Result value and the binary attachments it references
+    Value(Value, Vec<Attachment>),
+}
+
+/// Outcome of [`Tool::execute`]
+pub enum ToolOutcome {
+    /// The handler returned
+    Done(Result<ToolOutput, String>),
+    /// The handler panicked (with the panic message)
+    Panicked(String),
+    /// The deadline passed before the handler returned; holds the time limit
+    /// the call had
+    TimedOut(Duration),
+    /// The caller lacks a required scope; the handler was not run
+    Forbidden(String),
+}
+
+/// Arguments of a tool call, parsed as far as the handler needs
+pub enum ToolArgs<'a> {
+    /// Fully parsed arguments
+    Value(Value),
+    /// Unparsed arguments, borrowed from the host's buffer when possible
+    Raw(Cow<'a, RawValue>),
+}
+
+impl<'a> ToolArgs<'a> {
+    /// Parse an argument buffer for a tool
+    ///
+    /// With `raw` set and JSON as wire encoding, the buffer is only validated
+    /// and borrowed, not materialized. With a binary wire encoding the
+    /// arguments are decoded and re-serialized as JSON.
+    pub fn parse(bytes: &'a [u8], raw: bool) -> Result<Self, String> {
+        if !raw {
+            return encoding::decode(bytes).map(ToolArgs::Value);
+        }
+
+        match encoding::current() {
+            WireEncoding::Json => serde_json::from_slice::<&RawValue>(bytes)
+                .map(|raw| ToolArgs::Raw(Cow::Borrowed(raw)))
+                .map_err(|e| e.to_string()),
+            other => {
+                let value = other.decode(bytes)?;
+                serde_json::value::to_raw_value(&value)
+                    .map(|raw| ToolArgs::Raw(Cow::Owned(raw)))
+                    .map_err(|e| e.to_string())
             }
-        })
+        }
+    }
+
+    /// Copy borrowed raw arguments, detaching them from the host's buffer
+    pub fn into_owned(self) -> ToolArgs<'static> {
+        match self {
+            ToolArgs::Value(value) => ToolArgs::Value(value),
+            ToolArgs::Raw(raw) => ToolArgs::Raw(Cow::Owned(raw.into_owned())),
+        }
+    }
+}
+
+/// Convert a parameter list to a JSON Schema object
+///
+/// Returns the schema used as a tool's `inputSchema` (and by elicitation
+/// requests):
+/// ```json
+/// {
+///   "type": "object",
+///   "properties": { ... },
+///   "required": [ ... ]
+/// }
+/// ```
+pub fn params_to_schema(params: &[ToolParam]) -> Value {
+    let mut properties = serde_json::Map::new();
+    let mut required = Vec::new();
+    
+    for param in params {
+        properties.insert(
+            param.name.clone(),
+            json!({
+                "type": param.param_type.to_json_type(),
+                "description": param.description
+            })
+        );
+        
+        if param.required {
+            required.push(param.name.clone());
+        }
     }
+    
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required
+    })
 }
 
 /// Builder for creating tools with a fluent API
@@ -122,6 +545,10 @@ pub struct ToolBuilder {
     name: String,
     description: String,
     params: Vec<ToolParam>,
+    timeout: Option<Duration>,
+    required_scopes: Vec<String>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    cache: Option<Arc<ToolCache>>,
 }
 
 impl ToolBuilder {
@@ -137,6 +564,7 @@ impl ToolBuilder {
             description: description.to_string(),
             param_type: ParamType::String,
             required,
+            completion: None,
         });
         self
     }
@@ -148,6 +576,7 @@ impl ToolBuilder {
             description: description.to_string(),
             param_type: ParamType::Integer,
             required,
+            completion: None,
         });
         self
     }
@@ -159,6 +588,7 @@ impl ToolBuilder {
             description: description.to_string(),
             param_type: ParamType::Number,
             required,
+            completion: None,
         });
         self
     }
@@ -170,6 +600,7 @@ impl ToolBuilder {
             description: description.to_string(),
             param_type: ParamType::Boolean,
             required,
+            completion: None,
         });
         self
     }
@@ -181,6 +612,7 @@ impl ToolBuilder {
             description: description.to_string(),
             param_type: ParamType::Object,
             required,
+            completion: None,
         });
         self
     }
@@ -192,20 +624,338 @@ impl ToolBuilder {
             description: description.to_string(),
             param_type: ParamType::Array,
             required,
+            completion: None,
         });
         self
     }
     
+    /// Attach a completion provider to a parameter
+    ///
+    /// # Panics
+    ///
+    /// Panics if no parameter named `param` has been added yet;
+    /// `declare_tools!` reports the panic to the host as an error.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// fn complete_sku(partial: &str) -> Completion {
+    ///     Completion::from_candidates(partial, known_skus())
+    /// }
+    ///
+    /// Tool::builder("get_price", "Get the price of a product")
+    ///     .param_string("sku", "Product SKU", true)
+    ///     .complete("sku", complete_sku)
+    ///     .handler(handle_get_price)
+    /// ```
+    pub fn complete(mut self, param: &str, provider: CompletionProvider) -> Self {
+        let tool = &self.name;
+        let target = self
+            .params
+            .iter_mut()
+            .find(|p| p.name == param)
+            .unwrap_or_else(|| panic!("Tool '{}' has no parameter '{}'", tool, param));
+        target.completion = Some(provider);
+        self
+    }
+
+    /// Limit how long a call may run
+    ///
+    /// If the host also sends a deadline, the earlier one applies. When the
+    /// limit is reached, the host gets a `PluginStatus::Timeout` error and the
+    /// handler's context is cancelled (see [`ToolContext::check_cancelled`]).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// Tool::builder("crawl", "Crawl a site")
+    ///     .param_string("url", "Start URL", true)
+    ///     .timeout(Duration::from_secs(30))
+    ///     .handler_with_context(handle_crawl)
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Only run the tool for callers holding all of these scopes
+    ///
+    /// Calls without a [`Principal`](crate::Principal) or with missing scopes
+    /// fail with `PluginStatus::Forbidden` before the handler runs. The
+    /// scopes are listed in the tool's `_meta.requiredScopes`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// Tool::builder("cancel_order", "Cancel an order")
+    ///     .param_string("order_id", "Order to cancel", true)
+    ///     .require_scopes(&["orders:write"])
+    ///     .handler_with_context(handle_cancel_order)
+    /// ```
+    pub fn require_scopes(mut self, scopes: &[&str]) -> Self {
+        self.required_scopes
+            .extend(scopes.iter().map(|scope| scope.to_string()));
+        self
+    }
+
+    /// Run a middleware around this tool's handler
+    ///
+    /// Middleware added first runs outermost, inside any global middleware
+    /// of `declare_tools!`. See [`crate::middleware`].
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Cache successful results for `ttl`, keeping up to `max_entries`
+    ///
+    /// Repeated calls with the same arguments are answered without running
+    /// the handler. Only use it for tools whose result depends on nothing but
+    /// their arguments; see [`crate::cache`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_entries` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// Tool::builder("exchange_rate", "Get the exchange rate of two currencies")
+    ///     .param_string("from", "Source currency", true)
+    ///     .param_string("to", "Target currency", true)
+    ///     .cache(Duration::from_secs(60), 1000)
+    ///     .handler(handle_exchange_rate)
+    /// ```
+    pub fn cache(mut self, ttl: Duration, max_entries: usize) -> Self {
+        self.cache = Some(Arc::new(ToolCache::new(ttl, max_entries)));
+        self
+    }
+
     /// Set the handler function and finalize the tool
     ///
     /// This consumes the builder and returns the completed Tool.
     pub fn handler(self, handler: ToolHandler) -> Tool {
+        self.build(ToolHandlerKind::Plain(handler))
+    }
+
+    /// Set a handler receiving the call context and finalize the tool
+    ///
+    /// Use this when the handler needs host features such as sampling.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// fn handle_summarize(ctx: &ToolContext, args: &Value) -> Result<Value, String> {
+    ///     let result = ctx.sample(
+    ///         vec![SamplingMessage::user(args["text"].as_str().unwrap_or_default())],
+    ///         SamplingParams::new(200).system_prompt("Summarize in one sentence."),
+    ///     )?;
+    ///     Ok(text_content(result.text().unwrap_or_default()))
+    /// }
+    ///
+    /// Tool::builder("summarize", "Summarize a text")
+    ///     .param_string("text", "Text to summarize", true)
+    ///     .handler_with_context(handle_summarize)
+    /// ```
+    pub fn handler_with_context(self, handler: ContextToolHandler) -> Tool {
+        self.build(ToolHandlerKind::WithContext(handler))
+    }
+
+    /// Set a streaming handler and finalize the tool
+    ///
+    /// Use this for large or incremental output (exports, token streams).
+    /// Hosts calling `execute_tool_stream` receive each chunk as it is
+    /// written; other hosts receive the concatenated output.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// fn handle_export(_ctx: &ToolContext, args: &Value, out: &mut ChunkWriter) -> Result<Value, String> {
+    ///     let mut rows = 0;
+    ///     for order in load_orders(args["since"].as_str().unwrap_or_default())? {
+    ///         stream_json_line(out, &json!({"id": order.id, "total": order.total}))?;
+    ///         rows += 1;
+    ///     }
+    ///     Ok(json!({ "rows": rows }))
+    /// }
+    ///
+    /// Tool::builder("export_orders", "Export orders as JSON lines")
+    ///     .param_string("since", "ISO date of the oldest order", true)
+    ///     .streaming_handler(handle_export)
+    /// ```
+    pub fn streaming_handler(self, handler: StreamingToolHandler) -> Tool {
+        self.build(ToolHandlerKind::Streaming(handler))
+    }
+
+    /// Set a handler receiving raw arguments and finalize the tool
+    ///
+    /// The handler gets the arguments as `&RawValue`, borrowed from the
+    /// host's buffer without building a `Value` tree. Deserialize only what
+    /// is needed, into borrowed fields where possible (see
+    /// [`utils::parse_raw_args`](crate::utils::parse_raw_args)), or forward
+    /// the payload unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// #[derive(Deserialize)]
+    /// struct UploadArgs<'a> {
+    ///     bucket: &'a str,
+    ///     #[serde(borrow)]
+    ///     document: &'a RawValue,
+    /// }
+    ///
+    /// fn handle_upload(_ctx: &ToolContext, args: &RawValue) -> Result<Value, String> {
+    ///     let args: UploadArgs = parse_raw_args(args)?;
+    ///     store(args.bucket, args.document.get())?;
+    ///     Ok(text_content("Stored"))
+    /// }
+    ///
+    /// Tool::builder("upload", "Store a JSON document")
+    ///     .param_string("bucket", "Target bucket", true)
+    ///     .param_object("document", "The document", true)
+    ///     .raw_handler(handle_upload)
+    /// ```
+    pub fn raw_handler(self, handler: RawToolHandler) -> Tool {
+        self.build(ToolHandlerKind::Raw(handler))
+    }
+
+    /// Set a handler returning a typed result and finalize the tool
+    ///
+    /// The result is serialized straight into the output buffer, without
+    /// building an intermediate `serde_json::Value` tree, which halves peak
+    /// memory for large results. Binary content created with
+    /// `image_bytes_content` inside a typed result is always inlined as base64.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// #[derive(Serialize)]
+    /// struct PriceList {
+    ///     prices: Vec<Price>,
+    /// }
+    ///
+    /// fn handle_price_list(args: &Value) -> Result<PriceList, String> {
+    ///     Ok(PriceList { prices: load_prices(args["category"].as_str())? })
+    /// }
+    ///
+    /// Tool::builder("price_list", "List all prices of a category")
+    ///     .param_string("category", "Product category", false)
+    ///     .typed_handler(handle_price_list)
+    /// ```
+    pub fn typed_handler<T: Serialize + 'static>(
+        self,
+        handler: fn(&Value) -> Result<T, String>,
+    ) -> Tool {
+        self.typed_handler_with_context(move |_, args| handler(args))
+    }
+
+    /// Like [`typed_handler`](Self::typed_handler), for handlers that need the call context
+    pub fn typed_handler_with_context<T, F>(self, handler: F) -> Tool
+    where
+        T: Serialize + 'static,
+        F: Fn(&ToolContext, &Value) -> Result<T, String> + Send + Sync + 'static,
+    {
+        self.build(ToolHandlerKind::Typed(Arc::new(move |ctx, args, encoding| {
+            let result = handler(ctx, args)?;
+            encoding
+                .encode(&result)
+                .map_err(|e| format!("Failed to serialize result: {}", e))
+        })))
+    }
+
+    fn build(self, handler: ToolHandlerKind) -> Tool {
         Tool {
             name: self.name,
             description: self.description,
             params: self.params,
             handler,
+            timeout: self.timeout,
+            required_scopes: self.required_scopes,
+            middleware: self.middleware,
+            cache: self.cache,
         }
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn execute(tool: Tool, ctx: &ToolContext) -> ToolOutcome {
+        Arc::new(tool).execute(&[], ctx, ToolArgs::Value(json!({})), None)
+    }
+
+    fn value(outcome: ToolOutcome) -> Value {
+        match outcome {
+            ToolOutcome::Done(Ok(ToolOutput::Value(value, _))) => value,
+            _ => panic!("call did not succeed"),
+        }
+    }
+
+    fn handle_thread_name(_args: &Value) -> Result<Value, String> {
+        Ok(json!(std::thread::current().name()))
+    }
+
+    #[test]
+    fn calls_with_deadline_run_on_a_worker() {
+        let tool = || Tool::builder("thread_name", "").handler(handle_thread_name);
+
+        let inline = value(execute(tool(), &ToolContext::new()));
+        assert_eq!(inline, json!(std::thread::current().name()));
+
+        let ctx = ToolContext::new().with_deadline(Instant::now() + Duration::from_secs(60));
+        assert_eq!(value(execute(tool(), &ctx)), json!("mcp-tool-worker"));
+    }
+
+    static ZERO_DEADLINE_RAN: AtomicBool = AtomicBool::new(false);
+
+    fn handle_zero_deadline(_args: &Value) -> Result<Value, String> {
+        ZERO_DEADLINE_RAN.store(true, Ordering::SeqCst);
+        Ok(Value::Null)
+    }
+
+    #[test]
+    fn passed_deadline_does_not_run_handler() {
+        let tool = Tool::builder("zero_deadline", "").handler(handle_zero_deadline);
+        let ctx = ToolContext::from_json(json!({ "deadlineMs": 0 }));
+
+        let outcome = execute(tool, &ctx);
+        assert!(matches!(outcome, ToolOutcome::TimedOut(limit) if limit.is_zero()));
+        assert!(ctx.cancellation().is_cancelled());
+        assert!(!ZERO_DEADLINE_RAN.load(Ordering::SeqCst));
+    }
+
+    static SAW_CANCELLATION: AtomicBool = AtomicBool::new(false);
+
+    /// Wait for the cancellation token, not just the deadline
+    fn handle_wait_for_cancel(ctx: &ToolContext, _args: &Value) -> Result<Value, String> {
+        let give_up = Instant::now() + Duration::from_secs(10);
+        while !ctx.cancellation().is_cancelled() && Instant::now() < give_up {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        SAW_CANCELLATION.store(ctx.cancellation().is_cancelled(), Ordering::SeqCst);
+        Err("cancelled".to_string())
+    }
+
+    #[test]
+    fn timeout_cancels_the_call() {
+        let tool = Tool::builder("wait_for_cancel", "")
+            .timeout(Duration::from_millis(20))
+            .handler_with_context(handle_wait_for_cancel);
+        let ctx = ToolContext::new();
+
+        let started = Instant::now();
+        let outcome = execute(tool, &ctx);
+        assert!(matches!(outcome, ToolOutcome::TimedOut(limit) if limit <= Duration::from_millis(20)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let give_up = Instant::now() + Duration::from_secs(5);
+        while !SAW_CANCELLATION.load(Ordering::SeqCst) && Instant::now() < give_up {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(SAW_CANCELLATION.load(Ordering::SeqCst));
+    }
+}
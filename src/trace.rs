@@ -0,0 +1,546 @@
+//! Trace context propagation
+//!
+//! Hosts running distributed tracing pass the W3C trace context of the MCP
+//! request in the call context:
+//!
+//! ```json
+//! {
+//!   "traceparent": "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+//!   "tracestate": "vendor=value"
+//! }
+//! ```
+//!
+//! Handlers get it as a [`TraceContext`] through
+//! [`ToolContext::trace`](crate::ToolContext::trace), e.g. to forward it to
+//! downstream services.
+//!
+//! ## Span Export
+//!
+//! With the `tracing` cargo feature, the dispatcher generated by
+//! `declare_tools!` runs every call inside an `execute_tool` span of the
+//! [`tracing`](https://docs.rs/tracing) crate, with `tool` and `status`
+//! fields. The span continues the host's trace, and spans and events the
+//! handler creates become its children.
+//!
+//! Finished spans are shipped to the host one by one through the
+//! `export_trace` host callback as JSON records, with ids in hex:
+//!
+//! ```json
+//! {
+//!   "kind": "span",
+//!   "traceId": "4bf92f3577b34da6a3ce929d0e0e4736",
+//!   "spanId": "b7ad6b7169203331",
+//!   "parentSpanId": "00f067aa0ba902b7",
+//!   "traceState": "vendor=value",
+//!   "name": "execute_tool",
+//!   "target": "my_plugin",
+//!   "level": "INFO",
+//!   "startTimeUnixNano": 1700000000000000000,
+//!   "endTimeUnixNano": 1700000000012000000,
+//!   "attributes": { "tool": "search", "status": "ok" },
+//!   "events": [
+//!     { "name": "cache miss", "level": "DEBUG", "target": "my_plugin",
+//!       "timeUnixNano": 1700000000001000000, "attributes": { "key": "abc" } }
+//!   ]
+//! }
+//! ```
+//!
+//! Events are attached to the span they occur in; events outside of any
+//! span are shipped on their own as `"kind": "event"` records. Spans of
+//! traces the host did not sample (trace flags without the sampled bit) are
+//! not exported.
+//!
+//! When the host provides `export_trace`, [`install`] sets a global
+//! subscriber exporting spans at `INFO` level and above. Plugins that set
+//! their own subscriber add [`HostLayer`] to it instead.
+
+use crate::context::ToolContext;
+use crate::tool::ToolOutcome;
+use serde_json::Value;
+
+/// Call context key carrying the W3C `traceparent` header
+pub const TRACEPARENT_KEY: &str = "traceparent";
+
+/// Call context key carrying the W3C `tracestate` header
+pub const TRACESTATE_KEY: &str = "tracestate";
+
+/// W3C trace context of the request that led to a tool call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    parent_id: u64,
+    flags: u8,
+    tracestate: Option<String>,
+}
+
+impl TraceContext {
+    /// Parse a `traceparent` header and the optional `tracestate` header
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use mcp_plugin_api::trace::TraceContext;
+    ///
+    /// let trace = TraceContext::parse(
+    ///     "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+    ///     None,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(trace.trace_id(), 0x4bf92f3577b34da6a3ce929d0e0e4736);
+    /// assert!(trace.is_sampled());
+    ///
+    /// let zero_trace_id = "00-00000000000000000000000000000000-00f067aa0ba902b7-01";
+    /// assert!(TraceContext::parse(zero_trace_id, None).is_err());
+    /// ```
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> Result<Self, String> {
+        let invalid = || format!("Invalid traceparent: {}", traceparent);
+        let parts: Vec<&str> = traceparent.trim().split('-').collect();
+
+        // Later versions may append fields, version 00 has exactly four
+        let (version, trace_id, parent_id, flags) = match parts.as_slice() {
+            [version, trace_id, parent_id, flags, rest @ ..]
+                if rest.is_empty() || *version != "00" =>
+            {
+                (*version, *trace_id, *parent_id, *flags)
+            }
+            _ => return Err(invalid()),
+        };
+
+        let hex = |field: &str, len: usize| {
+            field.len() == len && field.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        };
+        if !hex(version, 2)
+            || version == "ff"
+            || !hex(trace_id, 32)
+            || !hex(parent_id, 16)
+            || !hex(flags, 2)
+        {
+            return Err(invalid());
+        }
+
+        let trace_id = u128::from_str_radix(trace_id, 16).map_err(|_| invalid())?;
+        let parent_id = u64::from_str_radix(parent_id, 16).map_err(|_| invalid())?;
+        let flags = u8::from_str_radix(flags, 16).map_err(|_| invalid())?;
+        if trace_id == 0 || parent_id == 0 {
+            return Err(invalid());
+        }
+
+        Ok(TraceContext {
+            trace_id,
+            parent_id,
+            flags,
+            tracestate: tracestate.filter(|s| !s.is_empty()).map(str::to_string),
+        })
+    }
+
+    /// Read the trace context from a call context JSON object
+    ///
+    /// Returns `None` if there is none or it is invalid.
+    pub fn from_json(context: &Value) -> Option<Self> {
+        let traceparent = context.get(TRACEPARENT_KEY)?.as_str()?;
+        let tracestate = context.get(TRACESTATE_KEY).and_then(Value::as_str);
+        TraceContext::parse(traceparent, tracestate).ok()
+    }
+
+    /// Id of the whole trace
+    pub fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    /// Id of the host's span the call belongs to
+    pub fn parent_id(&self) -> u64 {
+        self.parent_id
+    }
+
+    /// Trace flags
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Whether the host records this trace
+    pub fn is_sampled(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+
+    /// Vendor-specific trace state, if any
+    pub fn tracestate(&self) -> Option<&str> {
+        self.tracestate.as_deref()
+    }
+
+    /// The `traceparent` header value
+    pub fn traceparent(&self) -> String {
+        format!("00-{:032x}-{:016x}-{:02x}", self.trace_id, self.parent_id, self.flags)
+    }
+}
+
+/// Span around one tool call
+///
+/// Created by the dispatcher of `declare_tools!`. Without the `tracing`
+/// feature it does nothing.
+pub struct ToolSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl ToolSpan {
+    /// Open the span of a call, continuing the host's trace if there is one
+    pub fn new(tool: &str, ctx: &ToolContext) -> Self {
+        #[cfg(feature = "tracing")]
+        {
+            let span = match ctx.trace() {
+                Some(trace) => tracing::info_span!(
+                    "execute_tool",
+                    tool,
+                    status = tracing::field::Empty,
+                    traceparent = %trace.traceparent(),
+                    tracestate = trace.tracestate(),
+                ),
+                None => tracing::info_span!("execute_tool", tool, status = tracing::field::Empty),
+            };
+            ToolSpan { span }
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = (tool, ctx);
+            ToolSpan {}
+        }
+    }
+
+    /// Run `f` inside the span
+    pub fn in_scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        #[cfg(feature = "tracing")]
+        return self.span.in_scope(f);
+
+        #[cfg(not(feature = "tracing"))]
+        f()
+    }
+
+    /// Record how the call ended in the `status` field
+    pub fn record_outcome(&self, outcome: &ToolOutcome) {
+        #[cfg(feature = "tracing")]
+        self.span.record(
+            "status",
+            match outcome {
+                ToolOutcome::Done(Ok(_)) => "ok",
+                ToolOutcome::Done(Err(_)) => "error",
+                ToolOutcome::Panicked(_) => "panic",
+                ToolOutcome::TimedOut(_) => "timeout",
+                ToolOutcome::Forbidden(_) => "denied",
+            },
+        );
+
+        #[cfg(not(feature = "tracing"))]
+        let _ = outcome;
+    }
+}
+
+#[cfg(feature = "tracing")]
+pub use export::{install, HostLayer};
+
+#[cfg(feature = "tracing")]
+mod export {
+    use super::{TraceContext, TRACEPARENT_KEY, TRACESTATE_KEY};
+    use serde_json::{json, Map, Value};
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::Context;
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    /// Install a global subscriber exporting spans to the host
+    ///
+    /// Exports spans and events at `INFO` level and above. Does nothing and
+    /// returns `false` if a global subscriber is already set. Called by
+    /// [`set_host_callbacks`](crate::host::set_host_callbacks) when the host
+    /// provides `export_trace`.
+    pub fn install() -> bool {
+        use tracing_subscriber::filter::LevelFilter;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let subscriber = tracing_subscriber::registry()
+            .with(HostLayer::new().with_filter(LevelFilter::INFO));
+        tracing::subscriber::set_global_default(subscriber).is_ok()
+    }
+
+    /// `tracing` layer shipping spans and events to the host
+    ///
+    /// A span without a parent continues the trace given by its
+    /// `traceparent` and `tracestate` fields, as the dispatcher's
+    /// `execute_tool` span does; otherwise it starts a new trace.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use tracing_subscriber::prelude::*;
+    ///
+    /// tracing_subscriber::registry()
+    ///     .with(tracing_subscriber::fmt::layer())
+    ///     .with(mcp_plugin_api::trace::HostLayer::new())
+    ///     .init();
+    /// ```
+    #[derive(Debug, Default)]
+    pub struct HostLayer {
+        _private: (),
+    }
+
+    impl HostLayer {
+        /// Create the layer
+        pub fn new() -> Self {
+            HostLayer::default()
+        }
+    }
+
+    struct SpanData {
+        trace_id: u128,
+        span_id: u64,
+        parent_id: Option<u64>,
+        sampled: bool,
+        tracestate: Option<String>,
+        start: u64,
+        attributes: Map<String, Value>,
+        events: Vec<Value>,
+    }
+
+    impl<S> Layer<S> for HostLayer
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let Some(span) = ctx.span(id) else {
+                return;
+            };
+
+            let mut attributes = Map::new();
+            attrs.record(&mut FieldVisitor(&mut attributes));
+            let traceparent = attributes.remove(TRACEPARENT_KEY);
+            let tracestate = attributes.remove(TRACESTATE_KEY);
+
+            let parent = span.parent().and_then(|parent| {
+                let extensions = parent.extensions();
+                let parent = extensions.get::<SpanData>()?;
+                Some((parent.trace_id, parent.span_id, parent.sampled))
+            });
+            let remote = || {
+                let traceparent = traceparent.as_ref()?.as_str()?;
+                let tracestate = tracestate.as_ref().and_then(Value::as_str);
+                TraceContext::parse(traceparent, tracestate).ok()
+            };
+
+            let (trace_id, parent_id, sampled, tracestate) = match parent {
+                Some((trace_id, span_id, sampled)) => (trace_id, Some(span_id), sampled, None),
+                None => match remote() {
+                    Some(remote) => (
+                        remote.trace_id(),
+                        Some(remote.parent_id()),
+                        remote.is_sampled(),
+                        remote.tracestate().map(str::to_string),
+                    ),
+                    None => ((random_id() as u128) << 64 | random_id() as u128, None, true, None),
+                },
+            };
+
+            span.extensions_mut().insert(SpanData {
+                trace_id,
+                span_id: random_id(),
+                parent_id,
+                sampled,
+                tracestate,
+                start: now_unix_nanos(),
+                attributes,
+                events: Vec::new(),
+            });
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+            let Some(span) = ctx.span(id) else {
+                return;
+            };
+            let mut extensions = span.extensions_mut();
+            if let Some(data) = extensions.get_mut::<SpanData>() {
+                values.record(&mut FieldVisitor(&mut data.attributes));
+            }
+        }
+
+        fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+            let metadata = event.metadata();
+            let mut attributes = Map::new();
+            event.record(&mut FieldVisitor(&mut attributes));
+            let name = match attributes.remove("message") {
+                Some(Value::String(message)) => message,
+                Some(message) => message.to_string(),
+                None => metadata.name().to_string(),
+            };
+            let mut record = json!({
+                "name": name,
+                "level": metadata.level().as_str(),
+                "target": metadata.target(),
+                "timeUnixNano": now_unix_nanos(),
+                "attributes": attributes,
+            });
+
+            if let Some(span) = ctx.event_span(event) {
+                let mut extensions = span.extensions_mut();
+                if let Some(data) = extensions.get_mut::<SpanData>() {
+                    if data.sampled {
+                        data.events.push(record);
+                    }
+                    return;
+                }
+            }
+
+            record["kind"] = json!("event");
+            let _ = crate::host::export_trace(&record);
+        }
+
+        fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+            let Some(span) = ctx.span(&id) else {
+                return;
+            };
+            let Some(data) = span.extensions_mut().remove::<SpanData>() else {
+                return;
+            };
+            if !data.sampled {
+                return;
+            }
+
+            let metadata = span.metadata();
+            let record = json!({
+                "kind": "span",
+                "traceId": format!("{:032x}", data.trace_id),
+                "spanId": format!("{:016x}", data.span_id),
+                "parentSpanId": data.parent_id.map(|id| format!("{:016x}", id)),
+                "traceState": data.tracestate,
+                "name": metadata.name(),
+                "target": metadata.target(),
+                "level": metadata.level().as_str(),
+                "startTimeUnixNano": data.start,
+                "endTimeUnixNano": now_unix_nanos(),
+                "attributes": data.attributes,
+                "events": data.events,
+            });
+            let _ = crate::host::export_trace(&record);
+        }
+    }
+
+    /// Collects span and event fields as JSON
+    struct FieldVisitor<'a>(&'a mut Map<String, Value>);
+
+    impl Visit for FieldVisitor<'_> {
+        fn record_f64(&mut self, field: &Field, value: f64) {
+            self.0.insert(field.name().to_string(), json!(value));
+        }
+
+        fn record_i64(&mut self, field: &Field, value: i64) {
+            self.0.insert(field.name().to_string(), json!(value));
+        }
+
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            self.0.insert(field.name().to_string(), json!(value));
+        }
+
+        fn record_bool(&mut self, field: &Field, value: bool) {
+            self.0.insert(field.name().to_string(), json!(value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), json!(value));
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name().to_string(), json!(format!("{:?}", value)));
+        }
+    }
+
+    /// Random non-zero id for spans and new traces
+    fn random_id() -> u64 {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        loop {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+            let id = hasher.finish();
+            if id != 0 {
+                return id;
+            }
+        }
+    }
+
+    fn now_unix_nanos() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX))
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn valid_traceparents_are_parsed() {
+        let trace = TraceContext::parse(TRACEPARENT, Some("vendor=value")).unwrap();
+        assert_eq!(trace.trace_id(), 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(trace.parent_id(), 0x00f067aa0ba902b7);
+        assert_eq!(trace.flags(), 0x01);
+        assert!(trace.is_sampled());
+        assert_eq!(trace.tracestate(), Some("vendor=value"));
+        assert_eq!(trace.traceparent(), TRACEPARENT);
+
+        let unsampled = TraceContext::parse(&TRACEPARENT.replace("-01", "-00"), Some("")).unwrap();
+        assert!(!unsampled.is_sampled());
+        assert_eq!(unsampled.tracestate(), None);
+    }
+
+    #[test]
+    fn later_versions_may_append_fields() {
+        let trace = TraceContext::parse(&format!("cc{}-extra", &TRACEPARENT[2..]), None).unwrap();
+        assert_eq!(trace.parent_id(), 0x00f067aa0ba902b7);
+        assert_eq!(trace.traceparent(), TRACEPARENT);
+    }
+
+    #[test]
+    fn invalid_traceparents_are_rejected() {
+        let invalid = [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902bx-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-1",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+        ];
+        for traceparent in invalid {
+            assert_eq!(
+                TraceContext::parse(traceparent, None),
+                Err(format!("Invalid traceparent: {}", traceparent))
+            );
+        }
+    }
+
+    #[test]
+    fn call_contexts_without_a_valid_traceparent_have_no_trace() {
+        let trace = TraceContext::from_json(&json!({
+            "traceparent": TRACEPARENT,
+            "tracestate": "vendor=value"
+        }))
+        .unwrap();
+        assert_eq!(trace.tracestate(), Some("vendor=value"));
+
+        assert_eq!(TraceContext::from_json(&json!({})), None);
+        assert_eq!(TraceContext::from_json(&json!({ "traceparent": 1 })), None);
+        assert_eq!(TraceContext::from_json(&json!({ "traceparent": "00-invalid" })), None);
+    }
+}
@@ -3,13 +3,18 @@
 //! This module provides safe wrappers around the unsafe FFI memory management
 //! operations required by the plugin API.
 
+use crate::attachment::{self, Attachment, MultipartResult};
+use crate::stream::ChunkWriter;
+use crate::PluginStatus;
 use serde_json::Value;
+use std::cell::RefCell;
 use std::mem::ManuallyDrop;
 
 /// Return a success result to the framework
 ///
 /// This handles all the unsafe memory management details:
-/// - Converts the value to JSON
+/// - Converts the value to the selected wire encoding (JSON by default,
+///   see [`crate::encoding`])
 /// - Allocates a buffer
 /// - Shrinks to minimize memory usage
 /// - Returns the pointer and capacity to the framework
@@ -33,13 +38,70 @@ use std::mem::ManuallyDrop;
 pub unsafe fn return_success(data: Value, result_buf: *mut *mut u8, result_len: *mut usize) -> i32 {
     prepare_result(data, result_buf, result_len);
 
-    0 // Success code
+    PluginStatus::Ok.code()
+}
+
+/// Serialize a typed result directly into the result buffer
+///
+/// Unlike [`return_success`], no intermediate `serde_json::Value` tree is
+/// built: `data` is written straight into the output `Vec<u8>` (with
+/// `serde_json::to_writer` for JSON, or the selected wire encoding). Use
+/// this for large results in hand-written ABI functions; tools declared with
+/// [`ToolBuilder::typed_handler`](crate::ToolBuilder::typed_handler) get it
+/// automatically.
+///
+/// Returns an error result if `data` fails to serialize.
+///
+/// # Safety
+///
+/// Same requirements as [`return_success`].
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Serialize)]
+/// struct Report { rows: Vec<Row> }
+///
+/// unsafe {
+///     let report = build_report()?;
+///     return return_serialize(&report, result_buf, result_len);
+/// }
+/// ```
+pub unsafe fn return_serialize<T: serde::Serialize + ?Sized>(
+    data: &T,
+    result_buf: *mut *mut u8,
+    result_len: *mut usize,
+) -> i32 {
+    match crate::encoding::encode(data) {
+        Ok(vec) => return_encoded(vec, result_buf, result_len),
+        Err(e) => return_error_status(
+            PluginStatus::Internal,
+            &format!("Failed to serialize result: {}", e),
+            result_buf,
+            result_len,
+        ),
+    }
+}
+
+/// Return an already encoded success result to the framework
+///
+/// `data` must be encoded with the selected wire encoding (see
+/// [`crate::encoding::encode`]).
+///
+/// # Safety
+///
+/// Same requirements as [`return_success`].
+pub unsafe fn return_encoded(data: Vec<u8>, result_buf: *mut *mut u8, result_len: *mut usize) -> i32 {
+    write_buffer(data, result_buf, result_len);
+
+    PluginStatus::Ok.code()
 }
 
 /// Return an error result to the framework
 ///
 /// This wraps the error message in a JSON object and returns it
-/// with an error code.
+/// with [`PluginStatus::HandlerError`]. Use [`return_error_status`] for a
+/// more specific status.
 ///
 /// # Safety
 ///
@@ -57,13 +119,68 @@ pub unsafe fn return_success(data: Value, result_buf: *mut *mut u8, result_len:
 /// }
 /// ```
 pub unsafe fn return_error(error: &str, result_buf: *mut *mut u8, result_len: *mut usize) -> i32 {
+    return_error_status(PluginStatus::HandlerError, error, result_buf, result_len)
+}
+
+/// Return an error result with a specific status to the framework
+///
+/// Like [`return_error`]; the message is also recorded for `last_error`.
+///
+/// # Safety
+///
+/// Same requirements as [`return_error`].
+///
+/// # Example
+///
+/// ```ignore
+/// unsafe {
+///     return return_error_status(PluginStatus::NotFound, "Unknown tool: foo", result_buf, result_len);
+/// }
+/// ```
+pub unsafe fn return_error_status(
+    status: PluginStatus,
+    error: &str,
+    result_buf: *mut *mut u8,
+    result_len: *mut usize,
+) -> i32 {
+    set_last_error(error);
+
     let error_json = serde_json::json!({
         "error": error
     });
 
     prepare_result(error_json, result_buf, result_len);
 
-    1 // Error code
+    status.code()
+}
+
+/// Return a success result with binary attachments to the framework
+///
+/// Used by the generated dispatchers. With `multipart` set (the host
+/// accepted binary attachments), the result is returned in the multipart
+/// format of [`crate::attachment`]; otherwise the attachments are inlined as
+/// base64. Without attachments this is the same as [`return_success`].
+///
+/// # Safety
+///
+/// Same requirements as [`return_success`].
+pub unsafe fn return_success_with_attachments(
+    mut data: Value,
+    attachments: Vec<Attachment>,
+    multipart: bool,
+    result_buf: *mut *mut u8,
+    result_len: *mut usize,
+) -> i32 {
+    if attachments.is_empty() {
+        return return_success(data, result_buf, result_len);
+    }
+
+    if !multipart {
+        attachment::inline_attachments(&mut data, &attachments);
+        return return_success(data, result_buf, result_len);
+    }
+
+    return_encoded(MultipartResult::encode(&data, &attachments), result_buf, result_len)
 }
 
 /// Prepare a result for return to the framework
@@ -79,8 +196,16 @@ pub unsafe fn return_error(error: &str, result_buf: *mut *mut u8, result_len: *m
 /// - These pointers remain valid for the duration of the call
 /// - The pointers are not aliased (no other mutable references exist)
 pub unsafe fn prepare_result(data: Value, result_buf: *mut *mut u8, result_len: *mut usize) {
-    let json_string = data.to_string();
-    let mut vec = json_string.into_bytes();
+    let vec = crate::encoding::encode(&data).expect("JSON values are always encodable");
+    write_buffer(vec, result_buf, result_len);
+}
+
+/// Hand a buffer over to the framework
+///
+/// # Safety
+///
+/// Same requirements as [`prepare_result`].
+unsafe fn write_buffer(mut vec: Vec<u8>, result_buf: *mut *mut u8, result_len: *mut usize) {
     vec.shrink_to_fit();
 
     *result_len = vec.capacity();
@@ -88,6 +213,67 @@ pub unsafe fn prepare_result(data: Value, result_buf: *mut *mut u8, result_len:
     let _ = ManuallyDrop::new(vec);
 }
 
+// ============================================================================
+// Error Helpers - last error and panics at the ABI boundary
+// ============================================================================
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Record the message of a failure for `last_error`
+///
+/// Called by [`return_error_status`] and by generated entry points without
+/// an output buffer. The message is kept per thread.
+pub fn set_last_error(error: impl Into<String>) {
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(error.into()));
+}
+
+/// Forget the message of the last failure on this thread
+///
+/// Called at the start of every generated entry point, so that `last_error`
+/// only reports failures of the latest call.
+pub fn clear_last_error() {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+/// The message of the last failure on this thread, if any
+pub fn last_error() -> Option<String> {
+    LAST_ERROR.with(|e| e.borrow().clone())
+}
+
+/// Standard last_error implementation
+///
+/// `declare_plugin!` uses this automatically.
+///
+/// # Safety
+///
+/// `result_buf` and `result_len` must be valid for writes.
+pub unsafe extern "C" fn standard_last_error(result_buf: *mut *mut u8, result_len: *mut usize) -> i32 {
+    match last_error() {
+        Some(error) => return_encoded(error.into_bytes(), result_buf, result_len),
+        None => {
+            *result_buf = std::ptr::null_mut();
+            *result_len = 0;
+            PluginStatus::NotFound.code()
+        }
+    }
+}
+
+/// Run `f`, catching a panic instead of unwinding across the ABI
+///
+/// Returns the panic message as error. Used by the generated functions
+/// around every handler call.
+pub fn catch_panic<R>(f: impl FnOnce() -> R) -> Result<R, String> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).map_err(|payload| {
+        payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string())
+    })
+}
+
 /// Standard free_string implementation
 ///
 /// This can be used directly in the `declare_plugin!` macro.
@@ -116,6 +302,43 @@ pub unsafe extern "C" fn standard_free_string(ptr: *mut u8, capacity: usize) {
     }
 }
 
+// ============================================================================
+// Argument Helpers - borrowed parsing for raw handlers
+// ============================================================================
+
+/// Deserialize raw tool arguments, borrowing from the host's buffer
+///
+/// `&str` and `&RawValue` fields (with `#[serde(borrow)]`) point into the
+/// original arguments instead of being copied. Strings containing escape
+/// sequences cannot be borrowed as `&str`; use `Cow<'a, str>` with
+/// `#[serde(borrow)]` for fields that may contain them.
+///
+/// # Example
+///
+/// ```
+/// use mcp_plugin_api::utils::parse_raw_args;
+/// use serde::Deserialize;
+/// use serde_json::value::RawValue;
+///
+/// #[derive(Deserialize)]
+/// struct Args<'a> {
+///     id: &'a str,
+///     #[serde(borrow)]
+///     payload: &'a RawValue,
+/// }
+///
+/// let raw: &RawValue = serde_json::from_str(r#"{"id": "a1", "payload": {"x": [1, 2]}}"#).unwrap();
+/// let args: Args = parse_raw_args(raw).unwrap();
+///
+/// assert_eq!(args.id, "a1");
+/// assert_eq!(args.payload.get(), r#"{"x": [1, 2]}"#);
+/// ```
+pub fn parse_raw_args<'a, T: serde::Deserialize<'a>>(
+    args: &'a serde_json::value::RawValue,
+) -> Result<T, String> {
+    serde_json::from_str(args.get()).map_err(|e| format!("Invalid arguments: {}", e))
+}
+
 // ============================================================================
 // Content Helpers - MCP-compliant content construction
 // ============================================================================
@@ -319,6 +542,55 @@ pub fn image_data_content(data: impl Into<String>, mime_type: Option<String>) ->
     })
 }
 
+/// Helper to create an image content response from raw bytes
+///
+/// Produces the same response as [`image_data_content`], but the bytes are
+/// only base64-encoded if the host cannot take them as a binary attachment
+/// (see [`crate::attachment`]).
+///
+/// # Example
+///
+/// ```ignore
+/// fn handle_get_chart(args: &Value) -> Result<Value, String> {
+///     let png: Vec<u8> = render_chart(args)?;
+///     Ok(image_bytes_content(png, "image/png"))
+/// }
+/// ```
+pub fn image_bytes_content(bytes: impl Into<Vec<u8>>, mime_type: impl Into<String>) -> Value {
+    let mut img = serde_json::Map::new();
+    img.insert("type".to_string(), serde_json::json!("image"));
+    img.insert("mimeType".to_string(), serde_json::json!(mime_type.into()));
+    attachment::attach(&mut img, "imageData", bytes.into());
+
+    serde_json::json!({
+        "content": [img]
+    })
+}
+
+/// Helper to create an audio content response from raw bytes
+///
+/// Creates an MCP audio content item; like [`image_bytes_content`], the
+/// bytes travel as a binary attachment when the host supports it:
+/// ```json
+/// {
+///   "content": [{
+///     "type": "audio",
+///     "data": "base64-encoded-data",
+///     "mimeType": "audio/wav"
+///   }]
+/// }
+/// ```
+pub fn audio_bytes_content(bytes: impl Into<Vec<u8>>, mime_type: impl Into<String>) -> Value {
+    let mut audio = serde_json::Map::new();
+    audio.insert("type".to_string(), serde_json::json!("audio"));
+    audio.insert("mimeType".to_string(), serde_json::json!(mime_type.into()));
+    attachment::attach(&mut audio, "data", bytes.into());
+
+    serde_json::json!({
+        "content": [audio]
+    })
+}
+
 /// Helper to create an image content response (legacy)
 ///
 /// **DEPRECATED**: Use `image_url_content` or `image_data_content` instead.
@@ -408,3 +680,196 @@ pub fn multi_content(items: Vec<Value>) -> Value {
         "content": items
     })
 }
+
+// ============================================================================
+// Streaming Helpers - chunked output for streaming handlers
+// ============================================================================
+
+/// Stream a piece of text as one chunk
+///
+/// # Example
+///
+/// ```ignore
+/// fn handle_countdown(_ctx: &ToolContext, _args: &Value, out: &mut ChunkWriter) -> Result<Value, String> {
+///     for i in (1..=3).rev() {
+///         stream_text(out, &format!("{}...\n", i))?;
+///     }
+///     stream_text(out, "Liftoff!")?;
+///     Ok(Value::Null)
+/// }
+/// ```
+pub fn stream_text(out: &mut ChunkWriter, text: &str) -> Result<(), String> {
+    out.write_chunk(text.as_bytes()).map_err(String::from)
+}
+
+/// Stream a value as one JSON-lines record
+///
+/// Writes the compact JSON encoding of `value` followed by `\n` as a
+/// single chunk, so the host can forward records without re-framing them.
+pub fn stream_json_line(out: &mut ChunkWriter, value: &Value) -> Result<(), String> {
+    let mut line = serde_json::to_vec(value).map_err(|e| e.to_string())?;
+    line.push(b'\n');
+    out.write_chunk(&line).map_err(String::from)
+}
+
+/// Stream every value of an iterator as JSON-lines records
+///
+/// Stops at the first failed write. Returns the number of records written.
+///
+/// # Example
+///
+/// ```
+/// use mcp_plugin_api::stream::ChunkWriter;
+/// use mcp_plugin_api::utils::stream_json_lines;
+/// use serde_json::json;
+///
+/// let mut out = ChunkWriter::buffered();
+/// let count = stream_json_lines(&mut out, vec![json!({"id": 1}), json!({"id": 2})]).unwrap();
+///
+/// assert_eq!(count, 2);
+/// assert_eq!(out.into_buffer().unwrap(), b"{\"id\":1}\n{\"id\":2}\n");
+/// ```
+pub fn stream_json_lines<I>(out: &mut ChunkWriter, values: I) -> Result<usize, String>
+where
+    I: IntoIterator<Item = Value>,
+{
+    let mut count = 0;
+    for value in values {
+        stream_json_line(out, &value)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+// ============================================================================
+// Resource Helpers - MCP-compliant resources/read results
+// ============================================================================
+
+/// Helper to create a text resource read result
+///
+/// Creates a standard MCP `resources/read` result with text contents:
+/// ```json
+/// {
+///   "contents": [{
+///     "uri": "docs://readme",
+///     "mimeType": "text/markdown",  // optional
+///     "text": "# README"
+///   }]
+/// }
+/// ```
+///
+/// # Example
+///
+/// ```ignore
+/// fn read_readme(uri: &str) -> Result<Value, String> {
+///     let text = std::fs::read_to_string("README.md").map_err(|e| e.to_string())?;
+///     Ok(resource_text_contents(uri, Some("text/markdown".to_string()), text))
+/// }
+/// ```
+pub fn resource_text_contents(
+    uri: impl Into<String>,
+    mime_type: Option<String>,
+    text: impl Into<String>,
+) -> Value {
+    let mut contents = serde_json::json!({
+        "uri": uri.into(),
+        "text": text.into()
+    });
+
+    if let Some(mt) = mime_type {
+        contents["mimeType"] = serde_json::json!(mt);
+    }
+
+    serde_json::json!({
+        "contents": [contents]
+    })
+}
+
+/// Helper to create a binary resource read result with base64 data
+///
+/// Creates a standard MCP `resources/read` result with blob contents:
+/// ```json
+/// {
+///   "contents": [{
+///     "uri": "catalog://products.pdf",
+///     "mimeType": "application/pdf",  // optional
+///     "blob": "base64-encoded-data"
+///   }]
+/// }
+/// ```
+pub fn resource_blob_contents(
+    uri: impl Into<String>,
+    mime_type: Option<String>,
+    blob: impl Into<String>,
+) -> Value {
+    let mut contents = serde_json::json!({
+        "uri": uri.into(),
+        "blob": blob.into()
+    });
+
+    if let Some(mt) = mime_type {
+        contents["mimeType"] = serde_json::json!(mt);
+    }
+
+    serde_json::json!({
+        "contents": [contents]
+    })
+}
+
+// ============================================================================
+// Prompt Helpers - MCP-compliant prompts/get results
+// ============================================================================
+
+/// Helper to create a prompt message with text content
+///
+/// Creates a standard MCP prompt message:
+/// ```json
+/// {
+///   "role": "user",
+///   "content": { "type": "text", "text": "..." }
+/// }
+/// ```
+///
+/// `role` is either `"user"` or `"assistant"`.
+pub fn prompt_text_message(role: &str, text: impl Into<String>) -> Value {
+    serde_json::json!({
+        "role": role,
+        "content": {
+            "type": "text",
+            "text": text.into()
+        }
+    })
+}
+
+/// Helper to create a prompts/get result
+///
+/// Creates a standard MCP `prompts/get` result:
+/// ```json
+/// {
+///   "description": "...",  // optional
+///   "messages": [ ... ]
+/// }
+/// ```
+///
+/// # Example
+///
+/// ```ignore
+/// fn handle_summarize_order(args: &HashMap<String, String>) -> Result<Value, String> {
+///     let order_id = &args["order_id"];
+///     Ok(prompt_result(
+///         Some(format!("Summary of order {}", order_id)),
+///         vec![prompt_text_message("user", format!("Summarize order {} for the customer.", order_id))],
+///     ))
+/// }
+/// ```
+pub fn prompt_result(description: Option<String>, messages: Vec<Value>) -> Value {
+    let mut result = serde_json::json!({
+        "messages": messages
+    });
+
+    if let Some(d) = description {
+        result["description"] = serde_json::json!(d);
+    }
+
+    result
+}
@@ -0,0 +1,149 @@
+//! Worker threads for calls with a deadline
+//!
+//! [`Tool::execute`](crate::Tool::execute) runs handlers with a deadline on
+//! a worker so it can return as soon as the deadline passes. Workers are
+//! reused across calls and idle ones exit after [`IDLE_TIMEOUT`]. At most
+//! [`MAX_WORKERS`] exist at a time; a handler that ignores its cancellation
+//! keeps its worker busy until it returns, so abandoned calls can never hold
+//! more threads than that. When all workers are busy, [`spawn`] hands the
+//! job back and the caller runs it itself.
+
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+/// Maximum number of worker threads
+pub(crate) const MAX_WORKERS: usize = 64;
+
+/// How long a worker waits for a new job before exiting
+pub(crate) const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub(crate) type Job = Box<dyn FnOnce() + Send + 'static>;
+
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<Job>,
+    // Workers waiting for a job
+    idle: usize,
+    // Workers alive, busy or idle
+    threads: usize,
+}
+
+struct Pool {
+    max_workers: usize,
+    queue: Mutex<Queue>,
+    available: Condvar,
+}
+
+static POOL: OnceLock<Pool> = OnceLock::new();
+
+fn pool() -> &'static Pool {
+    POOL.get_or_init(|| Pool::new(MAX_WORKERS))
+}
+
+impl Pool {
+    fn new(max_workers: usize) -> Self {
+        Pool {
+            max_workers,
+            queue: Mutex::new(Queue::default()),
+            available: Condvar::new(),
+        }
+    }
+
+    fn queue(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn work(&'static self) {
+        let mut queue = self.queue();
+        loop {
+            if let Some(job) = queue.jobs.pop_front() {
+                drop(queue);
+                // Jobs catch their own panics; keep the worker alive anyway
+                let _ = crate::utils::catch_panic(job);
+                queue = self.queue();
+                continue;
+            }
+
+            queue.idle += 1;
+            let (guard, wait) = self
+                .available
+                .wait_timeout(queue, IDLE_TIMEOUT)
+                .unwrap_or_else(|e| e.into_inner());
+            queue = guard;
+            queue.idle -= 1;
+
+            if wait.timed_out() && queue.jobs.is_empty() {
+                queue.threads -= 1;
+                return;
+            }
+        }
+    }
+
+    fn spawn(&'static self, job: Job) -> Result<(), Job> {
+        let mut queue = self.queue();
+        queue.jobs.push_back(job);
+
+        if queue.idle >= queue.jobs.len() {
+            self.available.notify_one();
+            return Ok(());
+        }
+
+        let started = queue.threads < self.max_workers
+            && std::thread::Builder::new()
+                .name("mcp-tool-worker".to_string())
+                .spawn(move || self.work())
+                .is_ok();
+        if started {
+            queue.threads += 1;
+            Ok(())
+        } else {
+            // Still ours: the lock was held since it was queued
+            Err(queue.jobs.pop_back().expect("job was queued"))
+        }
+    }
+}
+
+/// Run `job` on a worker
+///
+/// Returns the job if all [`MAX_WORKERS`] workers are busy or no thread
+/// could be started.
+pub(crate) fn spawn(job: Job) -> Result<(), Job> {
+    pool().spawn(job)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn workers_are_reused_and_capped() {
+        let pool: &'static Pool = Box::leak(Box::new(Pool::new(2)));
+
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..10 {
+            let sender = sender.clone();
+            assert!(pool.spawn(Box::new(move || sender.send(()).unwrap())).is_ok());
+            receiver.recv().unwrap();
+            while pool.queue().idle == 0 {
+                std::thread::yield_now();
+            }
+        }
+        assert_eq!(pool.queue().threads, 1);
+
+        // Keep both workers busy, so the next job is handed back
+        let (release, blocked) = mpsc::channel::<()>();
+        let blocked = std::sync::Arc::new(Mutex::new(blocked));
+        for _ in 0..2 {
+            let blocked = blocked.clone();
+            let job = move || {
+                let _ = blocked.lock().unwrap().recv();
+            };
+            assert!(pool.spawn(Box::new(job)).is_ok());
+        }
+        assert!(pool.spawn(Box::new(|| {})).is_err());
+        assert_eq!(pool.queue().threads, 2);
+        drop(release);
+    }
+}
@@ -0,0 +1,114 @@
+//! Declarations whose builders panic
+//!
+//! The generated functions build their registries on first use. A panicking
+//! builder must turn into an error status and `last_error`, never unwind
+//! into the host.
+
+use mcp_plugin_api::utils::{last_error, standard_free_string};
+use mcp_plugin_api::*;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ffi::CString;
+
+fn read_nothing(_uri: &str, _vars: &HashMap<String, String>) -> Result<Value, String> {
+    Ok(Value::Null)
+}
+
+fn handle_nothing(_args: &HashMap<String, String>) -> Result<Value, String> {
+    Ok(Value::Null)
+}
+
+fn complete_nothing(partial: &str) -> Completion {
+    Completion::from_candidates(partial, Vec::<String>::new())
+}
+
+declare_prompts! {
+    prompts: [
+        Prompt::builder("summarize", "Summarize")
+            .argument("topic", "Topic", true)
+            .complete("tone", complete_nothing)
+            .handler(handle_nothing),
+    ]
+}
+
+declare_resources! {
+    resources: [],
+    templates: [
+        ResourceTemplate::builder("product://{id", "Broken template").handler(read_nothing),
+    ]
+}
+
+declare_tools! {
+    tools: [
+        Tool::builder("get_price", "Get a price")
+            .param_string("sku", "Product SKU", true)
+            .complete("currency", complete_nothing)
+            .handler(handle_no_tool),
+    ]
+}
+
+fn handle_no_tool(_args: &Value) -> Result<Value, String> {
+    Ok(Value::Null)
+}
+
+declare_completions!(tools, prompts, resources);
+
+/// Status of a generated function, freeing its result buffer
+fn status_of(call: impl FnOnce(*mut *mut u8, *mut usize) -> i32) -> i32 {
+    let mut buf = std::ptr::null_mut();
+    let mut len = 0;
+    let status = call(&mut buf, &mut len);
+    unsafe { standard_free_string(buf, len) };
+    status
+}
+
+#[test]
+fn invalid_resources_are_reported() {
+    let internal = PluginStatus::Internal.code();
+    let uri = CString::new("product://1").unwrap();
+
+    let status = status_of(|buf, len| unsafe { generated_list_resources(buf, len) });
+    assert_eq!(status, internal);
+    let status = status_of(|buf, len| unsafe { generated_list_resource_templates(buf, len) });
+    assert_eq!(status, internal);
+    let status = status_of(|buf, len| unsafe { generated_read_resource(uri.as_ptr(), buf, len) });
+    assert_eq!(status, internal);
+
+    let error = last_error().unwrap();
+    assert!(error.starts_with("Invalid resource declaration: Unclosed '{'"), "{}", error);
+}
+
+#[test]
+fn invalid_prompts_are_reported() {
+    let internal = PluginStatus::Internal.code();
+    let name = CString::new("summarize").unwrap();
+
+    let status = status_of(|buf, len| unsafe { generated_list_prompts(buf, len) });
+    assert_eq!(status, internal);
+    let status = status_of(|buf, len| unsafe {
+        generated_get_prompt(name.as_ptr(), b"{}".as_ptr(), 2, buf, len)
+    });
+    assert_eq!(status, internal);
+
+    assert_eq!(
+        last_error().as_deref(),
+        Some("Invalid prompt declaration: Prompt 'summarize' has no argument 'tone'")
+    );
+}
+
+#[test]
+fn completion_reports_invalid_declarations() {
+    let request = br#"{
+        "ref": { "type": "ref/tool", "name": "get_price" },
+        "argument": { "name": "sku", "value": "S" }
+    }"#;
+
+    let status = status_of(|buf, len| unsafe {
+        generated_complete(request.as_ptr(), request.len(), buf, len)
+    });
+    assert_eq!(status, PluginStatus::Internal.code());
+    assert_eq!(
+        last_error().as_deref(),
+        Some("Invalid tool declaration: Tool 'get_price' has no parameter 'currency'")
+    );
+}
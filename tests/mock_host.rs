@@ -0,0 +1,104 @@
+//! Sampling and elicitation through a MockHost
+//!
+//! Host callbacks are process-wide, so a single mock host answering by
+//! message is installed for all tests in this binary.
+
+use mcp_plugin_api::host::mock::MockHost;
+use mcp_plugin_api::sampling::{SamplingMessage, SamplingParams};
+use mcp_plugin_api::{ElicitationResponse, ElicitationSchema, ToolContext, ToolError};
+use serde_json::{json, Value};
+use std::sync::Once;
+
+fn elicit(params: &Value) -> Result<Value, String> {
+    match params["message"].as_str().unwrap() {
+        "accept" => Ok(json!({
+            "action": "accept",
+            "content": { "reason": "duplicate", "requested": params["requestedSchema"] }
+        })),
+        "accept without content" => Ok(json!({ "action": "accept" })),
+        "decline" => Ok(json!({ "action": "decline" })),
+        "cancel" => Ok(json!({ "action": "cancel" })),
+        "unknown action" => Ok(json!({ "action": "shrug" })),
+        other => Err(format!("no answer for {}", other)),
+    }
+}
+
+fn install() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        MockHost::new()
+            .on_sampling(|params| {
+                Ok(json!({
+                    "role": "assistant",
+                    "content": { "type": "text", "text": params["messages"][0]["content"]["text"] },
+                    "model": "mock-model"
+                }))
+            })
+            .on_elicitation(elicit)
+            .install();
+    });
+}
+
+#[test]
+fn sampling_returns_the_host_reply() {
+    install();
+    let result = ToolContext::new()
+        .sample(vec![SamplingMessage::user("echo me")], SamplingParams::new(16))
+        .unwrap();
+    assert_eq!(result.text(), Some("echo me"));
+}
+
+#[test]
+fn accepted_elicitation_returns_the_fields() {
+    install();
+    let schema = ElicitationSchema::new().param_string("reason", "Why?", true);
+
+    match ToolContext::new().elicit("accept", &schema).unwrap() {
+        ElicitationResponse::Accept(fields) => {
+            assert_eq!(fields["reason"], "duplicate");
+            assert_eq!(fields["requested"], schema.to_json_schema());
+        }
+        other => panic!("unexpected response: {:?}", other),
+    }
+
+    let response = ToolContext::new().elicit("accept without content", &schema).unwrap();
+    assert_eq!(response, ElicitationResponse::Accept(json!({})));
+}
+
+#[test]
+fn declined_and_cancelled_elicitations_are_told_apart() {
+    install();
+    let ctx = ToolContext::new();
+    let schema = ElicitationSchema::new();
+
+    assert_eq!(ctx.elicit("decline", &schema).unwrap(), ElicitationResponse::Decline);
+    assert_eq!(ctx.elicit("cancel", &schema).unwrap(), ElicitationResponse::Cancel);
+}
+
+#[test]
+fn confirm_is_true_only_when_accepted() {
+    install();
+    let ctx = ToolContext::new();
+
+    assert!(ctx.confirm("accept").unwrap());
+    assert!(!ctx.confirm("decline").unwrap());
+    assert!(!ctx.confirm("cancel").unwrap());
+}
+
+#[test]
+fn elicitation_errors_are_reported() {
+    install();
+    let ctx = ToolContext::new();
+    let schema = ElicitationSchema::new();
+
+    match ctx.elicit("unknown action", &schema) {
+        Err(ToolError::InvalidResponse(message)) => {
+            assert_eq!(message, "Unknown elicitation action: shrug")
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match ctx.confirm("no such message") {
+        Err(ToolError::Host(message)) => assert_eq!(message, "no answer for no such message"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,38 @@
+//! MockHost replies with a binary wire encoding selected
+//!
+//! Selecting an encoding is process-wide, so this runs in its own test
+//! binary.
+
+#![cfg(feature = "msgpack")]
+
+use mcp_plugin_api::host::mock::MockHost;
+use mcp_plugin_api::sampling::{SamplingMessage, SamplingParams};
+use mcp_plugin_api::{encoding, ToolContext, ToolError, WireEncoding};
+use serde_json::json;
+
+#[test]
+fn replies_stay_json_with_msgpack_selected() {
+    encoding::set_current(WireEncoding::MessagePack).unwrap();
+    MockHost::new()
+        .on_sampling(|_| {
+            Ok(json!({
+                "role": "assistant",
+                "content": { "type": "text", "text": "positive" },
+                "model": "mock-model"
+            }))
+        })
+        .on_elicitation(|_| Err("declined by test".to_string()))
+        .install();
+
+    let ctx = ToolContext::new();
+    let result = ctx
+        .sample(vec![SamplingMessage::user("Classify")], SamplingParams::new(16))
+        .unwrap();
+    assert_eq!(result.text(), Some("positive"));
+
+    let schema = mcp_plugin_api::ElicitationSchema::new();
+    match ctx.elicit("Confirm?", &schema) {
+        Err(ToolError::Host(message)) => assert_eq!(message, "declined by test"),
+        other => panic!("unexpected elicitation result: {:?}", other),
+    }
+}
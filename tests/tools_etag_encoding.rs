@@ -0,0 +1,31 @@
+//! Tool list etag with a binary wire encoding selected
+//!
+//! Selecting an encoding is process-wide, so this runs in its own test
+//! binary.
+
+#![cfg(feature = "msgpack")]
+
+use mcp_plugin_api::registry::ToolRegistry;
+use mcp_plugin_api::{encoding, Tool, WireEncoding};
+use serde_json::Value;
+
+fn handle_noop(_args: &Value) -> Result<Value, String> {
+    Ok(Value::Null)
+}
+
+#[test]
+fn etag_changes_with_the_wire_encoding() {
+    let registry = ToolRegistry::new(vec![Tool::builder("echo", "Echo").handler(handle_noop)]);
+    let json_list = registry.list_bytes();
+    let json_etag = registry.etag();
+
+    encoding::set_current(WireEncoding::MessagePack).unwrap();
+    assert_ne!(registry.list_bytes(), json_list);
+    let msgpack_etag = registry.etag();
+    assert_ne!(msgpack_etag, json_etag);
+    assert_eq!(registry.etag(), msgpack_etag);
+
+    encoding::set_current(WireEncoding::Json).unwrap();
+    assert_eq!(registry.list_bytes(), json_list);
+    assert_eq!(registry.etag(), json_etag);
+}
@@ -0,0 +1,85 @@
+//! Spans exported to a MockHost
+//!
+//! Installing the host sets the global `tracing` subscriber, so this runs
+//! in its own test binary.
+
+#![cfg(feature = "tracing")]
+
+use mcp_plugin_api::host::mock::MockHost;
+use mcp_plugin_api::tool::ToolOutcome;
+use mcp_plugin_api::trace::{TraceContext, ToolSpan};
+use mcp_plugin_api::ToolContext;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+static EXPORTED: Mutex<Vec<Value>> = Mutex::new(Vec::new());
+
+const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+fn exported() -> Vec<Value> {
+    std::mem::take(&mut *EXPORTED.lock().unwrap())
+}
+
+/// Run a call span under `trace` that logs one event
+fn run_call(trace: Option<TraceContext>) {
+    let ctx = match trace {
+        Some(trace) => ToolContext::new().with_trace(trace),
+        None => ToolContext::new(),
+    };
+    let span = ToolSpan::new("search", &ctx);
+    span.in_scope(|| {
+        tracing::info!(key = "abc", "cache miss");
+        tracing::debug!("below the export level");
+    });
+    span.record_outcome(&ToolOutcome::Done(Err("failed".to_string())));
+}
+
+#[test]
+fn spans_are_exported_through_the_host() {
+    MockHost::new()
+        .on_trace(|record| EXPORTED.lock().unwrap().push(record.clone()))
+        .install();
+
+    // Continues the host's trace
+    let trace = TraceContext::parse(TRACEPARENT, Some("vendor=value")).unwrap();
+    run_call(Some(trace));
+    let records = exported();
+    assert_eq!(records.len(), 1);
+    let span = &records[0];
+    assert_eq!(span["kind"], "span");
+    assert_eq!(span["name"], "execute_tool");
+    assert_eq!(span["level"], "INFO");
+    assert_eq!(span["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(span["parentSpanId"], "00f067aa0ba902b7");
+    assert_eq!(span["traceState"], "vendor=value");
+    assert_eq!(span["attributes"], json!({ "tool": "search", "status": "error" }));
+    assert_eq!(span["spanId"].as_str().unwrap().len(), 16);
+    assert!(span["startTimeUnixNano"].as_u64() <= span["endTimeUnixNano"].as_u64());
+
+    let events = span["events"].as_array().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["name"], "cache miss");
+    assert_eq!(events[0]["level"], "INFO");
+    assert_eq!(events[0]["attributes"], json!({ "key": "abc" }));
+
+    // Starts a new trace
+    run_call(None);
+    let records = exported();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["parentSpanId"], Value::Null);
+    assert_ne!(records[0]["traceId"], span["traceId"]);
+
+    // Traces the host did not sample are dropped
+    let unsampled = TraceContext::parse(&TRACEPARENT.replace("-01", "-00"), None).unwrap();
+    run_call(Some(unsampled));
+    assert_eq!(exported(), Vec::<Value>::new());
+
+    // Events outside of spans are shipped on their own
+    tracing::warn!(attempt = 2, "retrying");
+    let records = exported();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["kind"], "event");
+    assert_eq!(records[0]["name"], "retrying");
+    assert_eq!(records[0]["level"], "WARN");
+    assert_eq!(records[0]["attributes"], json!({ "attempt": 2 }));
+}